@@ -150,6 +150,21 @@ impl epi::App for App {
                     } else {
                         "-".to_owned()
                     }
+                })
+                .column("Duration", |sample| {
+                    if let Some(duration) = sample.duration() {
+                        format!("{:.2}s", duration.as_secs_f64())
+                    } else {
+                        "-".to_owned()
+                    }
+                })
+                .column("Format", |sample| {
+                    match (sample.sample_rate(), sample.bit_depth(), sample.channels()) {
+                        (Some(rate), Some(bits), Some(channels)) => {
+                            format!("{} Hz, {}-bit, {} ch", rate, bits, channels)
+                        }
+                        _ => "-".to_owned(),
+                    }
                 }),
             );
         });
@@ -0,0 +1,265 @@
+use super::{read_pstring, write_pstring};
+use crate::{
+    error::{Error, Result},
+    pcm::{Encoding, SampleType},
+    wav::SampleFormat,
+};
+use std::convert::TryInto;
+
+/// Common (`COMM`) chunk, describing how the sound data is encoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommonChunk {
+    channels: u16,
+    frame_count: u32,
+    sample_size: u16,
+    sample_rate: f64,
+
+    /// Compression type and its human-readable name. Only present in AIFF-C
+    /// files.
+    compression: Option<([u8; 4], Vec<u8>)>,
+}
+
+impl CommonChunk {
+    pub(crate) const ID: &[u8; 4] = b"COMM";
+    const LEN: usize = 18;
+
+    pub const COMPRESSION_NONE: &[u8; 4] = b"NONE";
+    /// Little-endian integer samples.
+    pub const COMPRESSION_SOWT: &[u8; 4] = b"sowt";
+    pub const COMPRESSION_FLOAT32: &[u8; 4] = b"fl32";
+    pub const COMPRESSION_FLOAT64: &[u8; 4] = b"fl64";
+
+    /// Create a common chunk for uncompressed big-endian integer samples.
+    pub fn new(channels: u16, sample_rate: u32, sample_size: u16) -> Self {
+        Self {
+            channels,
+            frame_count: 0,
+            sample_size,
+            sample_rate: sample_rate as f64,
+            compression: None,
+        }
+    }
+
+    /// Parse a common chunk from the chunk contents, not including the chunk
+    /// ID and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::LEN {
+            return Err(Error::invalid_chunk(b"COMM"));
+        }
+
+        let compression = if bytes.len() >= Self::LEN + 4 {
+            let compression_type = bytes[18..22].try_into().unwrap();
+            let name = read_pstring(&bytes[22..]).0.to_vec();
+
+            Some((compression_type, name))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            channels: u16::from_be_bytes([bytes[0], bytes[1]]),
+            frame_count: u32::from_be_bytes(bytes[2..6].try_into().unwrap()),
+            sample_size: u16::from_be_bytes([bytes[6], bytes[7]]),
+            sample_rate: read_extended(bytes[8..18].try_into().unwrap()),
+            compression,
+        })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN);
+
+        bytes.extend_from_slice(&self.channels.to_be_bytes());
+        bytes.extend_from_slice(&self.frame_count.to_be_bytes());
+        bytes.extend_from_slice(&self.sample_size.to_be_bytes());
+        bytes.extend_from_slice(&write_extended(self.sample_rate));
+
+        if let Some((compression_type, name)) = self.compression.as_ref() {
+            bytes.extend_from_slice(compression_type);
+            write_pstring(&mut bytes, name);
+        }
+
+        bytes
+    }
+
+    /// Whether the chunk comes from an AIFF-C file, and so has a compression
+    /// type.
+    pub fn is_aifc(&self) -> bool {
+        self.compression.is_some()
+    }
+
+    /// The AIFF-C compression type, if any.
+    pub fn compression_type(&self) -> Option<&[u8; 4]> {
+        self.compression
+            .as_ref()
+            .map(|(compression_type, _)| compression_type)
+    }
+
+    /// Set the AIFF-C compression type along with a human-readable name for
+    /// it, which makes this an AIFF-C chunk.
+    pub fn set_compression(&mut self, compression_type: &[u8; 4], name: &str) {
+        self.compression = Some((*compression_type, name.as_bytes().to_vec()));
+    }
+
+    /// The encoding of the samples, if it is one that is understood.
+    pub fn sample_format(&self) -> Option<SampleFormat> {
+        match self.compression_type() {
+            None | Some(b"NONE") | Some(b"twos") | Some(b"sowt") => Some(SampleFormat::Pcm),
+            Some(b"fl32") | Some(b"FL32") | Some(b"fl64") | Some(b"FL64") => {
+                Some(SampleFormat::IeeeFloat)
+            }
+            Some(b"alaw") | Some(b"ALAW") => Some(SampleFormat::ALaw),
+            Some(b"ulaw") | Some(b"ULAW") => Some(SampleFormat::MuLaw),
+            Some(_) => None,
+        }
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Number of sample frames in the sound data.
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    pub fn set_frame_count(&mut self, frame_count: u32) {
+        self.frame_count = frame_count;
+    }
+
+    /// Number of significant bits in each sample.
+    pub fn sample_size(&self) -> u16 {
+        self.sample_size
+    }
+
+    /// Number of sample frames per second.
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Size of a single sample frame in bytes, across all channels.
+    pub fn block_align(&self) -> u32 {
+        self.channels as u32 * self.sample_bytes() as u32
+    }
+
+    /// Samples are stored in the smallest whole number of bytes that fits
+    /// the sample size, or 4 or 8 bytes for floating point.
+    fn sample_bytes(&self) -> usize {
+        match self.compression_type() {
+            Some(b"fl32") | Some(b"FL32") => 4,
+            Some(b"fl64") | Some(b"FL64") => 8,
+            _ => (self.sample_size as usize).div_ceil(8),
+        }
+    }
+
+    /// Get the encoding of samples in the sound data, or an error if the
+    /// compression type isn't supported.
+    pub(crate) fn encoding(&self) -> Result<Encoding> {
+        let bytes = self.sample_bytes();
+        let bits = self.sample_size as u32;
+
+        let (sample_type, big_endian) = match self.compression_type() {
+            None | Some(b"NONE") | Some(b"twos") if (1..=32).contains(&bits) => {
+                (SampleType::Int { bytes, bits }, true)
+            }
+            // 8-bit samples are signed in AIFF, which is what big-endian
+            // encodings use.
+            Some(b"sowt") if (1..=32).contains(&bits) => {
+                (SampleType::Int { bytes, bits }, bytes == 1)
+            }
+            Some(b"fl32") | Some(b"FL32") => (SampleType::Float32, true),
+            Some(b"fl64") | Some(b"FL64") => (SampleType::Float64, true),
+            compression_type => {
+                return Err(Error::Unsupported(format!(
+                    "unsupported AIFF compression: {}",
+                    compression_type
+                        .map(|c| String::from_utf8_lossy(c).into_owned())
+                        .unwrap_or_else(|| format!("{}-bit samples", bits))
+                )))
+            }
+        };
+
+        if self.channels == 0 {
+            return Err(Error::invalid_chunk(b"COMM"));
+        }
+
+        Ok(Encoding {
+            sample_type,
+            big_endian,
+        })
+    }
+}
+
+/// Read an 80-bit IEEE 754 extended precision number.
+fn read_extended(bytes: [u8; 10]) -> f64 {
+    let sign = if bytes[0] & 0x80 != 0 { -1.0 } else { 1.0 };
+    let exponent = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7fff) as i32;
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+
+    sign * mantissa as f64 * 2f64.powi(exponent - 16383 - 63)
+}
+
+/// Write an 80-bit IEEE 754 extended precision number. Only finite, normal
+/// numbers and zero are supported, which covers all sensible sample rates.
+fn write_extended(value: f64) -> [u8; 10] {
+    let mut bytes = [0; 10];
+
+    if value == 0.0 || !value.is_normal() {
+        return bytes;
+    }
+
+    let bits = value.to_bits();
+    let sign = ((bits >> 63) as u16) << 15;
+    let exponent = ((bits >> 52) & 0x7ff) as u16 - 1023 + 16383;
+    let mantissa = ((bits & ((1 << 52) - 1)) | (1 << 52)) << 11;
+
+    bytes[..2].copy_from_slice(&(sign | exponent).to_be_bytes());
+    bytes[2..].copy_from_slice(&mantissa.to_be_bytes());
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extended_round_trip() {
+        let bytes = [0x40, 0x0e, 0xac, 0x44, 0, 0, 0, 0, 0, 0];
+        assert_eq!(read_extended(bytes), 44100.0);
+        assert_eq!(write_extended(44100.0), bytes);
+
+        for value in &[0.0, 1.0, 8000.0, 48000.0, 96000.0, 22050.5, -3.25] {
+            assert_eq!(read_extended(write_extended(*value)), *value);
+        }
+    }
+
+    #[test]
+    fn common_chunk_round_trip() {
+        let mut chunk = CommonChunk::new(2, 48000, 24);
+        chunk.set_frame_count(100);
+        let bytes = chunk.to_bytes();
+        assert_eq!(bytes.len(), 18);
+        assert_eq!(CommonChunk::from_bytes(&bytes).unwrap(), chunk);
+        assert_eq!(chunk.block_align(), 6);
+        assert!(chunk.encoding().unwrap().big_endian);
+
+        chunk.set_compression(CommonChunk::COMPRESSION_FLOAT32, "32-bit floating point");
+        let bytes = chunk.to_bytes();
+        assert_eq!(bytes.len(), 18 + 4 + 22);
+        assert_eq!(CommonChunk::from_bytes(&bytes).unwrap(), chunk);
+        assert_eq!(chunk.sample_format(), Some(SampleFormat::IeeeFloat));
+        assert_eq!(chunk.block_align(), 8);
+
+        chunk.set_compression(b"ima4", "IMA 4:1");
+        assert_eq!(chunk.sample_format(), None);
+        assert!(chunk.encoding().is_err());
+    }
+}
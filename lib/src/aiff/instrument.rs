@@ -0,0 +1,242 @@
+use crate::{
+    error::{Error, Result},
+    midi,
+};
+use std::{convert::TryInto, fmt};
+
+/// Instrument (`INST`) chunk, describing how a sound should be played back by
+/// a sampler, including its sustain and release loops.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstrumentChunk {
+    base_note: u8,
+    detune: i8,
+    low_note: u8,
+    high_note: u8,
+    low_velocity: u8,
+    high_velocity: u8,
+    gain: i16,
+    pub sustain_loop: Loop,
+    pub release_loop: Loop,
+}
+
+impl Default for InstrumentChunk {
+    fn default() -> Self {
+        Self {
+            base_note: 60,
+            detune: 0,
+            low_note: 0,
+            high_note: 127,
+            low_velocity: 1,
+            high_velocity: 127,
+            gain: 0,
+            sustain_loop: Loop::default(),
+            release_loop: Loop::default(),
+        }
+    }
+}
+
+impl InstrumentChunk {
+    pub(crate) const ID: &[u8; 4] = b"INST";
+    const LEN: usize = 20;
+
+    /// Parse an instrument chunk from the chunk contents, not including the
+    /// chunk ID and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::LEN {
+            return Err(Error::invalid_chunk(b"INST"));
+        }
+
+        Ok(Self {
+            base_note: bytes[0],
+            detune: bytes[1] as i8,
+            low_note: bytes[2],
+            high_note: bytes[3],
+            low_velocity: bytes[4],
+            high_velocity: bytes[5],
+            gain: i16::from_be_bytes([bytes[6], bytes[7]]),
+            sustain_loop: Loop::from_bytes(bytes[8..14].try_into().unwrap()),
+            release_loop: Loop::from_bytes(bytes[14..20].try_into().unwrap()),
+        })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![
+            self.base_note,
+            self.detune as u8,
+            self.low_note,
+            self.high_note,
+            self.low_velocity,
+            self.high_velocity,
+        ];
+
+        bytes.extend_from_slice(&self.gain.to_be_bytes());
+        bytes.extend_from_slice(&self.sustain_loop.to_bytes());
+        bytes.extend_from_slice(&self.release_loop.to_bytes());
+        bytes
+    }
+
+    /// The note at which the sound plays back at its original pitch.
+    pub fn base_note(&self) -> midi::Note {
+        self.base_note.into()
+    }
+
+    pub fn set_base_note(&mut self, note: midi::Note) {
+        self.base_note = note.into();
+    }
+
+    /// Pitch adjustment in cents, from -50 to 50.
+    pub fn detune(&self) -> i8 {
+        self.detune
+    }
+
+    pub fn set_detune(&mut self, detune: i8) {
+        self.detune = detune.clamp(-50, 50);
+    }
+
+    pub fn low_note(&self) -> midi::Note {
+        self.low_note.into()
+    }
+
+    pub fn set_low_note(&mut self, note: midi::Note) {
+        self.low_note = note.into();
+    }
+
+    pub fn high_note(&self) -> midi::Note {
+        self.high_note.into()
+    }
+
+    pub fn set_high_note(&mut self, note: midi::Note) {
+        self.high_note = note.into();
+    }
+
+    pub fn low_velocity(&self) -> u8 {
+        self.low_velocity
+    }
+
+    pub fn set_low_velocity(&mut self, velocity: u8) {
+        self.low_velocity = velocity;
+    }
+
+    pub fn high_velocity(&self) -> u8 {
+        self.high_velocity
+    }
+
+    pub fn set_high_velocity(&mut self, velocity: u8) {
+        self.high_velocity = velocity;
+    }
+
+    /// Playback gain in decibels.
+    pub fn gain(&self) -> i16 {
+        self.gain
+    }
+
+    pub fn set_gain(&mut self, gain: i16) {
+        self.gain = gain;
+    }
+}
+
+/// A loop inside an instrument chunk. The start and end of the loop are given
+/// by the IDs of markers in the marker chunk, and the end marker is the first
+/// frame after the loop.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Loop {
+    pub play_mode: PlayMode,
+    pub begin_marker_id: u16,
+    pub end_marker_id: u16,
+}
+
+impl Loop {
+    fn from_bytes(bytes: [u8; 6]) -> Self {
+        Self {
+            play_mode: u16::from_be_bytes([bytes[0], bytes[1]]).into(),
+            begin_marker_id: u16::from_be_bytes([bytes[2], bytes[3]]),
+            end_marker_id: u16::from_be_bytes([bytes[4], bytes[5]]),
+        }
+    }
+
+    fn to_bytes(&self) -> [u8; 6] {
+        let mut bytes = [0; 6];
+        bytes[..2].copy_from_slice(&u16::from(self.play_mode).to_be_bytes());
+        bytes[2..4].copy_from_slice(&self.begin_marker_id.to_be_bytes());
+        bytes[4..].copy_from_slice(&self.end_marker_id.to_be_bytes());
+        bytes
+    }
+
+    /// Whether the loop is played at all.
+    pub fn is_enabled(&self) -> bool {
+        self.play_mode != PlayMode::NoLooping
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PlayMode {
+    #[default]
+    NoLooping,
+    Forward,
+    ForwardBackward,
+    Other(u16),
+}
+
+impl From<u16> for PlayMode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Self::NoLooping,
+            1 => Self::Forward,
+            2 => Self::ForwardBackward,
+            value => Self::Other(value),
+        }
+    }
+}
+
+impl From<PlayMode> for u16 {
+    fn from(mode: PlayMode) -> Self {
+        match mode {
+            PlayMode::NoLooping => 0,
+            PlayMode::Forward => 1,
+            PlayMode::ForwardBackward => 2,
+            PlayMode::Other(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for PlayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoLooping => f.write_str("no looping"),
+            Self::Forward => f.write_str("forward"),
+            Self::ForwardBackward => f.write_str("forward/backward"),
+            Self::Other(value) => write!(f, "other ({})", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instrument_chunk_round_trip() {
+        let bytes = [
+            57, 0xf6, 36, 72, 1, 100, 0xff, 0xfa, 0, 1, 0, 1, 0, 2, 0, 0, 0, 0, 0, 0,
+        ];
+        let chunk = InstrumentChunk::from_bytes(&bytes).unwrap();
+
+        assert_eq!(u8::from(chunk.base_note()), 57);
+        assert_eq!(chunk.detune(), -10);
+        assert_eq!(u8::from(chunk.low_note()), 36);
+        assert_eq!(u8::from(chunk.high_note()), 72);
+        assert_eq!(chunk.high_velocity(), 100);
+        assert_eq!(chunk.gain(), -6);
+        assert_eq!(
+            chunk.sustain_loop,
+            Loop {
+                play_mode: PlayMode::Forward,
+                begin_marker_id: 1,
+                end_marker_id: 2,
+            }
+        );
+        assert!(!chunk.release_loop.is_enabled());
+        assert_eq!(chunk.to_bytes(), bytes);
+    }
+}
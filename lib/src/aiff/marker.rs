@@ -0,0 +1,125 @@
+use super::{decode_text, read_pstring, write_pstring};
+use crate::error::{Error, Result};
+use std::convert::TryInto;
+
+/// Marker (`MARK`) chunk, containing named positions in the sound data.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MarkerChunk {
+    markers: Vec<Marker>,
+}
+
+/// A single named position in the sound data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Marker {
+    /// Unique ID of the marker, which must be greater than 0.
+    pub id: u16,
+    /// Position of the marker in sample frames. A marker sits between two
+    /// frames, so 0 is before the first frame.
+    pub position: u32,
+    pub name: String,
+}
+
+impl MarkerChunk {
+    pub(crate) const ID: &[u8; 4] = b"MARK";
+
+    /// Parse a marker chunk from the chunk contents, not including the chunk
+    /// ID and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || Error::invalid_chunk(b"MARK");
+
+        if bytes.len() < 2 {
+            return Err(invalid());
+        }
+
+        let count = u16::from_be_bytes([bytes[0], bytes[1]]);
+        let mut markers = Vec::with_capacity(count as usize);
+        let mut offset = 2;
+
+        for _ in 0..count {
+            if bytes.len() < offset + 7 {
+                return Err(invalid());
+            }
+
+            let (name, len) = read_pstring(&bytes[offset + 6..]);
+
+            markers.push(Marker {
+                id: u16::from_be_bytes([bytes[offset], bytes[offset + 1]]),
+                position: u32::from_be_bytes(bytes[offset + 2..offset + 6].try_into().unwrap()),
+                name: decode_text(name),
+            });
+
+            offset += 6 + len;
+        }
+
+        Ok(Self { markers })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(&(self.markers.len() as u16).to_be_bytes());
+
+        for marker in &self.markers {
+            bytes.extend_from_slice(&marker.id.to_be_bytes());
+            bytes.extend_from_slice(&marker.position.to_be_bytes());
+            write_pstring(&mut bytes, marker.name.as_bytes());
+        }
+
+        bytes
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    pub fn markers_mut(&mut self) -> &mut Vec<Marker> {
+        &mut self.markers
+    }
+
+    pub fn get(&self, id: u16) -> Option<&Marker> {
+        self.markers.iter().find(|marker| marker.id == id)
+    }
+
+    /// Add a marker with an unused ID, returning the ID.
+    pub fn add(&mut self, position: u32, name: &str) -> u16 {
+        let id = self
+            .markers
+            .iter()
+            .map(|marker| marker.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        self.markers.push(Marker {
+            id,
+            position,
+            name: name.to_owned(),
+        });
+
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marker_chunk_round_trip() {
+        let mut chunk = MarkerChunk::default();
+        assert_eq!(chunk.add(100, "Start"), 1);
+        assert_eq!(chunk.add(2000, "Loop"), 2);
+
+        let bytes = chunk.to_bytes();
+        // Names are padded so that each one takes an even number of bytes.
+        assert_eq!(bytes.len(), 2 + (6 + 6) + (6 + 6));
+
+        let chunk = MarkerChunk::from_bytes(&bytes).unwrap();
+        assert_eq!(chunk.get(2).unwrap().position, 2000);
+        assert_eq!(chunk.get(1).unwrap().name, "Start");
+        assert_eq!(chunk.to_bytes(), bytes);
+
+        assert!(MarkerChunk::from_bytes(&bytes[..10]).is_err());
+    }
+}
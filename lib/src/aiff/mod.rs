@@ -0,0 +1,442 @@
+//! AIFF and AIFF-C format reading and writing routines.
+
+use std::{
+    convert::TryInto,
+    io::{BufReader, Read, Seek, SeekFrom, Write},
+    str,
+};
+
+mod common;
+mod instrument;
+mod marker;
+mod writer;
+
+use crate::error::{Error, Result};
+
+pub use crate::pcm::FrameReader;
+
+pub use self::{
+    common::CommonChunk,
+    instrument::{InstrumentChunk, Loop, PlayMode},
+    marker::{Marker, MarkerChunk},
+    writer::AiffWriter,
+};
+
+/// ID of the chunk holding the name of the sound.
+pub const NAME: &[u8; 4] = b"NAME";
+/// ID of the chunk holding the author of the sound.
+pub const AUTHOR: &[u8; 4] = b"AUTH";
+/// ID of the chunk holding a copyright notice.
+pub const COPYRIGHT: &[u8; 4] = b"(c) ";
+/// ID of the chunks holding comments. There can be more than one.
+pub const ANNOTATION: &[u8; 4] = b"ANNO";
+
+/// ID of the chunk holding the audio data.
+const SOUND_DATA: &[u8; 4] = b"SSND";
+
+/// Chunk used to fill space left over when a chunk is replaced. This is the
+/// same chunk used by Apple's own software.
+const FILLER: &[u8; 4] = b"FLLR";
+
+/// Check whether the start of a file looks like an AIFF or AIFF-C file.
+pub fn is_aiff(header: &[u8]) -> bool {
+    header.len() >= 12 && &header[..4] == b"FORM" && matches!(&header[8..12], b"AIFF" | b"AIFC")
+}
+
+pub struct Aiff<F> {
+    file: F,
+    form_len: u32,
+    aifc: bool,
+}
+
+/// Location of a chunk in the file.
+#[derive(Copy, Clone, Debug)]
+struct Chunk {
+    id: [u8; 4],
+    offset: u64,
+    len: u32,
+}
+
+impl<F: Read + Seek> Aiff<F> {
+    pub fn new(mut file: F) -> Result<Self> {
+        let mut header = [0; 12];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)
+            .map_err(Error::header("AIFF"))?;
+
+        if !is_aiff(&header) {
+            return Err(Error::NotRecognized { format: "AIFF" });
+        }
+
+        Ok(Self {
+            file,
+            form_len: u32::from_be_bytes(header[4..8].try_into().unwrap()),
+            aifc: &header[8..12] == b"AIFC",
+        })
+    }
+
+    /// Whether the file is an AIFF-C file rather than a plain AIFF file.
+    pub fn is_aifc(&self) -> bool {
+        self.aifc
+    }
+
+    pub fn get_common_chunk(&mut self) -> Result<Option<CommonChunk>> {
+        self.parse_chunk(CommonChunk::ID, CommonChunk::from_bytes)
+    }
+
+    pub fn get_instrument_chunk(&mut self) -> Result<Option<InstrumentChunk>> {
+        self.parse_chunk(InstrumentChunk::ID, InstrumentChunk::from_bytes)
+    }
+
+    pub fn get_marker_chunk(&mut self) -> Result<Option<MarkerChunk>> {
+        self.parse_chunk(MarkerChunk::ID, MarkerChunk::from_bytes)
+    }
+
+    /// Get the text of the first text chunk with the given ID, such as
+    /// [`NAME`].
+    pub fn get_text(&mut self, id: &[u8; 4]) -> Result<Option<String>> {
+        Ok(self.read_chunk(id)?.map(|data| decode_text(&data)))
+    }
+
+    /// Get the text of every annotation chunk.
+    pub fn get_annotations(&mut self) -> Result<Vec<String>> {
+        let chunks = self.chunks()?;
+        let mut annotations = Vec::new();
+
+        for chunk in chunks.iter().filter(|chunk| &chunk.id == ANNOTATION) {
+            annotations.push(decode_text(&self.read_contents(chunk)?));
+        }
+
+        Ok(annotations)
+    }
+
+    /// Get a reader that streams sample frames from the sound data, decoded
+    /// according to the common chunk.
+    pub fn frames(&mut self) -> Result<FrameReader<impl Read + '_>> {
+        let common = self
+            .get_common_chunk()?
+            .ok_or(Error::MissingChunk { id: *b"COMM" })?;
+        let encoding = common.encoding()?;
+
+        let (start, len) = match self.find_chunk(SOUND_DATA)? {
+            Some(chunk) if chunk.len >= 8 => {
+                let mut offset = [0; 4];
+                self.file.seek(SeekFrom::Start(chunk.offset + 8))?;
+                self.file.read_exact(&mut offset)?;
+
+                let offset = u32::from_be_bytes(offset).min(chunk.len - 8);
+                (chunk.offset + 16 + offset as u64, chunk.len - 8 - offset)
+            }
+            // The sound data chunk may be left out if there are no frames.
+            _ if common.frame_count() == 0 => (0, 0),
+            _ => return Err(Error::MissingChunk { id: *b"SSND" }),
+        };
+
+        let frames = (common.frame_count() as u64).min(len as u64 / common.block_align() as u64);
+
+        self.file.seek(SeekFrom::Start(start))?;
+
+        Ok(FrameReader::new(
+            BufReader::new((&mut self.file).take(len as u64)),
+            encoding,
+            common.channels() as usize,
+            common.sample_rate().round() as u32,
+            frames,
+        ))
+    }
+
+    /// Get the IDs of all chunks inside the form, in the order they appear.
+    pub fn chunk_ids(&mut self) -> Result<Vec<[u8; 4]>> {
+        Ok(self.chunks()?.into_iter().map(|chunk| chunk.id).collect())
+    }
+
+    /// Read the contents of the first chunk with the given ID.
+    /// Parse the contents of the first chunk with the given ID, adding the
+    /// offset of the chunk to any error.
+    fn parse_chunk<T>(
+        &mut self,
+        id: &[u8; 4],
+        parse: impl FnOnce(&[u8]) -> Result<T>,
+    ) -> Result<Option<T>> {
+        match self.find_chunk(id)? {
+            Some(chunk) => parse(&self.read_contents(&chunk)?)
+                .map(Some)
+                .map_err(|e| e.at_offset(chunk.offset)),
+            None => Ok(None),
+        }
+    }
+
+    fn read_chunk(&mut self, id: &[u8; 4]) -> Result<Option<Vec<u8>>> {
+        match self.find_chunk(id)? {
+            Some(chunk) => self.read_contents(&chunk).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn read_contents(&mut self, chunk: &Chunk) -> Result<Vec<u8>> {
+        let mut data = vec![0; chunk.len as usize];
+        self.file.seek(SeekFrom::Start(chunk.offset + 8))?;
+        self.file.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    fn find_chunk(&mut self, id: &[u8; 4]) -> Result<Option<Chunk>> {
+        Ok(self.chunks()?.into_iter().find(|chunk| &chunk.id == id))
+    }
+
+    /// List the chunks inside the form. A chunk header cut short by the end
+    /// of the form or file ends the list.
+    fn chunks(&mut self) -> Result<Vec<Chunk>> {
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+        let end = (8 + self.form_len as u64).min(file_len);
+        let mut offset = 12;
+        let mut chunks = Vec::new();
+
+        while offset + 8 <= end {
+            let mut header = [0; 8];
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.read_exact(&mut header)?;
+
+            let chunk = Chunk {
+                id: header[..4].try_into().unwrap(),
+                offset,
+                len: u32::from_be_bytes(header[4..].try_into().unwrap()),
+            };
+
+            offset += 8 + padded_len(chunk.len as u64);
+            chunks.push(chunk);
+        }
+
+        Ok(chunks)
+    }
+}
+
+impl<F: Read + Write + Seek> Aiff<F> {
+    pub fn update_instrument_chunk(&mut self, f: impl FnOnce(&mut InstrumentChunk)) -> Result<()> {
+        let mut chunk = self.get_instrument_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+        self.write_chunk(InstrumentChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn update_marker_chunk(&mut self, f: impl FnOnce(&mut MarkerChunk)) -> Result<()> {
+        let mut chunk = self.get_marker_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+        self.write_chunk(MarkerChunk::ID, &chunk.to_bytes())
+    }
+
+    /// Set the text of the first text chunk with the given ID, or remove it if
+    /// the text is `None`.
+    pub fn set_text(&mut self, id: &[u8; 4], text: Option<&str>) -> Result<()> {
+        match text {
+            Some(text) => self.write_chunk(id, text.as_bytes()),
+            None => {
+                if let Some(chunk) = self.find_chunk(id)? {
+                    self.file.seek(SeekFrom::Start(chunk.offset))?;
+                    self.file.write_all(FILLER)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Replace every annotation chunk with a single one holding the given
+    /// text, or remove them all if the text is `None`.
+    pub fn set_annotation(&mut self, text: Option<&str>) -> Result<()> {
+        let chunks = self.chunks()?;
+
+        for chunk in chunks
+            .iter()
+            .filter(|chunk| &chunk.id == ANNOTATION)
+            .skip(1)
+        {
+            self.file.seek(SeekFrom::Start(chunk.offset))?;
+            self.file.write_all(FILLER)?;
+        }
+
+        self.set_text(ANNOTATION, text)
+    }
+
+    /// Write a chunk, replacing the first existing chunk with the same ID.
+    ///
+    /// As with WAV files the sound data is never moved. If the new contents
+    /// fit in the space of the old chunk then they are written in place and
+    /// any leftover space is filled with a filler chunk. Otherwise the old
+    /// chunk is turned into a filler chunk and the new one is appended.
+    fn write_chunk(&mut self, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        let new_size = padded_len(data.len() as u64);
+
+        if let Some(existing) = self.find_chunk(id)? {
+            let old_size = padded_len(existing.len as u64);
+
+            if old_size == new_size || old_size >= new_size + 8 {
+                self.file.seek(SeekFrom::Start(existing.offset))?;
+                write_chunk_to(&mut self.file, id, data)?;
+
+                if old_size > new_size {
+                    write_chunk_to(
+                        &mut self.file,
+                        FILLER,
+                        &vec![0; (old_size - new_size - 8) as usize],
+                    )?;
+                }
+
+                return Ok(());
+            }
+
+            self.file.seek(SeekFrom::Start(existing.offset))?;
+            self.file.write_all(FILLER)?;
+        }
+
+        let form_len: u32 = (self.form_len as u64 + 8 + new_size)
+            .try_into()
+            .map_err(|_| Error::TooLarge("AIFF file too large"))?;
+
+        self.file.seek(SeekFrom::Start(8 + self.form_len as u64))?;
+        write_chunk_to(&mut self.file, id, data)?;
+
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&form_len.to_be_bytes())?;
+        self.form_len = form_len;
+
+        Ok(())
+    }
+}
+
+/// Write a complete chunk including its header and a trailing pad byte if the
+/// data has an odd length.
+fn write_chunk_to(mut writer: impl Write, id: &[u8; 4], data: &[u8]) -> Result<()> {
+    let len: u32 = data
+        .len()
+        .try_into()
+        .map_err(|_| Error::TooLarge("chunk too large"))?;
+
+    writer.write_all(id)?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(data)?;
+
+    if data.len() % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+
+    Ok(())
+}
+
+/// Read a Pascal-style string, returning its contents and the number of bytes
+/// it takes up including the count byte and padding.
+fn read_pstring(bytes: &[u8]) -> (&[u8], usize) {
+    let len = match bytes.first() {
+        Some(len) => (*len as usize).min(bytes.len() - 1),
+        None => return (&[], 0),
+    };
+
+    (&bytes[1..1 + len], padded_len(1 + len as u64) as usize)
+}
+
+/// Write a Pascal-style string, cut short to 255 bytes and padded so that it
+/// takes up an even number of bytes.
+fn write_pstring(out: &mut Vec<u8>, text: &[u8]) {
+    let text = &text[..text.len().min(255)];
+
+    out.push(text.len() as u8);
+    out.extend_from_slice(text);
+
+    if text.len().is_multiple_of(2) {
+        out.push(0);
+    }
+}
+
+/// Decode text, which is usually ASCII but may be in an older Mac encoding.
+/// Anything that isn't UTF-8 is read as Latin-1.
+fn decode_text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+
+    match str::from_utf8(&bytes[..end]) {
+        Ok(s) => s.to_owned(),
+        Err(_) => bytes[..end].iter().map(|b| *b as char).collect(),
+    }
+}
+
+/// Chunks are aligned to 2-byte boundaries.
+fn padded_len(len: u64) -> u64 {
+    len + len % 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::Note;
+    use std::io::Cursor;
+
+    fn aiff_file() -> Vec<u8> {
+        let mut common = CommonChunk::new(1, 44100, 16);
+        common.set_frame_count(2);
+
+        let mut body = b"AIFF".to_vec();
+        write_chunk_to(&mut body, CommonChunk::ID, &common.to_bytes()).unwrap();
+        write_chunk_to(&mut body, NAME, b"Bell").unwrap();
+        write_chunk_to(&mut body, ANNOTATION, b"One").unwrap();
+        write_chunk_to(&mut body, ANNOTATION, b"Two").unwrap();
+        write_chunk_to(
+            &mut body,
+            SOUND_DATA,
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0x40, 0x00, 0xc0, 0x00],
+        )
+        .unwrap();
+
+        let mut file = b"FORM".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        file.extend_from_slice(&body);
+        file
+    }
+
+    #[test]
+    fn read_aiff() {
+        let mut aiff = Aiff::new(Cursor::new(aiff_file())).unwrap();
+
+        assert!(!aiff.is_aifc());
+        assert_eq!(
+            aiff.get_common_chunk().unwrap().unwrap().sample_rate(),
+            44100.0
+        );
+        assert_eq!(aiff.get_text(NAME).unwrap().as_deref(), Some("Bell"));
+        assert_eq!(aiff.get_text(AUTHOR).unwrap(), None);
+        assert_eq!(aiff.get_annotations().unwrap(), vec!["One", "Two"]);
+        assert_eq!(aiff.get_instrument_chunk().unwrap(), None);
+        assert_eq!(
+            aiff.frames().unwrap().read_to_end_f32().unwrap(),
+            vec![0.5, -0.5]
+        );
+
+        assert!(Aiff::new(Cursor::new(b"RIFF\0\0\0\0WAVE".to_vec())).is_err());
+    }
+
+    #[test]
+    fn update_chunks() {
+        let mut aiff = Aiff::new(Cursor::new(aiff_file())).unwrap();
+
+        aiff.update_instrument_chunk(|chunk| chunk.set_base_note(Note::from(48)))
+            .unwrap();
+        aiff.update_marker_chunk(|chunk| {
+            chunk.add(1, "Loop start");
+        })
+        .unwrap();
+        aiff.set_text(NAME, Some("Gong")).unwrap();
+        aiff.set_text(ANNOTATION, None).unwrap();
+
+        let file = aiff.file.into_inner();
+        assert_eq!(
+            u32::from_be_bytes(file[4..8].try_into().unwrap()) as usize,
+            file.len() - 8
+        );
+
+        let mut aiff = Aiff::new(Cursor::new(file)).unwrap();
+        let instrument = aiff.get_instrument_chunk().unwrap().unwrap();
+        assert_eq!(u8::from(instrument.base_note()), 48);
+        assert_eq!(aiff.get_marker_chunk().unwrap().unwrap().markers().len(), 1);
+        assert_eq!(aiff.get_text(NAME).unwrap().as_deref(), Some("Gong"));
+        assert_eq!(aiff.get_annotations().unwrap(), vec!["Two"]);
+        assert_eq!(aiff.frames().unwrap().frames_remaining(), 2);
+    }
+}
@@ -0,0 +1,303 @@
+use super::{write_chunk_to, CommonChunk, InstrumentChunk, MarkerChunk, ANNOTATION, SOUND_DATA};
+use crate::{
+    error::{Error, Result},
+    pcm::Encoding,
+};
+use std::{
+    convert::TryInto,
+    io::{Seek, SeekFrom, Write},
+};
+
+/// Timestamp of the only version of the AIFF-C specification, which AIFF-C
+/// files must declare in a `FVER` chunk.
+const AIFC_VERSION_1: u32 = 0xa280_5140;
+
+/// Writes a new AIFF or AIFF-C file from a common chunk, a stream of sample
+/// frames, and any number of other chunks.
+///
+/// An AIFF-C file is written if the common chunk has a compression type.
+/// Chunks written before the first frame are placed before the sound data,
+/// and chunks written after any frames are placed after it, at which point no
+/// more frames can be written.
+///
+/// The sizes and frame count are only filled in by [`finish`](Self::finish),
+/// so it must be called once everything has been written.
+pub struct AiffWriter<W> {
+    writer: W,
+    common: CommonChunk,
+    common_offset: u64,
+    encoding: Encoding,
+    state: State,
+    frames: u64,
+    buffer: Vec<u8>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Header,
+    /// Frames are being written to the sound data chunk whose contents begin
+    /// at the given offset.
+    Frames {
+        start: u64,
+        len: u64,
+    },
+    Trailer,
+}
+
+impl<W: Write + Seek> AiffWriter<W> {
+    /// Start a new file, writing the form header and the common chunk.
+    ///
+    /// Returns an error if the format isn't one that frames can be encoded to.
+    pub fn new(mut writer: W, common: CommonChunk) -> Result<Self> {
+        let encoding = common.encoding()?;
+
+        writer.write_all(b"FORM")?;
+        writer.write_all(&0u32.to_be_bytes())?;
+
+        if common.is_aifc() {
+            writer.write_all(b"AIFC")?;
+            write_chunk_to(&mut writer, b"FVER", &AIFC_VERSION_1.to_be_bytes())?;
+        } else {
+            writer.write_all(b"AIFF")?;
+        }
+
+        let common_offset = writer.stream_position()?;
+        write_chunk_to(&mut writer, CommonChunk::ID, &common.to_bytes())?;
+
+        Ok(Self {
+            writer,
+            common,
+            common_offset,
+            encoding,
+            state: State::Header,
+            frames: 0,
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn common_chunk(&self) -> &CommonChunk {
+        &self.common
+    }
+
+    /// Write interleaved normalized samples. Samples outside of `-1.0..=1.0`
+    /// are clipped when writing integer formats.
+    ///
+    /// The number of samples must be a multiple of the channel count.
+    pub fn write_f32(&mut self, samples: &[f32]) -> Result<()> {
+        self.check_frames(samples.len())?;
+        self.buffer.clear();
+
+        for sample in samples {
+            self.encoding.encode_f64(*sample as f64, &mut self.buffer);
+        }
+
+        self.write_buffer(samples.len())
+    }
+
+    /// Like [`write_f32`](Self::write_f32), but with double precision so that
+    /// 64-bit float and 32-bit integer samples are written without any loss.
+    pub fn write_f64(&mut self, samples: &[f64]) -> Result<()> {
+        self.check_frames(samples.len())?;
+        self.buffer.clear();
+
+        for sample in samples {
+            self.encoding.encode_f64(*sample, &mut self.buffer);
+        }
+
+        self.write_buffer(samples.len())
+    }
+
+    /// Write interleaved integer samples in the native range of the format,
+    /// such as `-32768..=32767` for 16-bit audio.
+    ///
+    /// Returns an error if the format isn't an integer format.
+    pub fn write_i32(&mut self, samples: &[i32]) -> Result<()> {
+        if !self.encoding.is_integer() {
+            return Err(Error::InvalidInput("samples are not integers".to_owned()));
+        }
+
+        self.check_frames(samples.len())?;
+        self.buffer.clear();
+
+        for sample in samples {
+            self.encoding.encode_int(*sample, &mut self.buffer);
+        }
+
+        self.write_buffer(samples.len())
+    }
+
+    pub fn write_instrument_chunk(&mut self, chunk: &InstrumentChunk) -> Result<()> {
+        self.write_chunk(InstrumentChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn write_marker_chunk(&mut self, chunk: &MarkerChunk) -> Result<()> {
+        self.write_chunk(MarkerChunk::ID, &chunk.to_bytes())
+    }
+
+    /// Write a text chunk, such as [`NAME`](super::NAME).
+    pub fn write_text(&mut self, id: &[u8; 4], text: &str) -> Result<()> {
+        self.write_chunk(id, text.as_bytes())
+    }
+
+    pub fn write_annotation(&mut self, text: &str) -> Result<()> {
+        self.write_text(ANNOTATION, text)
+    }
+
+    /// Write an arbitrary chunk. The common and sound data chunks are managed
+    /// by the writer and can't be written this way.
+    pub fn write_chunk(&mut self, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        if id == CommonChunk::ID || id == SOUND_DATA {
+            return Err(Error::InvalidInput(format!(
+                "cannot write {} chunk",
+                String::from_utf8_lossy(id)
+            )));
+        }
+
+        self.end_data()?;
+        write_chunk_to(&mut self.writer, id, data)
+    }
+
+    /// Fill in the sizes and frame count and return the underlying writer.
+    pub fn finish(mut self) -> Result<W> {
+        if self.state == State::Header {
+            self.start_data()?;
+        }
+
+        self.end_data()?;
+
+        let too_large = || Error::TooLarge("AIFF file too large");
+        let end = self.writer.seek(SeekFrom::End(0))?;
+        let form_len: u32 = (end - 8).try_into().map_err(|_| too_large())?;
+
+        self.common
+            .set_frame_count(self.frames.try_into().map_err(|_| too_large())?);
+
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer.write_all(&form_len.to_be_bytes())?;
+        self.writer.seek(SeekFrom::Start(self.common_offset))?;
+        write_chunk_to(&mut self.writer, CommonChunk::ID, &self.common.to_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn check_frames(&mut self, samples: usize) -> Result<()> {
+        if !samples.is_multiple_of(self.common.channels() as usize) {
+            return Err(Error::InvalidInput(
+                "sample count is not a multiple of the channel count".to_owned(),
+            ));
+        }
+
+        match self.state {
+            State::Header => self.start_data(),
+            State::Frames { .. } => Ok(()),
+            State::Trailer => Err(Error::InvalidInput(
+                "cannot write frames after the sound data chunk".to_owned(),
+            )),
+        }
+    }
+
+    fn write_buffer(&mut self, samples: usize) -> Result<()> {
+        self.writer.write_all(&self.buffer)?;
+        self.frames += (samples / self.common.channels() as usize) as u64;
+
+        if let State::Frames { len, .. } = &mut self.state {
+            *len += self.buffer.len() as u64;
+        }
+
+        Ok(())
+    }
+
+    fn start_data(&mut self) -> Result<()> {
+        self.writer.write_all(SOUND_DATA)?;
+        self.writer.write_all(&0u32.to_be_bytes())?;
+        // Offset and block size, which are only used for block-aligned data.
+        self.writer.write_all(&[0; 8])?;
+
+        self.state = State::Frames {
+            start: self.writer.stream_position()? - 8,
+            len: 8,
+        };
+
+        Ok(())
+    }
+
+    /// Close the sound data chunk if frames are being written, filling in its
+    /// size and padding it to an even length.
+    fn end_data(&mut self) -> Result<()> {
+        if let State::Frames { start, len } = self.state {
+            let size: u32 = len
+                .try_into()
+                .map_err(|_| Error::TooLarge("sound data chunk too large"))?;
+
+            if len % 2 == 1 {
+                self.writer.write_all(&[0])?;
+            }
+
+            let end = self.writer.stream_position()?;
+            self.writer.seek(SeekFrom::Start(start - 4))?;
+            self.writer.write_all(&size.to_be_bytes())?;
+            self.writer.seek(SeekFrom::Start(end))?;
+
+            self.state = State::Trailer;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aiff::{Aiff, NAME};
+    use std::io::Cursor;
+
+    #[test]
+    fn write_and_read_back() {
+        let mut markers = MarkerChunk::default();
+        markers.add(1, "Loop");
+
+        let mut writer =
+            AiffWriter::new(Cursor::new(Vec::new()), CommonChunk::new(2, 22050, 24)).unwrap();
+        writer.write_text(NAME, "Pad").unwrap();
+        writer.write_f32(&[0.5, -0.5]).unwrap();
+        writer.write_i32(&[1, -1]).unwrap();
+        writer.write_marker_chunk(&markers).unwrap();
+        assert!(writer.write_i32(&[0, 0]).is_err());
+
+        let file = writer.finish().unwrap().into_inner();
+        assert_eq!(
+            u32::from_be_bytes(file[4..8].try_into().unwrap()) as usize,
+            file.len() - 8
+        );
+
+        let mut aiff = Aiff::new(Cursor::new(file)).unwrap();
+        assert_eq!(aiff.get_common_chunk().unwrap().unwrap().frame_count(), 2);
+        assert_eq!(aiff.get_text(NAME).unwrap().as_deref(), Some("Pad"));
+        assert_eq!(aiff.get_marker_chunk().unwrap(), Some(markers));
+
+        let mut samples = [0; 4];
+        aiff.frames().unwrap().read_i32(&mut samples).unwrap();
+        assert_eq!(samples, [0x400000, -0x400000, 1, -1]);
+    }
+
+    #[test]
+    fn write_aifc() {
+        let mut common = CommonChunk::new(1, 48000, 16);
+        common.set_compression(CommonChunk::COMPRESSION_SOWT, "");
+
+        let mut writer = AiffWriter::new(Cursor::new(Vec::new()), common).unwrap();
+        writer.write_i32(&[0x1234]).unwrap();
+
+        let file = writer.finish().unwrap().into_inner();
+        assert_eq!(&file[8..12], b"AIFC");
+
+        let mut aiff = Aiff::new(Cursor::new(file)).unwrap();
+        assert!(aiff.is_aifc());
+
+        let mut samples = [0; 1];
+        aiff.frames().unwrap().read_i32(&mut samples).unwrap();
+        assert_eq!(samples, [0x1234]);
+    }
+}
@@ -0,0 +1,323 @@
+//! Safe replacement of files on disk.
+//!
+//! Editing a file in place means a crash or a full disk part way through can
+//! leave it corrupted. [`AtomicFile`] instead does all its writing to a
+//! temporary file next to the original, and only once that has been written
+//! out in full and checked is it renamed over the original.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File, FileTimes, OpenOptions},
+    io::{self, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::error::{Error, Result};
+
+/// A file being written to a temporary path, which replaces the file at its
+/// real path once committed.
+///
+/// If dropped without being committed, the temporary file is removed and the
+/// original file is left untouched.
+#[derive(Debug)]
+pub struct AtomicFile {
+    path: PathBuf,
+    temp_path: PathBuf,
+    file: File,
+    /// Metadata of the original file, whose ownership, permissions and
+    /// timestamps are carried over when editing.
+    original: Option<fs::Metadata>,
+    backup_suffix: Option<OsString>,
+    committed: bool,
+}
+
+impl AtomicFile {
+    /// Start editing an existing file. Writes go to a copy of the file, and
+    /// the copy keeps the ownership, permissions and timestamps of the
+    /// original.
+    pub fn edit(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let original = fs::metadata(&path)?;
+        let temp_path = temp_path(&path)?;
+
+        fs::copy(&path, &temp_path)?;
+
+        let file = match OpenOptions::new().read(true).write(true).open(&temp_path) {
+            Ok(file) => file,
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                return Err(e.into());
+            }
+        };
+
+        Ok(Self {
+            path,
+            temp_path,
+            file,
+            original: Some(original),
+            backup_suffix: None,
+            committed: false,
+        })
+    }
+
+    /// Start writing a new file, which replaces any existing file at the path.
+    pub fn create(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let temp_path = temp_path(&path)?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)?;
+
+        Ok(Self {
+            path,
+            temp_path,
+            file,
+            original: None,
+            backup_suffix: None,
+            committed: false,
+        })
+    }
+
+    /// Start rewriting an existing file from scratch. The new file starts out
+    /// empty, but otherwise this is the same as [`edit`](Self::edit).
+    pub fn rewrite(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let original = fs::metadata(&path)?;
+        let mut file = Self::create(path)?;
+        file.original = Some(original);
+
+        Ok(file)
+    }
+
+    /// Keep a copy of the file being replaced, named by appending the suffix
+    /// to the file name, such as `kick.wav.bak`.
+    pub fn with_backup_suffix(mut self, suffix: impl Into<OsString>) -> Self {
+        self.backup_suffix = Some(suffix.into());
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The temporary file to write to.
+    pub fn file(&mut self) -> &mut File {
+        &mut self.file
+    }
+
+    /// Replace the file at the real path with the temporary file.
+    ///
+    /// The temporary file is flushed to disk and passed to `verify` first,
+    /// which should parse it and fail if it isn't valid. If anything goes
+    /// wrong the original file is left as it was.
+    pub fn commit(mut self, verify: impl FnOnce(&mut File) -> Result<()>) -> Result<()> {
+        self.file.sync_all()?;
+        self.file.seek(SeekFrom::Start(0))?;
+
+        verify(&mut self.file).map_err(|e| Error::Verify(Box::new(e)))?;
+
+        if let Some(original) = self.original.as_ref() {
+            set_owner(&self.file, original)?;
+            self.file.set_permissions(original.permissions())?;
+
+            let mut times = FileTimes::new().set_modified(original.modified()?);
+
+            if let Ok(accessed) = original.accessed() {
+                times = times.set_accessed(accessed);
+            }
+
+            self.file.set_times(times)?;
+        }
+
+        if let Some(suffix) = self.backup_suffix.as_ref() {
+            if self.path.exists() {
+                let mut backup_path = self.path.clone().into_os_string();
+                backup_path.push(suffix);
+                backup(&self.path, Path::new(&backup_path))?;
+            }
+        }
+
+        fs::rename(&self.temp_path, &self.path)?;
+        self.committed = true;
+        sync_parent_dir(&self.path)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Get a path for a temporary file in the same directory as a file, so that
+/// it can be renamed over the file without crossing file systems.
+///
+/// Each call gives a different path, so the same file can be written to by
+/// more than one [`AtomicFile`] at once.
+fn temp_path(path: &Path) -> Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::InvalidInput("path has no file name".to_owned()))?;
+
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    Ok(path.with_file_name(temp_name))
+}
+
+/// Give a file the same owner and group as the original. Only the superuser
+/// can change the owner, and only to a group the user is in, so this is
+/// skipped where it isn't permitted, as `cp -p` does.
+#[cfg(unix)]
+fn set_owner(file: &File, original: &fs::Metadata) -> io::Result<()> {
+    use std::os::unix::fs::{fchown, MetadataExt};
+
+    let metadata = file.metadata()?;
+
+    if (metadata.uid(), metadata.gid()) == (original.uid(), original.gid()) {
+        return Ok(());
+    }
+
+    match fchown(file, Some(original.uid()), Some(original.gid())) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            match fchown(file, None, Some(original.gid())) {
+                Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Ok(()),
+                result => result,
+            }
+        }
+        result => result,
+    }
+}
+
+#[cfg(not(unix))]
+fn set_owner(_file: &File, _original: &fs::Metadata) -> io::Result<()> {
+    Ok(())
+}
+
+/// Flush the directory containing a file to disk, so that a rename into it
+/// survives a crash.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Make a backup of a file. A hard link is used where possible, since the
+/// original is about to be replaced rather than modified.
+fn backup(path: &Path, backup_path: &Path) -> io::Result<()> {
+    match fs::remove_file(backup_path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    if fs::hard_link(path, backup_path).is_err() {
+        fs::copy(path, backup_path)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env,
+        io::{Read, Write},
+    };
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("smplinfo-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn commit_replaces_file_and_keeps_backup() {
+        let dir = test_dir("atomic-commit");
+        let path = dir.join("kick.wav");
+        fs::write(&path, b"old").unwrap();
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let mut file = AtomicFile::edit(&path).unwrap().with_backup_suffix(".bak");
+        let mut contents = String::new();
+        file.file().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "old");
+
+        file.file().write_all(b" and new").unwrap();
+        file.commit(|_| Ok(())).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"old and new");
+        assert_eq!(fs::read(dir.join("kick.wav.bak")).unwrap(), b"old");
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_verify_leaves_original() {
+        let dir = test_dir("atomic-verify");
+        let path = dir.join("kick.wav");
+        fs::write(&path, b"old").unwrap();
+
+        let mut file = AtomicFile::edit(&path).unwrap();
+        file.file().write_all(b"bad").unwrap();
+        let result = file.commit(|_| Err(Error::MissingChunk { id: *b"data" }));
+
+        assert!(result.is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        drop(AtomicFile::create(dir.join("new.wav")).unwrap());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn commit_keeps_owner() {
+        use std::os::unix::fs::{chown, MetadataExt};
+
+        let dir = test_dir("atomic-owner");
+        let path = dir.join("kick.wav");
+        fs::write(&path, b"old").unwrap();
+
+        // Only possible when running as the superuser.
+        if chown(&path, Some(1234), Some(1234)).is_ok() {
+            let mut file = AtomicFile::edit(&path).unwrap();
+            file.file().write_all(b"new").unwrap();
+            file.commit(|_| Ok(())).unwrap();
+
+            let metadata = fs::metadata(&path).unwrap();
+            assert_eq!((metadata.uid(), metadata.gid()), (1234, 1234));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,734 @@
+//! Conversion between WAV and AIFF files.
+//!
+//! Sample frames are copied without any loss, and as much metadata as possible
+//! is carried over to the equivalent chunks of the other format. Anything that
+//! has no equivalent is dropped and described in the returned warnings.
+
+use std::{
+    collections::BTreeSet,
+    io::{Read, Seek, Write},
+};
+
+use crate::{
+    aiff::{self, Aiff, AiffWriter, CommonChunk},
+    error::{Error, Result},
+    midi::Note,
+    pcm::FrameReader,
+    wav::{
+        AssociatedData, AssociatedDataList, CueChunk, CuePoint, FormatChunk, InstrumentChunk,
+        ListInfo, LoopType, SampleFormat, SampleLoop, SamplerChunk, Wav, WavWriter,
+    },
+};
+
+/// AIFF text chunks along with the INFO tags they are equivalent to.
+/// Annotations are handled separately, since there may be more than one.
+pub(crate) const TEXT_TAGS: &[(&[u8; 4], &[u8; 4])] = &[
+    (aiff::NAME, b"INAM"),
+    (aiff::AUTHOR, b"IART"),
+    (aiff::COPYRIGHT, b"ICOP"),
+];
+
+/// INFO tag that AIFF annotations are equivalent to.
+pub(crate) const COMMENT_TAG: &[u8; 4] = b"ICMT";
+
+/// WAV chunks that are either converted or have no meaning in an AIFF file.
+const WAV_CHUNKS: &[&[u8; 4]] = &[
+    b"fmt ", b"data", b"fact", b"smpl", b"inst", b"cue ", b"LIST", b"JUNK", b"junk", b"PAD ",
+];
+
+/// AIFF chunks that are either converted or have no meaning in a WAV file.
+const AIFF_CHUNKS: &[&[u8; 4]] = &[
+    b"COMM", b"SSND", b"MARK", b"INST", b"NAME", b"AUTH", b"(c) ", b"ANNO", b"FVER", b"FLLR",
+];
+
+/// Number of frames copied at a time.
+const BLOCK_FRAMES: usize = 4096;
+
+/// Convert a WAV file into an AIFF file.
+///
+/// Integer samples are written as an AIFF file and floating point samples as
+/// an AIFF-C file. The sampler chunk maps to the instrument chunk, with the
+/// first two loops becoming the sustain and release loops, cue points map to
+/// markers, and INFO tags map to text chunks.
+///
+/// Returns a description of each piece of information that couldn't be
+/// converted.
+pub fn wav_to_aiff<R, W>(input: R, output: W) -> Result<Vec<String>>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let mut wav = Wav::new(input)?;
+    let mut warnings = Vec::new();
+
+    let format = wav
+        .get_format_chunk()?
+        .ok_or(Error::MissingChunk { id: *b"fmt " })?;
+    let mut common = CommonChunk::new(
+        format.channels(),
+        format.sample_rate(),
+        format.valid_bits_per_sample(),
+    );
+
+    match (format.sample_format(), format.bits_per_sample()) {
+        (SampleFormat::Pcm, _) => {}
+        (SampleFormat::IeeeFloat, 32) => {
+            common.set_compression(CommonChunk::COMPRESSION_FLOAT32, "32-bit floating point")
+        }
+        (SampleFormat::IeeeFloat, 64) => {
+            common.set_compression(CommonChunk::COMPRESSION_FLOAT64, "64-bit floating point")
+        }
+        (sample_format, _) => {
+            return Err(Error::Unsupported(format!(
+                "unsupported sample format: {}",
+                sample_format
+            )))
+        }
+    }
+
+    if let Some(mask) = format.channel_mask().filter(|mask| *mask != 0) {
+        warnings.push(format!(
+            "channel mask 0x{:x} has no AIFF equivalent and was dropped",
+            mask
+        ));
+    }
+
+    let sampler = wav.get_sampler_chunk()?;
+    let wav_instrument = wav.get_instrument_chunk()?;
+    let mut markers = aiff::MarkerChunk::default();
+
+    for marker in wav.get_markers()? {
+        let name = marker.label.unwrap_or_default();
+
+        if name.len() > 255 {
+            warnings.push(format!(
+                "label of marker {} was cut short to 255 bytes",
+                marker.id
+            ));
+        }
+
+        markers.add(marker.position, &name);
+    }
+
+    let instrument = if sampler.is_some() || wav_instrument.is_some() {
+        let mut instrument = aiff::InstrumentChunk::default();
+
+        if let Some(chunk) = wav_instrument.as_ref() {
+            instrument.set_base_note(chunk.unshifted_note());
+            instrument.set_detune(chunk.fine_tune());
+            instrument.set_low_note(chunk.low_note());
+            instrument.set_high_note(chunk.high_note());
+            instrument.set_low_velocity(chunk.low_velocity());
+            instrument.set_high_velocity(chunk.high_velocity());
+            instrument.set_gain(chunk.gain() as i16);
+        }
+
+        if let Some(sampler) = sampler.as_ref() {
+            let (note, detune) =
+                note_from_pitch_fraction(sampler.midi_unity_note(), sampler.midi_pitch_fraction());
+
+            instrument.set_base_note(note);
+            instrument.set_detune(detune);
+
+            for (i, sample_loop) in sampler.loops().iter().enumerate() {
+                let aiff_loop = match i {
+                    0 => &mut instrument.sustain_loop,
+                    1 => &mut instrument.release_loop,
+                    _ => {
+                        warnings.push(format!(
+                            "loop {} was dropped, since AIFF only has a sustain and a release loop",
+                            i + 1
+                        ));
+                        continue;
+                    }
+                };
+
+                *aiff_loop = aiff_loop_from(i, sample_loop, &mut markers, &mut warnings);
+            }
+
+            warn_sampler_fields(sampler, &mut warnings);
+        }
+
+        Some(instrument)
+    } else {
+        None
+    };
+
+    let mut texts = Vec::new();
+
+    if let Some(info) = wav.get_list_info()? {
+        for (id, value) in info.iter() {
+            if &id == COMMENT_TAG {
+                texts.push((aiff::ANNOTATION, value));
+            } else if let Some((text_id, _)) = TEXT_TAGS.iter().find(|(_, tag)| **tag == id) {
+                texts.push((*text_id, value));
+            } else {
+                warnings.push(format!(
+                    "tag {} ({:?}) has no AIFF equivalent and was dropped",
+                    String::from_utf8_lossy(&id),
+                    value
+                ));
+            }
+        }
+    }
+
+    if let Some(adtl) = wav.get_associated_data_list()? {
+        warn_associated_data(&adtl, &mut warnings);
+    }
+
+    warn_unknown_chunks(&wav.chunk_ids()?, WAV_CHUNKS, "AIFF", &mut warnings);
+
+    let mut writer = AiffWriter::new(output, common)?;
+
+    if !markers.markers().is_empty() {
+        writer.write_marker_chunk(&markers)?;
+    }
+
+    if let Some(instrument) = instrument.as_ref() {
+        writer.write_instrument_chunk(instrument)?;
+    }
+
+    for (id, text) in &texts {
+        writer.write_text(id, text)?;
+    }
+
+    copy_frames(&mut wav.frames()?, |samples| match samples {
+        Samples::Int(samples) => writer.write_i32(samples),
+        Samples::Float(samples) => writer.write_f64(samples),
+    })?;
+
+    writer.finish()?;
+
+    Ok(warnings)
+}
+
+/// Convert an AIFF or AIFF-C file into a WAV file.
+///
+/// The instrument chunk maps to both the sampler chunk, with the sustain and
+/// release loops as its loops, and the `inst` chunk. Markers map to cue
+/// points, and text chunks map to INFO tags.
+///
+/// Returns a description of each piece of information that couldn't be
+/// converted.
+pub fn aiff_to_wav<R, W>(input: R, output: W) -> Result<Vec<String>>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let mut aiff = Aiff::new(input)?;
+    let mut warnings = Vec::new();
+
+    let common = aiff
+        .get_common_chunk()?
+        .ok_or(Error::MissingChunk { id: *b"COMM" })?;
+    // Make sure the frames can be decoded before going any further.
+    common.encoding()?;
+
+    let format = format_chunk(&common)?;
+    let sample_rate = format.sample_rate();
+
+    if common.sample_rate().fract() != 0.0 {
+        warnings.push(format!(
+            "sample rate of {} Hz was rounded to {} Hz",
+            common.sample_rate(),
+            sample_rate
+        ));
+    }
+
+    let markers = aiff.get_marker_chunk()?.unwrap_or_default();
+    let mut cue = CueChunk::default();
+    let mut adtl = AssociatedDataList::default();
+
+    for marker in markers.markers() {
+        cue.points_mut()
+            .push(CuePoint::new(marker.id as u32, marker.position));
+
+        if !marker.name.is_empty() {
+            adtl.set_label(marker.id as u32, Some(&marker.name));
+        }
+    }
+
+    let mut sampler = None;
+    let mut wav_instrument = None;
+
+    if let Some(instrument) = aiff.get_instrument_chunk()? {
+        let mut chunk = SamplerChunk::default();
+        let (note, fraction) =
+            pitch_fraction_from_note(instrument.base_note(), instrument.detune());
+
+        chunk.set_midi_unity_note(note);
+        chunk.set_midi_pitch_fraction(fraction);
+
+        if let Some(period) = 1_000_000_000u32.checked_div(sample_rate) {
+            chunk.set_sample_period(period);
+        }
+
+        for (name, aiff_loop) in &[
+            ("sustain", &instrument.sustain_loop),
+            ("release", &instrument.release_loop),
+        ] {
+            if !aiff_loop.is_enabled() {
+                continue;
+            }
+
+            match sample_loop(aiff_loop, &markers) {
+                Some(sample_loop) => {
+                    if let aiff::PlayMode::Other(mode) = aiff_loop.play_mode {
+                        warnings.push(format!(
+                            "{} loop has unknown play mode {} and was converted to a forward loop",
+                            name, mode
+                        ));
+                    }
+
+                    chunk.loops_mut().push(sample_loop);
+                }
+                None => warnings.push(format!(
+                    "{} loop was dropped, since its markers are missing or out of order",
+                    name
+                )),
+            }
+        }
+
+        let inst = instrument_chunk(&instrument);
+
+        if inst.gain() as i16 != instrument.gain() {
+            warnings.push(format!(
+                "gain of {} dB was clamped to {} dB",
+                instrument.gain(),
+                inst.gain()
+            ));
+        }
+
+        sampler = Some(chunk);
+        wav_instrument = Some(inst);
+    }
+
+    let mut info = ListInfo::default();
+
+    for (id, tag) in TEXT_TAGS {
+        if let Some(text) = aiff.get_text(id)? {
+            info.set(tag, &text);
+        }
+    }
+
+    let annotations = aiff.get_annotations()?;
+
+    if !annotations.is_empty() {
+        info.set(COMMENT_TAG, &annotations.join("\n"));
+    }
+
+    warn_unknown_chunks(&aiff.chunk_ids()?, AIFF_CHUNKS, "WAV", &mut warnings);
+
+    let mut writer = WavWriter::new(output, format)?;
+
+    if let Some(sampler) = sampler.as_ref() {
+        writer.write_sampler_chunk(sampler)?;
+    }
+
+    if let Some(inst) = wav_instrument.as_ref() {
+        writer.write_instrument_chunk(inst)?;
+    }
+
+    if !cue.points().is_empty() {
+        writer.write_cue_chunk(&cue)?;
+    }
+
+    if !adtl.is_empty() {
+        writer.write_associated_data_list(&adtl)?;
+    }
+
+    if !info.is_empty() {
+        writer.write_list_info(&info)?;
+    }
+
+    copy_frames(&mut aiff.frames()?, |samples| match samples {
+        Samples::Int(samples) => writer.write_i32(samples),
+        Samples::Float(samples) => writer.write_f64(samples),
+    })?;
+
+    writer.finish()?;
+
+    Ok(warnings)
+}
+
+/// Get the WAV format equivalent to an AIFF common chunk. Compressed AIFF-C
+/// files are treated as PCM, so their encoding should be checked first.
+///
+/// Fails if the chunk has no channels, or frames too large for a WAV file.
+pub(crate) fn format_chunk(common: &CommonChunk) -> Result<FormatChunk> {
+    if common.channels() == 0 || common.block_align() > u16::MAX as u32 {
+        return Err(Error::invalid_chunk(CommonChunk::ID));
+    }
+
+    let (sample_format, bits) = match common.sample_format() {
+        Some(SampleFormat::IeeeFloat) => (
+            SampleFormat::IeeeFloat,
+            (common.block_align() / common.channels() as u32 * 8) as u16,
+        ),
+        _ => (SampleFormat::Pcm, common.sample_size()),
+    };
+
+    FormatChunk::new(
+        sample_format,
+        common.channels(),
+        common.sample_rate().round() as u32,
+        bits,
+    )
+    .map_err(|_| Error::invalid_chunk(CommonChunk::ID))
+}
+
+/// Get the `inst` chunk equivalent to an AIFF instrument chunk, leaving out
+/// the loops. The gain is clamped to the range of the `inst` chunk.
+pub(crate) fn instrument_chunk(instrument: &aiff::InstrumentChunk) -> InstrumentChunk {
+    let mut chunk = InstrumentChunk::default();
+    chunk.set_unshifted_note(instrument.base_note());
+    chunk.set_fine_tune(instrument.detune());
+    chunk.set_gain(instrument.gain().clamp(i8::MIN as i16, i8::MAX as i16) as i8);
+    chunk.set_low_note(instrument.low_note());
+    chunk.set_high_note(instrument.high_note());
+    chunk.set_low_velocity(instrument.low_velocity());
+    chunk.set_high_velocity(instrument.high_velocity());
+    chunk
+}
+
+/// Convert an AIFF loop into a sampler loop, if it is enabled and its markers
+/// exist. The end marker of an AIFF loop comes after the last frame of the
+/// loop, whereas the end of a sampler loop is the last frame itself.
+pub(crate) fn sample_loop(
+    aiff_loop: &aiff::Loop,
+    markers: &aiff::MarkerChunk,
+) -> Option<SampleLoop> {
+    if !aiff_loop.is_enabled() {
+        return None;
+    }
+
+    let start = markers.get(aiff_loop.begin_marker_id)?.position;
+    let end = markers.get(aiff_loop.end_marker_id)?.position;
+
+    if end <= start {
+        return None;
+    }
+
+    Some(SampleLoop {
+        loop_type: match aiff_loop.play_mode {
+            aiff::PlayMode::ForwardBackward => LoopType::Alternating,
+            _ => LoopType::Forward,
+        },
+        ..SampleLoop::new(start, end - 1)
+    })
+}
+
+/// Convert the `index`th sampler loop into an AIFF loop, adding markers for
+/// its start and end.
+pub(crate) fn aiff_loop_from(
+    index: usize,
+    sample_loop: &SampleLoop,
+    markers: &mut aiff::MarkerChunk,
+    warnings: &mut Vec<String>,
+) -> aiff::Loop {
+    let play_mode = match sample_loop.loop_type {
+        LoopType::Forward => aiff::PlayMode::Forward,
+        LoopType::Alternating => aiff::PlayMode::ForwardBackward,
+        loop_type => {
+            warnings.push(format!(
+                "loop {} is a {} loop, which AIFF doesn't support, and was converted to a forward loop",
+                index + 1,
+                loop_type
+            ));
+            aiff::PlayMode::Forward
+        }
+    };
+
+    if sample_loop.play_count != 0 {
+        warnings.push(format!(
+            "play count of loop {} was dropped, so it loops forever",
+            index + 1
+        ));
+    }
+
+    if sample_loop.fraction != 0 {
+        warnings.push(format!(
+            "fractional end position of loop {} was dropped",
+            index + 1
+        ));
+    }
+
+    aiff::Loop {
+        play_mode,
+        begin_marker_id: markers.add(sample_loop.start, &format!("Loop {} start", index + 1)),
+        end_marker_id: markers.add(
+            sample_loop.end.saturating_add(1),
+            &format!("Loop {} end", index + 1),
+        ),
+    }
+}
+
+/// Split a note and a pitch fraction of a semitone upwards into a note and a
+/// detune in cents between -50 and 50.
+pub(crate) fn note_from_pitch_fraction(note: Note, fraction: u32) -> (Note, i8) {
+    let cents = (fraction as f64 / (1u64 << 32) as f64 * 100.0).round() as i16;
+    let note = u8::from(note);
+
+    if cents > 50 && note < 127 {
+        ((note + 1).into(), (cents - 100) as i8)
+    } else {
+        (note.into(), cents.min(50) as i8)
+    }
+}
+
+/// The reverse of [`note_from_pitch_fraction`].
+pub(crate) fn pitch_fraction_from_note(note: Note, detune: i8) -> (Note, u32) {
+    let fraction = |cents: i16| (cents as f64 / 100.0 * (1u64 << 32) as f64).round() as u32;
+    let note = u8::from(note);
+
+    if detune < 0 && note > 0 {
+        ((note - 1).into(), fraction(100 + detune as i16))
+    } else {
+        (note.into(), fraction(detune.max(0) as i16))
+    }
+}
+
+fn warn_sampler_fields(sampler: &SamplerChunk, warnings: &mut Vec<String>) {
+    if sampler.manufacturer() != 0 || sampler.product() != 0 {
+        warnings.push(format!(
+            "sampler manufacturer 0x{:x} and product 0x{:x} were dropped",
+            sampler.manufacturer(),
+            sampler.product()
+        ));
+    }
+
+    if sampler.smpte_format() != 0 || sampler.smpte_offset() != 0 {
+        warnings.push("SMPTE offset of the sampler chunk was dropped".to_owned());
+    }
+
+    if !sampler.sampler_data().is_empty() {
+        warnings.push(format!(
+            "{} bytes of sampler-specific data were dropped",
+            sampler.sampler_data().len()
+        ));
+    }
+}
+
+fn warn_associated_data(adtl: &AssociatedDataList, warnings: &mut Vec<String>) {
+    for entry in adtl.entries() {
+        match entry {
+            AssociatedData::Label { .. } => {}
+            AssociatedData::Note { cue_point_id, text } => warnings.push(format!(
+                "note on marker {} ({:?}) was dropped",
+                cue_point_id, text
+            )),
+            AssociatedData::LabeledText(text) => warnings.push(format!(
+                "labeled text on marker {} ({:?}) was dropped",
+                text.cue_point_id, text.text
+            )),
+            AssociatedData::Other { id, .. } => warnings.push(format!(
+                "associated data {} chunk was dropped",
+                String::from_utf8_lossy(id)
+            )),
+        }
+    }
+}
+
+/// Warn about each kind of chunk that isn't one of the known chunks.
+fn warn_unknown_chunks(
+    ids: &[[u8; 4]],
+    known: &[&[u8; 4]],
+    target: &str,
+    warnings: &mut Vec<String>,
+) {
+    let unknown = ids
+        .iter()
+        .filter(|id| !known.contains(id))
+        .collect::<BTreeSet<_>>();
+
+    for id in unknown {
+        warnings.push(format!(
+            "{:?} chunk has no {} equivalent and was dropped",
+            String::from_utf8_lossy(id),
+            target
+        ));
+    }
+}
+
+/// A block of interleaved samples, in whichever representation holds them
+/// without loss.
+enum Samples<'a> {
+    Int(&'a [i32]),
+    Float(&'a [f64]),
+}
+
+fn copy_frames<R: Read>(
+    frames: &mut FrameReader<R>,
+    mut write: impl FnMut(Samples<'_>) -> Result<()>,
+) -> Result<()> {
+    let channels = frames.channels();
+
+    if frames.is_integer() {
+        let mut buffer = vec![0; BLOCK_FRAMES * channels];
+
+        loop {
+            match frames.read_i32(&mut buffer)? {
+                0 => return Ok(()),
+                count => write(Samples::Int(&buffer[..count * channels]))?,
+            }
+        }
+    } else {
+        let mut buffer = vec![0.0; BLOCK_FRAMES * channels];
+
+        loop {
+            match frames.read_f64(&mut buffer)? {
+                0 => return Ok(()),
+                count => write(Samples::Float(&buffer[..count * channels]))?,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wav::Marker;
+    use std::io::Cursor;
+
+    fn wav_file() -> Vec<u8> {
+        let mut sampler = SamplerChunk::default();
+        sampler.set_midi_unity_note(Note::from(57));
+        // A quarter of a semitone up.
+        sampler.set_midi_pitch_fraction(0x4000_0000);
+        sampler.loops_mut().push(SampleLoop::new(1, 2));
+        sampler.loops_mut().push(SampleLoop {
+            loop_type: LoopType::Backward,
+            ..SampleLoop::new(0, 3)
+        });
+        sampler.loops_mut().push(SampleLoop::new(2, 3));
+
+        let mut cue = CueChunk::default();
+        cue.points_mut().push(CuePoint::new(7, 3));
+
+        let mut adtl = AssociatedDataList::default();
+        adtl.set_label(7, Some("Hit"));
+
+        let mut info = ListInfo::default();
+        info.set(b"INAM", "Piano");
+        info.set(b"ICMT", "Soft");
+        info.set(b"IGNR", "Keys");
+
+        let mut writer = WavWriter::new(
+            Cursor::new(Vec::new()),
+            FormatChunk::new(SampleFormat::Pcm, 1, 44100, 24).unwrap(),
+        )
+        .unwrap();
+        writer.write_sampler_chunk(&sampler).unwrap();
+        writer.write_cue_chunk(&cue).unwrap();
+        writer.write_associated_data_list(&adtl).unwrap();
+        writer.write_list_info(&info).unwrap();
+        writer.write_chunk(b"bext", &[0; 4]).unwrap();
+        writer.write_i32(&[1, -2, 0x7fffff, -0x800000]).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn convert_wav_to_aiff() {
+        let mut output = Cursor::new(Vec::new());
+        let warnings = wav_to_aiff(Cursor::new(wav_file()), &mut output).unwrap();
+
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].starts_with("loop 2 is a backward loop"));
+        assert!(warnings[1].starts_with("loop 3 was dropped"));
+        assert!(warnings[2].starts_with("tag IGNR"));
+        assert!(warnings[3].starts_with("\"bext\" chunk"));
+
+        let mut aiff = Aiff::new(output).unwrap();
+        let common = aiff.get_common_chunk().unwrap().unwrap();
+        assert_eq!(common.sample_size(), 24);
+        assert_eq!(common.frame_count(), 4);
+
+        let instrument = aiff.get_instrument_chunk().unwrap().unwrap();
+        assert_eq!(u8::from(instrument.base_note()), 57);
+        assert_eq!(instrument.detune(), 25);
+
+        let markers = aiff.get_marker_chunk().unwrap().unwrap();
+        assert_eq!(markers.markers()[0].name, "Hit");
+        assert_eq!(
+            sample_loop(&instrument.sustain_loop, &markers),
+            Some(SampleLoop::new(1, 2))
+        );
+        assert_eq!(
+            sample_loop(&instrument.release_loop, &markers),
+            Some(SampleLoop::new(0, 3))
+        );
+
+        assert_eq!(aiff.get_text(aiff::NAME).unwrap().as_deref(), Some("Piano"));
+        assert_eq!(aiff.get_annotations().unwrap(), vec!["Soft"]);
+
+        let mut samples = [0; 4];
+        aiff.frames().unwrap().read_i32(&mut samples).unwrap();
+        assert_eq!(samples, [1, -2, 0x7fffff, -0x800000]);
+    }
+
+    #[test]
+    fn convert_aiff_to_wav() {
+        let mut aiff = Cursor::new(Vec::new());
+        wav_to_aiff(Cursor::new(wav_file()), &mut aiff).unwrap();
+        aiff.set_position(0);
+
+        let mut output = Cursor::new(Vec::new());
+        assert!(aiff_to_wav(aiff, &mut output).unwrap().is_empty());
+
+        let mut wav = Wav::new(output).unwrap();
+        let sampler = wav.get_sampler_chunk().unwrap().unwrap();
+        assert_eq!(u8::from(sampler.midi_unity_note()), 57);
+        assert_eq!(sampler.midi_pitch_fraction(), 0x4000_0000);
+        assert_eq!(
+            sampler.loops(),
+            &[SampleLoop::new(1, 2), SampleLoop::new(0, 3)]
+        );
+
+        assert_eq!(
+            wav.get_markers().unwrap()[0],
+            Marker {
+                id: 1,
+                position: 3,
+                label: Some("Hit".to_owned()),
+            }
+        );
+
+        let info = wav.get_list_info().unwrap().unwrap();
+        assert_eq!(info.get(b"INAM").as_deref(), Some("Piano"));
+        assert_eq!(info.get(b"ICMT").as_deref(), Some("Soft"));
+
+        let mut samples = [0; 4];
+        wav.frames().unwrap().read_i32(&mut samples).unwrap();
+        assert_eq!(samples, [1, -2, 0x7fffff, -0x800000]);
+    }
+
+    #[test]
+    fn convert_aiff_with_oversized_frames() {
+        let aiff = AiffWriter::new(Cursor::new(Vec::new()), CommonChunk::new(30000, 44100, 24))
+            .unwrap()
+            .finish()
+            .unwrap()
+            .into_inner();
+
+        assert!(matches!(
+            aiff_to_wav(Cursor::new(aiff), Cursor::new(Vec::new())),
+            Err(Error::InvalidChunk { id, .. }) if &id == b"COMM"
+        ));
+    }
+
+    #[test]
+    fn pitch_fraction_conversion() {
+        assert_eq!(
+            note_from_pitch_fraction(Note::from(60), 0xc000_0000),
+            (Note::from(61), -25)
+        );
+        assert_eq!(
+            pitch_fraction_from_note(Note::from(61), -25),
+            (Note::from(60), 0xc000_0000)
+        );
+        assert_eq!(
+            pitch_fraction_from_note(Note::from(60), 10),
+            (Note::from(60), 0x1999_999a)
+        );
+    }
+}
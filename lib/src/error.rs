@@ -0,0 +1,243 @@
+//! Errors returned when reading and writing sample files.
+
+use std::{
+    error, fmt, io,
+    path::{Path, PathBuf},
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An error reading or writing a sample file.
+///
+/// Errors are grouped by their cause, so that a file that isn't a sample file
+/// at all can be told apart from one that is damaged, or from a problem with
+/// the file system.
+#[derive(Debug)]
+pub enum Error {
+    /// An error from the underlying file, such as a missing file or a
+    /// permission problem.
+    Io(io::Error),
+    /// The file isn't in the expected format, such as a text file being read
+    /// as a WAV file.
+    NotRecognized { format: &'static str },
+    /// A chunk needed to read the file is missing.
+    MissingChunk { id: [u8; 4] },
+    /// A chunk is too short or contains values that don't make sense.
+    InvalidChunk { id: [u8; 4], offset: Option<u64> },
+    /// A chunk is cut short by the end of its contents or the end of the
+    /// file.
+    TruncatedChunk { id: [u8; 4], offset: Option<u64> },
+    /// A FLAC metadata block needed to read the file is missing.
+    MissingBlock { name: &'static str },
+    /// A FLAC metadata block is too short or contains values that don't make
+    /// sense.
+    InvalidBlock {
+        name: &'static str,
+        offset: Option<u64>,
+    },
+    /// The file is valid, but uses an encoding or feature that isn't
+    /// supported.
+    Unsupported(String),
+    /// Something is too large to be stored in the format.
+    TooLarge(&'static str),
+    /// The library was asked to do something that isn't possible, such as
+    /// writing integer samples from a float source.
+    InvalidInput(String),
+    /// A file that was written couldn't be read back, so it wasn't used.
+    Verify(Box<Error>),
+    /// An error in a particular file.
+    File { path: PathBuf, source: Box<Error> },
+}
+
+impl Error {
+    /// Attach the path of the file the error happened in.
+    pub fn with_path(self, path: impl Into<PathBuf>) -> Self {
+        match self {
+            Error::File { .. } => self,
+            error => Error::File {
+                path: path.into(),
+                source: Box::new(error),
+            },
+        }
+    }
+
+    /// The error without any path attached by
+    /// [`with_path`](Self::with_path).
+    pub fn without_path(&self) -> &Self {
+        match self {
+            Error::File { source, .. } => source.without_path(),
+            error => error,
+        }
+    }
+
+    /// The path of the file the error happened in, if known.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::File { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The ID of the chunk the error is about, if any.
+    pub fn chunk_id(&self) -> Option<[u8; 4]> {
+        match self.without_path() {
+            Error::MissingChunk { id }
+            | Error::InvalidChunk { id, .. }
+            | Error::TruncatedChunk { id, .. } => Some(*id),
+            Error::Verify(source) => source.chunk_id(),
+            _ => None,
+        }
+    }
+
+    /// Offset from the start of the file of the chunk or block the error is
+    /// about, if known.
+    pub fn offset(&self) -> Option<u64> {
+        match self.without_path() {
+            Error::InvalidChunk { offset, .. }
+            | Error::TruncatedChunk { offset, .. }
+            | Error::InvalidBlock { offset, .. } => *offset,
+            Error::Verify(source) => source.offset(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn invalid_chunk(id: &[u8; 4]) -> Self {
+        Error::InvalidChunk {
+            id: *id,
+            offset: None,
+        }
+    }
+
+    pub(crate) fn truncated_chunk(id: &[u8; 4]) -> Self {
+        Error::TruncatedChunk {
+            id: *id,
+            offset: None,
+        }
+    }
+
+    /// Convert an error reading the header of a file, treating a file too
+    /// short to have a header as not being in the format at all.
+    pub(crate) fn header(format: &'static str) -> impl FnOnce(io::Error) -> Self {
+        move |e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::NotRecognized { format },
+            _ => Error::Io(e),
+        }
+    }
+
+    /// Set the offset of the chunk or block the error is about, if it isn't
+    /// already known.
+    pub(crate) fn at_offset(mut self, chunk_offset: u64) -> Self {
+        match &mut self {
+            Error::InvalidChunk { offset, .. }
+            | Error::TruncatedChunk { offset, .. }
+            | Error::InvalidBlock { offset, .. } => {
+                offset.get_or_insert(chunk_offset);
+            }
+            _ => {}
+        }
+
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => e.fmt(f),
+            Error::NotRecognized { format } => write!(f, "not in {} format", format),
+            Error::MissingChunk { id } => write!(f, "missing {} chunk", chunk_name(id)),
+            Error::InvalidChunk { id, offset } => {
+                write!(f, "invalid {} chunk", chunk_name(id))?;
+                write_offset(f, *offset)
+            }
+            Error::TruncatedChunk { id, offset } => {
+                write!(f, "truncated {} chunk", chunk_name(id))?;
+                write_offset(f, *offset)
+            }
+            Error::MissingBlock { name } => write!(f, "missing {} block", name),
+            Error::InvalidBlock { name, offset } => {
+                write!(f, "invalid {} block", name)?;
+                write_offset(f, *offset)
+            }
+            Error::Unsupported(message) => f.write_str(message),
+            Error::TooLarge(message) => f.write_str(message),
+            Error::InvalidInput(message) => f.write_str(message),
+            Error::Verify(source) => write!(
+                f,
+                "refusing to write file that can't be read back: {}",
+                source
+            ),
+            Error::File { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Verify(source) | Error::File { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            e @ (Error::InvalidInput(_) | Error::TooLarge(_)) => {
+                io::Error::new(io::ErrorKind::InvalidInput, e)
+            }
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+/// A chunk ID as text, without the trailing spaces of IDs such as `fmt `.
+fn chunk_name(id: &[u8; 4]) -> String {
+    String::from_utf8_lossy(id).trim_end().to_owned()
+}
+
+fn write_offset(f: &mut fmt::Formatter<'_>, offset: Option<u64>) -> fmt::Result {
+    match offset {
+        Some(offset) => write!(f, " at offset {}", offset),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_context() {
+        let error = Error::invalid_chunk(b"fmt ")
+            .at_offset(12)
+            .at_offset(40)
+            .with_path("kick.wav");
+
+        assert_eq!(
+            error.to_string(),
+            "kick.wav: invalid fmt chunk at offset 12"
+        );
+        assert_eq!(error.path(), Some(Path::new("kick.wav")));
+        assert_eq!(error.chunk_id(), Some(*b"fmt "));
+        assert_eq!(error.offset(), Some(12));
+        assert!(matches!(error.without_path(), Error::InvalidChunk { .. }));
+
+        let error = Error::from(io::Error::from(io::ErrorKind::NotFound)).with_path("a.wav");
+        assert!(matches!(error.without_path(), Error::Io(_)));
+        assert_eq!(error.chunk_id(), None);
+        assert_eq!(
+            io::Error::from(Error::MissingChunk { id: *b"data" }).kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,562 @@
+//! FLAC metadata reading and writing routines.
+//!
+//! Only the metadata blocks at the start of the file are touched; the audio is
+//! never decoded. Sampler metadata is stored the same way as
+//! `flac --keep-foreign-metadata` does it, with the chunks of the original WAV
+//! file kept in `APPLICATION` blocks with the ID `riff`.
+
+use std::{
+    convert::TryInto,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+mod stream_info;
+mod vorbis;
+
+use crate::{
+    error::{Error, Result},
+    wav::{self, FormatChunk, InstrumentChunk, SampleFormat, SamplerChunk},
+};
+
+pub use self::{stream_info::StreamInfo, vorbis::VorbisComment};
+
+const STREAMINFO: u8 = 0;
+const PADDING: u8 = 1;
+const APPLICATION: u8 = 2;
+const VORBIS_COMMENT: u8 = 4;
+
+/// Flag set in the header of the last metadata block.
+const LAST_BLOCK: u8 = 0x80;
+
+/// Application ID of blocks holding chunks from a WAV file.
+const RIFF_APPLICATION: &[u8; 4] = b"riff";
+
+/// Amount of padding added when the metadata has to grow, so later edits can
+/// be made without moving the audio again.
+const PADDING_LEN: u32 = 4096;
+
+/// Check whether the start of a file looks like a FLAC file.
+pub fn is_flac(header: &[u8]) -> bool {
+    header.starts_with(b"fLaC")
+}
+
+pub struct Flac<F> {
+    file: F,
+    blocks: Vec<Block>,
+    /// Offset of the first audio frame, just after the last metadata block.
+    audio_offset: u64,
+}
+
+/// Location of a metadata block in the file.
+#[derive(Copy, Clone, Debug)]
+struct Block {
+    block_type: u8,
+    /// Offset of the block header.
+    offset: u64,
+    len: u32,
+}
+
+impl<F: Read + Seek> Flac<F> {
+    pub fn new(mut file: F) -> Result<Self> {
+        let mut header = [0; 4];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header)
+            .map_err(Error::header("FLAC"))?;
+
+        if !is_flac(&header) {
+            return Err(Error::NotRecognized { format: "FLAC" });
+        }
+
+        let mut flac = Self {
+            file,
+            blocks: Vec::new(),
+            audio_offset: 4,
+        };
+        flac.scan_blocks()?;
+
+        match flac.blocks.first() {
+            Some(block) if block.block_type == STREAMINFO => Ok(flac),
+            _ => Err(Error::MissingBlock { name: "STREAMINFO" }),
+        }
+    }
+
+    pub fn get_stream_info(&mut self) -> Result<StreamInfo> {
+        let block = self.blocks[0];
+        StreamInfo::from_bytes(&self.read_block(&block)?).map_err(|e| e.at_offset(block.offset))
+    }
+
+    pub fn get_vorbis_comment(&mut self) -> Result<Option<VorbisComment>> {
+        match self.find_block(VORBIS_COMMENT) {
+            Some(block) => VorbisComment::from_bytes(&self.read_block(&block)?)
+                .map(Some)
+                .map_err(|e| e.at_offset(block.offset)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_sampler_chunk(&mut self) -> Result<Option<SamplerChunk>> {
+        match self.read_riff_chunk(SamplerChunk::ID)? {
+            Some(data) => SamplerChunk::from_bytes(&data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_instrument_chunk(&mut self) -> Result<Option<InstrumentChunk>> {
+        match self.read_riff_chunk(InstrumentChunk::ID)? {
+            Some(data) => InstrumentChunk::from_bytes(&data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the IDs of the WAV chunks kept in `APPLICATION` blocks, in the
+    /// order they appeared in the original file.
+    pub fn riff_chunk_ids(&mut self) -> Result<Vec<[u8; 4]>> {
+        Ok(self
+            .read_riff_blocks()?
+            .iter()
+            .skip(1)
+            .map(|(_, data)| data[4..8].try_into().unwrap())
+            .collect())
+    }
+
+    /// Read the contents of the WAV chunk with the given ID.
+    fn read_riff_chunk(&mut self, id: &[u8; 4]) -> Result<Option<Vec<u8>>> {
+        let blocks = self.read_riff_blocks()?;
+
+        Ok(blocks
+            .iter()
+            .skip(1)
+            .find(|(_, data)| &data[4..8] == id)
+            .map(|(_, data)| {
+                let len = (read_u32_le(data, 8) as usize).min(data.len() - 12);
+                data[12..12 + len].to_vec()
+            }))
+    }
+
+    /// Read every `APPLICATION` block holding part of a WAV file, along with
+    /// its index. The first holds the RIFF header and each of the others holds
+    /// a single chunk, so each is at least 12 bytes long.
+    fn read_riff_blocks(&mut self) -> Result<Vec<(usize, Vec<u8>)>> {
+        let mut riff_blocks = Vec::new();
+
+        for (index, block) in self.blocks.clone().iter().enumerate() {
+            if block.block_type != APPLICATION || block.len < 12 {
+                continue;
+            }
+
+            let data = self.read_block(block)?;
+
+            if &data[..4] == RIFF_APPLICATION {
+                riff_blocks.push((index, data));
+            }
+        }
+
+        Ok(riff_blocks)
+    }
+
+    fn find_block(&self, block_type: u8) -> Option<Block> {
+        self.blocks
+            .iter()
+            .find(|block| block.block_type == block_type)
+            .copied()
+    }
+
+    fn read_block(&mut self, block: &Block) -> Result<Vec<u8>> {
+        let mut data = vec![0; block.len as usize];
+        self.file.seek(SeekFrom::Start(block.offset + 4))?;
+        self.file.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    /// Find the metadata blocks, which run until the block flagged as the last
+    /// one.
+    fn scan_blocks(&mut self) -> Result<()> {
+        let mut offset = 4;
+        self.blocks.clear();
+
+        loop {
+            let mut header = [0; 4];
+            self.file.seek(SeekFrom::Start(offset))?;
+            self.file.read_exact(&mut header)?;
+
+            let block = Block {
+                block_type: header[0] & !LAST_BLOCK,
+                offset,
+                len: u32::from_be_bytes([0, header[1], header[2], header[3]]),
+            };
+            self.blocks.push(block);
+            offset += 4 + block.len as u64;
+
+            if header[0] & LAST_BLOCK != 0 {
+                break;
+            }
+        }
+
+        self.audio_offset = offset;
+
+        Ok(())
+    }
+}
+
+impl<F: Read + Seek + Write> Flac<F> {
+    pub fn update_vorbis_comment(&mut self, f: impl FnOnce(&mut VorbisComment)) -> Result<()> {
+        let mut comment = self.get_vorbis_comment()?.unwrap_or_default();
+        f(&mut comment);
+
+        let mut blocks = self.read_blocks()?;
+        let data = comment.to_bytes();
+
+        match blocks
+            .iter_mut()
+            .find(|(block_type, _)| *block_type == VORBIS_COMMENT)
+        {
+            Some(block) => block.1 = data,
+            None => blocks.push((VORBIS_COMMENT, data)),
+        }
+
+        self.write_blocks(&blocks)
+    }
+
+    pub fn update_sampler_chunk(&mut self, f: impl FnOnce(&mut SamplerChunk)) -> Result<()> {
+        let mut chunk = self.get_sampler_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+        self.write_riff_chunk(SamplerChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn update_instrument_chunk(&mut self, f: impl FnOnce(&mut InstrumentChunk)) -> Result<()> {
+        let mut chunk = self.get_instrument_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+        self.write_riff_chunk(InstrumentChunk::ID, &chunk.to_bytes())
+    }
+
+    /// Replace or add a WAV chunk in the `APPLICATION` blocks.
+    ///
+    /// New chunks go just before the `data` chunk. If the file has no WAV
+    /// chunks at all, the RIFF header and `fmt ` and `data` chunks are made up
+    /// from the stream info, so that decoding the file with
+    /// `flac --keep-foreign-metadata` gives back a complete WAV file.
+    fn write_riff_chunk(&mut self, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        let riff_blocks = self.read_riff_blocks()?;
+        let mut blocks = self.read_blocks()?;
+        let block = riff_block(id, data)?;
+
+        if riff_blocks.is_empty() {
+            let info = self.get_stream_info()?;
+            let format = FormatChunk::new(
+                SampleFormat::Pcm,
+                info.channels(),
+                info.sample_rate(),
+                info.bits_per_sample(),
+            )?;
+            let data_len = info
+                .total_samples()
+                .map(|samples| samples * format.block_align() as u64)
+                .and_then(|len| len.try_into().ok())
+                .ok_or_else(|| {
+                    Error::Unsupported("unknown or too large audio size for a WAV file".to_owned())
+                })?;
+
+            let mut header = RIFF_APPLICATION.to_vec();
+            header.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+            let mut data_header = RIFF_APPLICATION.to_vec();
+            data_header.extend_from_slice(b"data");
+            data_header.extend_from_slice(&u32::to_le_bytes(data_len));
+
+            blocks.push((APPLICATION, header));
+            blocks.push((
+                APPLICATION,
+                riff_block(FormatChunk::ID, &format.to_bytes())?,
+            ));
+            blocks.push((APPLICATION, block));
+            blocks.push((APPLICATION, data_header));
+        } else {
+            // Indices of the blocks after the padding ones were removed.
+            let indices = riff_blocks
+                .iter()
+                .map(|(index, _)| self.block_index_without_padding(*index))
+                .collect::<Vec<_>>();
+            let existing = riff_blocks
+                .iter()
+                .zip(&indices)
+                .skip(1)
+                .find(|((_, data), _)| &data[4..8] == id);
+
+            match existing {
+                Some((_, index)) => blocks[*index].1 = block,
+                None => {
+                    let data_index = riff_blocks
+                        .iter()
+                        .zip(&indices)
+                        .skip(1)
+                        .find(|((_, data), _)| &data[4..8] == b"data")
+                        .map(|(_, index)| *index)
+                        .unwrap_or(indices[indices.len() - 1] + 1);
+                    blocks.insert(data_index, (APPLICATION, block));
+                }
+            }
+        }
+
+        update_riff_size(&mut blocks)?;
+        self.write_blocks(&blocks)
+    }
+
+    /// Read the type and contents of every block other than padding.
+    fn read_blocks(&mut self) -> Result<Vec<(u8, Vec<u8>)>> {
+        let mut blocks = Vec::new();
+
+        for block in self.blocks.clone() {
+            if block.block_type != PADDING {
+                blocks.push((block.block_type, self.read_block(&block)?));
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    fn block_index_without_padding(&self, index: usize) -> usize {
+        self.blocks[..index]
+            .iter()
+            .filter(|block| block.block_type != PADDING)
+            .count()
+    }
+
+    /// Replace all metadata blocks with the given ones.
+    ///
+    /// The blocks are written in place if they fit in the space taken by the
+    /// existing metadata, with any space left over turned into padding.
+    /// Otherwise the audio is moved along to make room, leaving some padding
+    /// for future edits.
+    fn write_blocks(&mut self, blocks: &[(u8, Vec<u8>)]) -> Result<()> {
+        let available = self.audio_offset - 4;
+        let needed = blocks
+            .iter()
+            .map(|(_, data)| 4 + data.len() as u64)
+            .sum::<u64>();
+
+        let padding = if needed == available {
+            None
+        } else if needed + 4 <= available {
+            Some(available - needed - 4)
+        } else {
+            self.move_audio(needed + 4 + PADDING_LEN as u64 - available)?;
+            Some(PADDING_LEN as u64)
+        };
+
+        let mut bytes = Vec::new();
+
+        for (i, (block_type, data)) in blocks.iter().enumerate() {
+            let last = padding.is_none() && i == blocks.len() - 1;
+            write_block_to(&mut bytes, *block_type, data, last)?;
+        }
+
+        if let Some(padding) = padding {
+            write_block_to(&mut bytes, PADDING, &vec![0; padding as usize], true)?;
+        }
+
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&bytes)?;
+        self.scan_blocks()
+    }
+
+    /// Move the audio frames towards the end of the file.
+    fn move_audio(&mut self, distance: u64) -> Result<()> {
+        wav::move_tail(&mut self.file, self.audio_offset, distance)?;
+        self.audio_offset += distance;
+
+        Ok(())
+    }
+}
+
+/// Build the contents of an `APPLICATION` block holding a single WAV chunk.
+fn riff_block(id: &[u8; 4], data: &[u8]) -> Result<Vec<u8>> {
+    let mut block = RIFF_APPLICATION.to_vec();
+    wav::write_chunk_to(&mut block, id, data)?;
+    Ok(block)
+}
+
+/// Set the size in the RIFF header to match the chunks kept in the blocks,
+/// counting the full size of the audio for the `data` chunk.
+fn update_riff_size(blocks: &mut [(u8, Vec<u8>)]) -> Result<()> {
+    let mut riff_blocks = blocks.iter_mut().filter(|(block_type, data)| {
+        *block_type == APPLICATION && data.len() >= 12 && &data[..4] == RIFF_APPLICATION
+    });
+
+    let header = match riff_blocks.next() {
+        Some((_, header)) if &header[4..8] == b"RIFF" => header,
+        _ => {
+            return Err(Error::Unsupported(
+                "unsupported foreign metadata".to_owned(),
+            ))
+        }
+    };
+
+    let mut size = 4;
+
+    for (_, data) in riff_blocks {
+        size += match &data[4..8] {
+            b"data" => 8 + wav::padded_len(read_u32_le(data, 8) as u64),
+            _ => data.len() as u64 - 4,
+        };
+    }
+
+    let size: u32 = size
+        .try_into()
+        .map_err(|_| Error::TooLarge("RIFF size too large"))?;
+    header[8..12].copy_from_slice(&size.to_le_bytes());
+
+    Ok(())
+}
+
+/// Write a metadata block including its header.
+fn write_block_to(mut writer: impl Write, block_type: u8, data: &[u8], last: bool) -> Result<()> {
+    if data.len() >= 1 << 24 {
+        return Err(Error::TooLarge("metadata block too large"));
+    }
+
+    let len = (data.len() as u32).to_be_bytes();
+    let flag = if last { LAST_BLOCK } else { 0 };
+
+    writer.write_all(&[block_type | flag, len[1], len[2], len[3]])?;
+    writer.write_all(data)?;
+
+    Ok(())
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi;
+    use std::io::Cursor;
+
+    /// 44100 Hz, stereo, 16 bits per sample, 1000 samples.
+    fn stream_info_bytes() -> Vec<u8> {
+        let mut bytes = vec![0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0];
+        bytes.extend_from_slice(&[0x0a, 0xc4, 0x42, 0xf0, 0x00, 0x00, 0x03, 0xe8]);
+        bytes.extend_from_slice(&[0; 16]);
+        bytes
+    }
+
+    fn flac_with_blocks(blocks: &[(u8, Vec<u8>)], audio: &[u8]) -> Vec<u8> {
+        let mut file = b"fLaC".to_vec();
+
+        for (i, (block_type, data)) in blocks.iter().enumerate() {
+            write_block_to(&mut file, *block_type, data, i == blocks.len() - 1).unwrap();
+        }
+
+        file.extend_from_slice(audio);
+        file
+    }
+
+    fn audio() -> Vec<u8> {
+        (0..100_000).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn read_foreign_metadata() {
+        let mut sampler = SamplerChunk::default();
+        sampler.set_midi_unity_note(midi::Note::from(48));
+        let mut comment = Vec::new();
+        comment.extend_from_slice(&3u32.to_le_bytes());
+        comment.extend_from_slice(b"foo");
+        comment.extend_from_slice(&1u32.to_le_bytes());
+        comment.extend_from_slice(&10u32.to_le_bytes());
+        comment.extend_from_slice(b"TITLE=Kick");
+
+        let header = b"riffRIFF\x00\x10\0\0WAVE".to_vec();
+
+        let bytes = flac_with_blocks(
+            &[
+                (STREAMINFO, stream_info_bytes()),
+                (VORBIS_COMMENT, comment),
+                (APPLICATION, header),
+                (
+                    APPLICATION,
+                    riff_block(b"smpl", &sampler.to_bytes()).unwrap(),
+                ),
+                (APPLICATION, b"riffdata\xa0\x0f\0\0".to_vec()),
+                (PADDING, vec![0; 10]),
+            ],
+            &audio(),
+        );
+
+        let mut flac = Flac::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(flac.get_stream_info().unwrap().sample_rate(), 44100);
+        assert_eq!(
+            flac.get_vorbis_comment().unwrap().unwrap().get("title"),
+            Some("Kick")
+        );
+        assert_eq!(flac.get_sampler_chunk().unwrap(), Some(sampler));
+        assert_eq!(flac.get_instrument_chunk().unwrap(), None);
+        assert_eq!(flac.riff_chunk_ids().unwrap(), vec![*b"smpl", *b"data"]);
+    }
+
+    #[test]
+    fn update_sampler_chunk_in_padding() {
+        let bytes = flac_with_blocks(
+            &[(STREAMINFO, stream_info_bytes()), (PADDING, vec![0; 8192])],
+            &audio(),
+        );
+        let len = bytes.len();
+
+        let mut flac = Flac::new(Cursor::new(bytes)).unwrap();
+        flac.update_sampler_chunk(|chunk| {
+            chunk.set_midi_unity_note(midi::Note::from(60));
+        })
+        .unwrap();
+
+        let bytes = flac.file.into_inner();
+        assert_eq!(bytes.len(), len);
+        assert_eq!(bytes[len - 100_000..], audio()[..]);
+
+        let mut flac = Flac::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            flac.get_sampler_chunk().unwrap().unwrap().midi_unity_note(),
+            midi::Note::from(60)
+        );
+        assert_eq!(
+            flac.riff_chunk_ids().unwrap(),
+            vec![*b"fmt ", *b"smpl", *b"data"]
+        );
+
+        // The made up WAV file has 1000 frames of 4 bytes.
+        let blocks = flac.read_riff_blocks().unwrap();
+        let size = 4 + 8 + 16 + 8 + 36 + 8 + 4000;
+        assert_eq!(read_u32_le(&blocks[0].1, 8), size);
+        assert_eq!(read_u32_le(&blocks[3].1, 8), 4000);
+        assert_eq!(flac.blocks.last().unwrap().block_type, PADDING);
+    }
+
+    #[test]
+    fn update_grows_metadata() {
+        let bytes = flac_with_blocks(&[(STREAMINFO, stream_info_bytes())], &audio());
+
+        let mut flac = Flac::new(Cursor::new(bytes)).unwrap();
+        flac.update_vorbis_comment(|comment| comment.set("TITLE", "Snare"))
+            .unwrap();
+        flac.update_instrument_chunk(|chunk| chunk.set_gain(-3))
+            .unwrap();
+        flac.update_sampler_chunk(|chunk| {
+            chunk.set_midi_unity_note(midi::Note::from(62));
+        })
+        .unwrap();
+
+        let bytes = flac.file.into_inner();
+        assert_eq!(bytes[bytes.len() - 100_000..], audio()[..]);
+
+        let mut flac = Flac::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            flac.get_vorbis_comment().unwrap().unwrap().get("title"),
+            Some("Snare")
+        );
+        assert_eq!(flac.get_instrument_chunk().unwrap().unwrap().gain(), -3);
+        assert_eq!(
+            flac.riff_chunk_ids().unwrap(),
+            vec![*b"fmt ", *b"inst", *b"smpl", *b"data"]
+        );
+    }
+}
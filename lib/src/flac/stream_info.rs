@@ -0,0 +1,102 @@
+use crate::error::{Error, Result};
+use std::convert::TryInto;
+
+/// Stream info (`STREAMINFO`) block, describing the audio stream. Every FLAC
+/// file starts with one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamInfo {
+    min_block_size: u16,
+    max_block_size: u16,
+    min_frame_size: u32,
+    max_frame_size: u32,
+    sample_rate: u32,
+    channels: u8,
+    bits_per_sample: u8,
+    total_samples: u64,
+    md5: [u8; 16],
+}
+
+impl StreamInfo {
+    const LEN: usize = 34;
+
+    /// Parse a stream info block from the block contents, not including the
+    /// block header.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::LEN {
+            return Err(Error::InvalidBlock {
+                name: "STREAMINFO",
+                offset: None,
+            });
+        }
+
+        let packed = u64::from_be_bytes(bytes[10..18].try_into().unwrap());
+
+        Ok(Self {
+            min_block_size: u16::from_be_bytes([bytes[0], bytes[1]]),
+            max_block_size: u16::from_be_bytes([bytes[2], bytes[3]]),
+            min_frame_size: u32::from_be_bytes([0, bytes[4], bytes[5], bytes[6]]),
+            max_frame_size: u32::from_be_bytes([0, bytes[7], bytes[8], bytes[9]]),
+            sample_rate: (packed >> 44) as u32,
+            channels: ((packed >> 41) & 0x7) as u8 + 1,
+            bits_per_sample: ((packed >> 36) & 0x1f) as u8 + 1,
+            total_samples: packed & 0xf_ffff_ffff,
+            md5: bytes[18..34].try_into().unwrap(),
+        })
+    }
+
+    /// Smallest and largest number of samples per channel in a block.
+    pub fn block_size(&self) -> (u16, u16) {
+        (self.min_block_size, self.max_block_size)
+    }
+
+    /// Smallest and largest size of a frame in bytes, or 0 if unknown.
+    pub fn frame_size(&self) -> (u32, u32) {
+        (self.min_frame_size, self.max_frame_size)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    pub fn bits_per_sample(&self) -> u16 {
+        self.bits_per_sample as u16
+    }
+
+    /// Number of samples per channel in the stream, if known.
+    pub fn total_samples(&self) -> Option<u64> {
+        Some(self.total_samples).filter(|samples| *samples > 0)
+    }
+
+    /// MD5 signature of the decoded audio, or all zeros if unknown.
+    pub fn md5(&self) -> &[u8; 16] {
+        &self.md5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stream_info() {
+        let mut bytes = vec![0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x0e, 0x00, 0x3a, 0x5c];
+        // 44100 Hz, stereo, 24 bits per sample, 88200 samples.
+        bytes.extend_from_slice(&[0x0a, 0xc4, 0x43, 0x70, 0x00, 0x01, 0x58, 0x88]);
+        bytes.extend_from_slice(&[7; 16]);
+
+        let info = StreamInfo::from_bytes(&bytes).unwrap();
+        assert_eq!(info.block_size(), (4096, 4096));
+        assert_eq!(info.frame_size(), (14, 14940));
+        assert_eq!(info.sample_rate(), 44100);
+        assert_eq!(info.channels(), 2);
+        assert_eq!(info.bits_per_sample(), 24);
+        assert_eq!(info.total_samples(), Some(88200));
+        assert_eq!(info.md5(), &[7; 16]);
+
+        assert!(StreamInfo::from_bytes(&bytes[..20]).is_err());
+    }
+}
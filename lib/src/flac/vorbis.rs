@@ -0,0 +1,160 @@
+use crate::error::{Error, Result};
+use std::convert::TryInto;
+
+/// Vorbis comment (`VORBIS_COMMENT`) block, containing metadata tags as
+/// `NAME=value` pairs.
+///
+/// Tag names are case-insensitive and a tag may appear more than once, such as
+/// for a track with several artists. Tags are kept in their original order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VorbisComment {
+    vendor: String,
+    comments: Vec<(String, String)>,
+}
+
+impl VorbisComment {
+    /// Parse a Vorbis comment block from the block contents, not including the
+    /// block header.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || Error::InvalidBlock {
+            name: "VORBIS_COMMENT",
+            offset: None,
+        };
+        let mut offset = 0;
+
+        let vendor = read_string(bytes, &mut offset).ok_or_else(invalid)?;
+        let count = read_u32(bytes, &mut offset).ok_or_else(invalid)?;
+        let mut comments = Vec::new();
+
+        for _ in 0..count {
+            let comment = read_string(bytes, &mut offset).ok_or_else(invalid)?;
+
+            // A comment without a separator is invalid, but it is kept as a
+            // tag with no value rather than thrown away.
+            comments.push(match comment.split_once('=') {
+                Some((name, value)) => (name.to_owned(), value.to_owned()),
+                None => (comment, String::new()),
+            });
+        }
+
+        Ok(Self { vendor, comments })
+    }
+
+    /// Serialize the block contents, not including the block header.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_string(&mut bytes, &self.vendor);
+        bytes.extend_from_slice(&(self.comments.len() as u32).to_le_bytes());
+
+        for (name, value) in &self.comments {
+            write_string(&mut bytes, &format!("{}={}", name, value));
+        }
+
+        bytes
+    }
+
+    /// Name of the software that wrote the file.
+    pub fn vendor(&self) -> &str {
+        &self.vendor
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.comments.is_empty()
+    }
+
+    /// Iterate over the name and value of every tag.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.comments
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Get the value of the first tag with the given name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.comments
+            .iter()
+            .find(|(tag_name, _)| tag_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the value of a tag, replacing any existing values.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self
+            .comments
+            .iter()
+            .position(|(tag_name, _)| tag_name.eq_ignore_ascii_case(name))
+        {
+            Some(i) => {
+                self.comments[i].1 = value.to_owned();
+
+                // Drop any other values after the first one.
+                let mut seen = false;
+                self.comments.retain(|(tag_name, _)| {
+                    let matches = tag_name.eq_ignore_ascii_case(name);
+                    let duplicate = matches && seen;
+                    seen |= matches;
+                    !duplicate
+                });
+            }
+            None => self.comments.push((name.to_owned(), value.to_owned())),
+        }
+    }
+
+    /// Remove all values of a tag, returning true if it was present.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.comments.len();
+        self.comments
+            .retain(|(tag_name, _)| !tag_name.eq_ignore_ascii_case(name));
+        self.comments.len() != len
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: &mut usize) -> Option<u32> {
+    let value = u32::from_le_bytes(bytes.get(*offset..*offset + 4)?.try_into().unwrap());
+    *offset += 4;
+    Some(value)
+}
+
+/// Read a string prefixed with its length.
+fn read_string(bytes: &[u8], offset: &mut usize) -> Option<String> {
+    let len = read_u32(bytes, offset)? as usize;
+    let string = bytes.get(*offset..offset.checked_add(len)?)?;
+    *offset += len;
+    Some(String::from_utf8_lossy(string).into_owned())
+}
+
+fn write_string(bytes: &mut Vec<u8>, string: &str) {
+    bytes.extend_from_slice(&(string.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(string.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vorbis_comment_round_trip() {
+        let mut bytes = Vec::new();
+        write_string(&mut bytes, "reference libFLAC 1.4.3");
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        write_string(&mut bytes, "TITLE=Rhodes C3");
+        write_string(&mut bytes, "Artist=A");
+        write_string(&mut bytes, "ARTIST=B");
+
+        let mut comment = VorbisComment::from_bytes(&bytes).unwrap();
+        assert_eq!(comment.vendor(), "reference libFLAC 1.4.3");
+        assert_eq!(comment.get("title"), Some("Rhodes C3"));
+        assert_eq!(comment.iter().count(), 3);
+        assert_eq!(comment.to_bytes(), bytes);
+
+        comment.set("artist", "C");
+        assert_eq!(
+            comment.iter().collect::<Vec<_>>(),
+            vec![("TITLE", "Rhodes C3"), ("Artist", "C")]
+        );
+        assert!(comment.remove("TITLE"));
+        assert!(!comment.remove("TITLE"));
+
+        assert!(VorbisComment::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -1,3 +1,13 @@
+pub mod aiff;
+pub mod atomic_file;
+pub mod convert;
+pub mod error;
+pub mod flac;
 pub mod midi;
+mod pcm;
 pub mod sample;
+pub mod sample_file;
+pub mod tags;
 pub mod wav;
+
+pub use crate::error::{Error, Result};
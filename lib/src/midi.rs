@@ -2,7 +2,7 @@
 
 use std::{fmt, str::FromStr};
 
-const NOTE_NAMES: &'static [&'static str] = &["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+const NOTE_NAMES: &[&str] = &["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
 
 /// A MIDI note number.
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
@@ -28,10 +28,17 @@ impl FromStr for Note {
             return Ok(number.into());
         }
 
-        for (offset, name) in NOTE_NAMES.into_iter().enumerate() {
+        for (offset, name) in NOTE_NAMES.iter().enumerate() {
             if let Some(suffix) = s.strip_prefix(name) {
-                if let Ok(number) = suffix.parse::<u8>() {
-                    return Ok(Note::from(((number as usize + 2) * NOTE_NAMES.len() + offset) as u8));
+                let number = suffix.parse::<i16>().ok().and_then(|octave| {
+                    octave
+                        .checked_add(2)?
+                        .checked_mul(NOTE_NAMES.len() as i16)?
+                        .checked_add(offset as i16)
+                });
+
+                if let Some(number) = number.filter(|number| (0..=127).contains(number)) {
+                    return Ok(Note::from(number as u8));
                 }
             }
         }
@@ -58,6 +65,13 @@ mod tests {
         assert_eq!(Note::from_str("51").unwrap(), Note::from(51));
         assert_eq!(Note::from_str("A0").unwrap(), Note::from(33));
         assert_eq!(Note::from_str("C1").unwrap(), Note::from(36));
+        assert_eq!(Note::from_str("C-2").unwrap(), Note::from(0));
+        assert_eq!(Note::from_str("G#-1").unwrap(), Note::from(20));
+        assert_eq!(Note::from_str("G8").unwrap(), Note::from(127));
+        assert!(Note::from_str("G#8").is_err());
+        assert!(Note::from_str("C-3").is_err());
+        assert!(Note::from_str("C3000").is_err());
+        assert!(Note::from_str("B-32768").is_err());
     }
 
     #[test]
@@ -0,0 +1,411 @@
+//! Encoding and decoding of uncompressed sample data shared between file
+//! formats.
+
+use crate::error::{Error, Result};
+use std::io::Read;
+
+/// Reads interleaved sample frames from the audio data of a file, decoding
+/// them from the encoding used by the file.
+///
+/// Frames can be read either as `f32` normalized to the range `-1.0..=1.0`, or
+/// for integer formats as native integers. 8-bit WAV samples are converted
+/// from unsigned to signed so that silence is always 0.
+pub struct FrameReader<R> {
+    reader: R,
+    encoding: Encoding,
+    channels: usize,
+    sample_rate: u32,
+    frames_remaining: u64,
+    buffer: Vec<u8>,
+}
+
+/// How a single sample is stored.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Encoding {
+    pub(crate) sample_type: SampleType,
+
+    /// Whether samples are big-endian, as in AIFF files. 8-bit samples are
+    /// signed when big-endian and unsigned otherwise.
+    pub(crate) big_endian: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum SampleType {
+    /// Integer PCM stored in a container of the given number of bytes, with
+    /// the given number of significant bits.
+    Int {
+        bytes: usize,
+        bits: u32,
+    },
+    Float32,
+    Float64,
+}
+
+impl<R: Read> FrameReader<R> {
+    pub(crate) fn new(
+        reader: R,
+        encoding: Encoding,
+        channels: usize,
+        sample_rate: u32,
+        frames: u64,
+    ) -> Self {
+        Self {
+            reader,
+            encoding,
+            channels: channels.max(1),
+            sample_rate,
+            frames_remaining: frames,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Number of samples in each frame.
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of frames that have not been read yet.
+    pub fn frames_remaining(&self) -> u64 {
+        self.frames_remaining
+    }
+
+    /// Whether samples are integers, and so can be read with
+    /// [`read_i32`](Self::read_i32).
+    pub fn is_integer(&self) -> bool {
+        self.encoding.is_integer()
+    }
+
+    /// Number of significant bits in each sample.
+    pub fn bits_per_sample(&self) -> u32 {
+        match self.encoding.sample_type {
+            SampleType::Int { bits, .. } => bits,
+            SampleType::Float32 => 32,
+            SampleType::Float64 => 64,
+        }
+    }
+
+    /// Read as many whole frames as fit into the given buffer as interleaved
+    /// normalized samples, returning the number of frames read. Returns 0 once
+    /// all frames have been read.
+    pub fn read_f32(&mut self, buf: &mut [f32]) -> Result<usize> {
+        let frames = self.fill_buffer(buf.len())?;
+        let encoding = self.encoding;
+
+        for (sample, bytes) in buf.iter_mut().zip(self.samples()) {
+            *sample = encoding.decode_f64(bytes) as f32;
+        }
+
+        Ok(frames)
+    }
+
+    /// Like [`read_f32`](Self::read_f32), but with double precision so that
+    /// 64-bit float and 32-bit integer samples are read without any loss.
+    pub fn read_f64(&mut self, buf: &mut [f64]) -> Result<usize> {
+        let frames = self.fill_buffer(buf.len())?;
+        let encoding = self.encoding;
+
+        for (sample, bytes) in buf.iter_mut().zip(self.samples()) {
+            *sample = encoding.decode_f64(bytes);
+        }
+
+        Ok(frames)
+    }
+
+    /// Read as many whole frames as fit into the given buffer as interleaved
+    /// integer samples, returning the number of frames read. Samples keep
+    /// their original range, such as `-32768..=32767` for 16-bit audio.
+    ///
+    /// Returns an error if the samples are not integers.
+    pub fn read_i32(&mut self, buf: &mut [i32]) -> Result<usize> {
+        if !self.encoding.is_integer() {
+            return Err(Error::InvalidInput("samples are not integers".to_owned()));
+        }
+
+        let frames = self.fill_buffer(buf.len())?;
+        let encoding = self.encoding;
+
+        for (sample, bytes) in buf.iter_mut().zip(self.samples()) {
+            *sample = encoding.decode_int(bytes);
+        }
+
+        Ok(frames)
+    }
+
+    /// Read all remaining frames as interleaved normalized samples.
+    pub fn read_to_end_f32(&mut self) -> Result<Vec<f32>> {
+        let mut samples = vec![0.0; self.frames_remaining as usize * self.channels];
+        let mut offset = 0;
+
+        while offset < samples.len() {
+            let frames = self.read_f32(&mut samples[offset..])?;
+
+            if frames == 0 {
+                break;
+            }
+
+            offset += frames * self.channels;
+        }
+
+        samples.truncate(offset);
+        Ok(samples)
+    }
+
+    /// Read up to `max_samples` worth of whole frames into the internal
+    /// buffer, returning the number of frames read.
+    fn fill_buffer(&mut self, max_samples: usize) -> Result<usize> {
+        let frames = ((max_samples / self.channels) as u64).min(self.frames_remaining) as usize;
+        let len = frames * self.channels * self.encoding.sample_bytes();
+
+        self.buffer.resize(len, 0);
+        self.reader.read_exact(&mut self.buffer)?;
+        self.frames_remaining -= frames as u64;
+
+        Ok(frames)
+    }
+
+    fn samples(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.buffer.chunks_exact(self.encoding.sample_bytes())
+    }
+}
+
+impl Encoding {
+    pub(crate) fn is_integer(&self) -> bool {
+        matches!(self.sample_type, SampleType::Int { .. })
+    }
+
+    pub(crate) fn sample_bytes(&self) -> usize {
+        match self.sample_type {
+            SampleType::Int { bytes, .. } => bytes,
+            SampleType::Float32 => 4,
+            SampleType::Float64 => 8,
+        }
+    }
+
+    fn decode_f64(&self, bytes: &[u8]) -> f64 {
+        match self.sample_type {
+            SampleType::Int { bits, .. } => {
+                self.decode_int(bytes) as f64 / (1u64 << (bits - 1)) as f64
+            }
+            SampleType::Float32 => {
+                let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+
+                if self.big_endian {
+                    f32::from_be_bytes(bytes) as f64
+                } else {
+                    f32::from_le_bytes(bytes) as f64
+                }
+            }
+            SampleType::Float64 => {
+                let mut value = [0; 8];
+                value.copy_from_slice(bytes);
+
+                if self.big_endian {
+                    f64::from_be_bytes(value)
+                } else {
+                    f64::from_le_bytes(value)
+                }
+            }
+        }
+    }
+
+    /// Decode an integer sample, whose significant bits are stored in the
+    /// most significant bits of the container.
+    fn decode_int(&self, bytes: &[u8]) -> i32 {
+        let bits = match self.sample_type {
+            SampleType::Int { bits, .. } => bits,
+            _ => return 0,
+        };
+
+        if bytes.len() == 1 {
+            let value = if self.big_endian {
+                bytes[0] as i8 as i32
+            } else {
+                bytes[0] as i32 - 128
+            };
+
+            return value >> (8 - bits);
+        }
+
+        let mut value = [0; 4];
+
+        if self.big_endian {
+            value[..bytes.len()].copy_from_slice(bytes);
+            i32::from_be_bytes(value) >> (32 - bits)
+        } else {
+            value[4 - bytes.len()..].copy_from_slice(bytes);
+            i32::from_le_bytes(value) >> (32 - bits)
+        }
+    }
+
+    /// Encode a normalized sample, clipping it if it is out of range.
+    pub(crate) fn encode_f64(&self, sample: f64, out: &mut Vec<u8>) {
+        match self.sample_type {
+            SampleType::Int { bits, .. } => {
+                let scale = (1u64 << (bits - 1)) as f64;
+                let value = (sample * scale).round().clamp(-scale, scale - 1.0);
+
+                self.encode_int(value as i32, out);
+            }
+            SampleType::Float32 if self.big_endian => {
+                out.extend_from_slice(&(sample as f32).to_be_bytes())
+            }
+            SampleType::Float32 => out.extend_from_slice(&(sample as f32).to_le_bytes()),
+            SampleType::Float64 if self.big_endian => out.extend_from_slice(&sample.to_be_bytes()),
+            SampleType::Float64 => out.extend_from_slice(&sample.to_le_bytes()),
+        }
+    }
+
+    /// Encode an integer sample in the native range of an integer encoding.
+    pub(crate) fn encode_int(&self, sample: i32, out: &mut Vec<u8>) {
+        if let SampleType::Int { bytes, bits } = self.sample_type {
+            let max = (1i64 << (bits - 1)) - 1;
+            let sample = (sample as i64).clamp(-max - 1, max) as i32;
+
+            if bytes == 1 && self.big_endian {
+                out.push((sample << (8 - bits)) as u8);
+            } else if bytes == 1 {
+                out.push(((sample << (8 - bits)) + 128) as u8);
+            } else if self.big_endian {
+                out.extend_from_slice(&(sample << (32 - bits)).to_be_bytes()[..bytes]);
+            } else {
+                out.extend_from_slice(&(sample << (32 - bits)).to_le_bytes()[4 - bytes..]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wav::FormatChunk;
+
+    fn format(format_tag: u16, channels: u16, bits: u16) -> FormatChunk {
+        let block_align = channels * bits / 8;
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&format_tag.to_le_bytes());
+        bytes.extend_from_slice(&channels.to_le_bytes());
+        bytes.extend_from_slice(&8000u32.to_le_bytes());
+        bytes.extend_from_slice(&(8000 * block_align as u32).to_le_bytes());
+        bytes.extend_from_slice(&block_align.to_le_bytes());
+        bytes.extend_from_slice(&bits.to_le_bytes());
+
+        FormatChunk::from_bytes(&bytes).unwrap()
+    }
+
+    fn frame_reader<'a>(format: &FormatChunk, data: &'a [u8]) -> FrameReader<&'a [u8]> {
+        let frames = data.len() as u64 / format.block_align() as u64;
+        let encoding = format.encoding().unwrap();
+
+        FrameReader::new(
+            data,
+            encoding,
+            format.channels() as usize,
+            format.sample_rate(),
+            frames,
+        )
+    }
+
+    fn read_i32(format: &FormatChunk, data: &[u8]) -> Vec<i32> {
+        let mut reader = frame_reader(format, data);
+        let mut samples = vec![0; data.len()];
+        let frames = reader.read_i32(&mut samples).unwrap();
+
+        samples.truncate(frames * reader.channels());
+        samples
+    }
+
+    #[test]
+    fn decode_integer_pcm() {
+        assert_eq!(
+            read_i32(&format(1, 1, 8), &[0x00, 0x80, 0xff]),
+            vec![-128, 0, 127]
+        );
+        assert_eq!(
+            read_i32(
+                &format(1, 2, 16),
+                &[0x00, 0x80, 0xff, 0x7f, 0x01, 0x00, 0xff, 0xff]
+            ),
+            vec![-32768, 32767, 1, -1]
+        );
+        assert_eq!(
+            read_i32(&format(1, 1, 24), &[0x00, 0x00, 0x80, 0x56, 0x34, 0x12]),
+            vec![-8388608, 0x123456]
+        );
+        assert_eq!(
+            read_i32(&format(1, 1, 32), &[0xff, 0xff, 0xff, 0x7f]),
+            vec![i32::MAX]
+        );
+    }
+
+    #[test]
+    fn decode_normalized() {
+        let format = format(1, 3, 16);
+        let data = [
+            0x00, 0x80, 0x00, 0x40, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x80,
+        ];
+        let mut reader = frame_reader(&format, &data);
+
+        // Only whole frames are read.
+        let mut buf = [0.0; 5];
+        assert_eq!(reader.read_f32(&mut buf).unwrap(), 1);
+        assert_eq!(&buf[..3], &[-1.0, 0.5, 0.0]);
+        assert_eq!(reader.frames_remaining(), 1);
+
+        assert_eq!(reader.read_to_end_f32().unwrap(), vec![-0.5, 0.0, -1.0]);
+        assert_eq!(reader.read_f32(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn decode_float() {
+        let mut data = Vec::new();
+        data.extend_from_slice(&0.25f32.to_le_bytes());
+        data.extend_from_slice(&(-1.0f32).to_le_bytes());
+
+        let format32 = format(3, 2, 32);
+        let mut reader = frame_reader(&format32, &data);
+        assert!(!reader.is_integer());
+        assert_eq!(reader.read_to_end_f32().unwrap(), vec![0.25, -1.0]);
+        assert!(reader.read_i32(&mut [0; 2]).is_err());
+
+        let data = 0.75f64.to_le_bytes();
+        let format64 = format(3, 1, 64);
+        let mut reader = frame_reader(&format64, &data);
+        assert_eq!(reader.read_to_end_f32().unwrap(), vec![0.75]);
+    }
+
+    #[test]
+    fn big_endian_round_trip() {
+        for (sample_type, data) in &[
+            (
+                SampleType::Int { bytes: 1, bits: 8 },
+                &[0x80, 0x00, 0x7f][..],
+            ),
+            (
+                SampleType::Int { bytes: 3, bits: 24 },
+                &[0x80, 0x00, 0x00, 0x00, 0x00, 0x01, 0x7f, 0xff, 0xff][..],
+            ),
+        ] {
+            let encoding = Encoding {
+                sample_type: *sample_type,
+                big_endian: true,
+            };
+            let mut samples = [0; 3];
+            FrameReader::new(*data, encoding, 1, 8000, 3)
+                .read_i32(&mut samples)
+                .unwrap();
+
+            assert_eq!(samples[0], -(1 << (encoding.sample_bytes() * 8 - 1)));
+            assert_eq!(samples[2], (1 << (encoding.sample_bytes() * 8 - 1)) - 1);
+
+            let mut encoded = Vec::new();
+            for sample in &samples {
+                encoding.encode_int(*sample, &mut encoded);
+            }
+            assert_eq!(&encoded, data);
+        }
+    }
+}
@@ -1,37 +1,196 @@
 use std::{
     borrow::Cow,
     fs::File,
-    io,
+    io::{Read, Seek, SeekFrom},
+    ops::RangeInclusive,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use crate::{midi::Note, wav::Wav};
+use crate::{
+    aiff::{self, Aiff},
+    convert::{instrument_chunk, sample_loop, COMMENT_TAG, TEXT_TAGS},
+    error::Result,
+    flac::{self, Flac},
+    midi::Note,
+    tags::TagKey,
+    wav::{InstrumentChunk, Marker, SampleFormat, SampleLoop, Wav},
+};
 
-/// A WAV file along with sample attributes.
-#[derive(Debug)]
+/// A WAV, AIFF or FLAC file along with sample attributes.
+#[derive(Debug, Default)]
 pub struct Sample {
     path: PathBuf,
+    sample_format: Option<SampleFormat>,
+    sample_rate: Option<u32>,
+    channels: Option<u16>,
+    bit_depth: Option<u16>,
+    frame_count: Option<u64>,
     note: Option<Note>,
+    loops: Vec<SampleLoop>,
+    instrument: Option<InstrumentChunk>,
+    markers: Vec<Marker>,
+    tags: Vec<(String, String)>,
 }
 
 impl Sample {
     /// Read a sample from a file.
     ///
-    /// This opens the file, validates that it is a WAV file, and scrapes some
-    /// metadata, but doesn't load the whole sample into memory.
-    pub fn read(path: impl Into<PathBuf>) -> io::Result<Self> {
+    /// This opens the file, works out whether it is a WAV, AIFF or FLAC file from
+    /// its signature, and scrapes some metadata, but doesn't load the whole
+    /// sample into memory.
+    ///
+    /// Any error has the path of the file attached.
+    pub fn read(path: impl Into<PathBuf>) -> Result<Self> {
         let path = path.into();
-        let mut wav = Wav::new(File::open(&path)?)?;
+
+        Self::read_file(&path).map_err(|e| e.with_path(path))
+    }
+
+    fn read_file(path: &Path) -> Result<Self> {
+        let path = path.to_owned();
+        let mut file = File::open(&path)?;
+        let mut header = [0; 12];
+        let header_len = file.read(&mut header)?;
+        file.seek(SeekFrom::Start(0))?;
+
+        if aiff::is_aiff(&header[..header_len]) {
+            Self::read_aiff(path, Aiff::new(file)?)
+        } else if flac::is_flac(&header[..header_len]) {
+            Self::read_flac(path, Flac::new(file)?)
+        } else {
+            Self::read_wav(path, Wav::new(file)?)
+        }
+    }
+
+    fn read_wav(path: PathBuf, mut wav: Wav<File>) -> Result<Self> {
+        let format = wav.get_format_chunk()?;
+        let frame_count = wav.frame_count()?;
         let mut note = None;
+        let mut loops = Vec::new();
 
         if let Some(chunk) = wav.get_sampler_chunk()? {
             note = Some(chunk.midi_unity_note());
+            loops = chunk.loops().to_vec();
+        }
+
+        let instrument = wav.get_instrument_chunk()?;
+
+        if let Some(chunk) = instrument.as_ref() {
+            note = note.or_else(|| Some(chunk.unshifted_note()));
+        }
+
+        let markers = wav.get_markers()?;
+        let tags = wav
+            .get_list_info()?
+            .map(|info| {
+                info.iter()
+                    .map(|(id, value)| (tag_name(&id), value))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(Self {
+            path,
+            sample_format: format.as_ref().map(|format| format.sample_format()),
+            sample_rate: format.as_ref().map(|format| format.sample_rate()),
+            channels: format.as_ref().map(|format| format.channels()),
+            bit_depth: format.as_ref().map(|format| format.valid_bits_per_sample()),
+            frame_count,
+            note,
+            loops,
+            instrument,
+            markers,
+            tags,
+        })
+    }
+
+    fn read_aiff(path: PathBuf, mut aiff: Aiff<File>) -> Result<Self> {
+        let common = aiff.get_common_chunk()?;
+        let aiff_markers = aiff.get_marker_chunk()?.unwrap_or_default();
+        let mut sample = Self {
+            path,
+            sample_format: common.as_ref().and_then(|common| common.sample_format()),
+            sample_rate: common
+                .as_ref()
+                .map(|common| common.sample_rate().round() as u32),
+            channels: common.as_ref().map(|common| common.channels()),
+            bit_depth: common.as_ref().map(|common| common.sample_size()),
+            frame_count: common.as_ref().map(|common| common.frame_count() as u64),
+            markers: aiff_markers
+                .markers()
+                .iter()
+                .map(|marker| Marker {
+                    id: marker.id as u32,
+                    position: marker.position,
+                    label: Some(marker.name.clone()).filter(|name| !name.is_empty()),
+                })
+                .collect(),
+            ..Self::default()
+        };
+
+        if let Some(chunk) = aiff.get_instrument_chunk()? {
+            sample.note = Some(chunk.base_note());
+            sample.instrument = Some(instrument_chunk(&chunk));
+            sample.loops = [&chunk.sustain_loop, &chunk.release_loop]
+                .iter()
+                .filter_map(|aiff_loop| sample_loop(aiff_loop, &aiff_markers))
+                .collect();
+        }
+
+        for (id, info_id) in TEXT_TAGS {
+            if let Some(text) = aiff.get_text(id)? {
+                sample.tags.push((tag_name(info_id), text));
+            }
+        }
+
+        for text in aiff.get_annotations()? {
+            sample.tags.push((tag_name(COMMENT_TAG), text));
+        }
+
+        Ok(sample)
+    }
+
+    /// Read a FLAC file. Sampler metadata comes from the WAV chunks kept by
+    /// `flac --keep-foreign-metadata`, and tags from the Vorbis comments.
+    fn read_flac(path: PathBuf, mut flac: Flac<File>) -> Result<Self> {
+        let info = flac.get_stream_info()?;
+        let mut sample = Self {
+            path,
+            sample_format: Some(SampleFormat::Pcm),
+            sample_rate: Some(info.sample_rate()),
+            channels: Some(info.channels()),
+            bit_depth: Some(info.bits_per_sample()),
+            frame_count: info.total_samples(),
+            instrument: flac.get_instrument_chunk()?,
+            ..Self::default()
+        };
+
+        if let Some(chunk) = flac.get_sampler_chunk()? {
+            sample.note = Some(chunk.midi_unity_note());
+            sample.loops = chunk.loops().to_vec();
+        }
+
+        if let Some(chunk) = sample.instrument.as_ref() {
+            sample.note = sample.note.or_else(|| Some(chunk.unshifted_note()));
+        }
+
+        if let Some(comment) = flac.get_vorbis_comment()? {
+            sample.tags = comment
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        TagKey::from_vorbis_field(name).to_string(),
+                        value.to_owned(),
+                    )
+                })
+                .collect();
         }
 
-        Ok(Self { path, note })
+        Ok(sample)
     }
 
-    pub fn name(&self) -> Cow<str> {
+    pub fn name(&self) -> Cow<'_, str> {
         self.path
             .file_name()
             .map(|s| s.to_string_lossy())
@@ -42,7 +201,93 @@ impl Sample {
         self.path.as_path()
     }
 
+    /// Encoding of the samples, such as integer PCM or floating point.
+    pub fn sample_format(&self) -> Option<SampleFormat> {
+        self.sample_format
+    }
+
+    /// Number of sample frames per second.
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> Option<u16> {
+        self.channels
+    }
+
+    /// Number of significant bits in each sample.
+    pub fn bit_depth(&self) -> Option<u16> {
+        self.bit_depth
+    }
+
+    /// Number of sample frames in the audio data.
+    pub fn frame_count(&self) -> Option<u64> {
+        self.frame_count
+    }
+
+    /// Length of the audio when played back at its sample rate.
+    pub fn duration(&self) -> Option<Duration> {
+        match (self.frame_count, self.sample_rate) {
+            (Some(frames), Some(rate)) if rate > 0 => {
+                Some(Duration::from_secs_f64(frames as f64 / rate as f64))
+            }
+            _ => None,
+        }
+    }
+
     pub fn note(&self) -> Option<&Note> {
         self.note.as_ref()
     }
+
+    pub fn loops(&self) -> &[SampleLoop] {
+        &self.loops
+    }
+
+    /// Cue points in the sample along with their labels.
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Metadata tags of the sample as name and value pairs. Well-known tags use
+    /// a friendly name such as `title`.
+    pub fn tags(&self) -> &[(String, String)] {
+        &self.tags
+    }
+
+    /// Get the value of a tag by name.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Fine tuning of the sample in cents.
+    pub fn fine_tune(&self) -> Option<i8> {
+        self.instrument.as_ref().map(InstrumentChunk::fine_tune)
+    }
+
+    /// Playback gain of the sample in decibels.
+    pub fn gain(&self) -> Option<i8> {
+        self.instrument.as_ref().map(InstrumentChunk::gain)
+    }
+
+    /// Range of notes the sample is mapped to.
+    pub fn key_range(&self) -> Option<RangeInclusive<Note>> {
+        self.instrument
+            .as_ref()
+            .map(|chunk| chunk.low_note()..=chunk.high_note())
+    }
+
+    /// Range of velocities the sample is mapped to.
+    pub fn velocity_range(&self) -> Option<RangeInclusive<u8>> {
+        self.instrument
+            .as_ref()
+            .map(|chunk| chunk.low_velocity()..=chunk.high_velocity())
+    }
+}
+
+/// Get the name used for a tag, which is the friendly name if there is one.
+fn tag_name(id: &[u8; 4]) -> String {
+    TagKey::Info(*id).to_string()
 }
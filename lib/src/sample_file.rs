@@ -0,0 +1,651 @@
+//! Reading and editing sampler metadata in WAV, AIFF and FLAC files alike.
+//!
+//! Metadata is read and written as the WAV chunks it is equivalent to, so the
+//! same edits can be made whatever the format. AIFF files keep the root note,
+//! ranges and loops in their instrument and marker chunks and their tags in
+//! text chunks. FLAC files keep WAV chunks in `APPLICATION` blocks and their
+//! tags in the Vorbis comment, and tags are read and written as [`Tags`].
+
+use std::{
+    convert::TryFrom,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+use crate::{
+    aiff::{self, Aiff},
+    convert::{
+        aiff_loop_from, format_chunk, instrument_chunk, note_from_pitch_fraction,
+        pitch_fraction_from_note, sample_loop, COMMENT_TAG, TEXT_TAGS,
+    },
+    error::{Error, Result},
+    flac::{self, Flac},
+    tags::{TagKey, Tags},
+    wav::{
+        AcidChunk, BroadcastExtensionChunk, FormatChunk, InstrumentChunk, ListInfo, LoopType,
+        Marker, SampleFormat, SamplerChunk, Wav,
+    },
+};
+
+/// A WAV, AIFF or FLAC file.
+pub enum SampleFile<F> {
+    Wav(Wav<F>),
+    Aiff(Aiff<F>),
+    Flac(Flac<F>),
+}
+
+impl<F: Read + Seek> SampleFile<F> {
+    /// Open a file, working out whether it is a WAV, AIFF or FLAC file from
+    /// its signature.
+    pub fn new(mut file: F) -> Result<Self> {
+        let mut header = Vec::with_capacity(12);
+        file.seek(SeekFrom::Start(0))?;
+        file.by_ref().take(12).read_to_end(&mut header)?;
+
+        if aiff::is_aiff(&header) {
+            Aiff::new(file).map(SampleFile::Aiff)
+        } else if flac::is_flac(&header) {
+            Flac::new(file).map(SampleFile::Flac)
+        } else {
+            Wav::new(file).map(SampleFile::Wav)
+        }
+    }
+
+    /// Name of the container format, such as `RIFF`, `AIFF-C` or `FLAC`.
+    pub fn container(&self) -> String {
+        match self {
+            SampleFile::Wav(wav) => wav.container().to_string(),
+            SampleFile::Aiff(aiff) if aiff.is_aifc() => "AIFF-C".to_owned(),
+            SampleFile::Aiff(_) => "AIFF".to_owned(),
+            SampleFile::Flac(_) => "FLAC".to_owned(),
+        }
+    }
+
+    /// Get the WAV file, if this is one.
+    pub fn as_wav(&mut self) -> Option<&mut Wav<F>> {
+        match self {
+            SampleFile::Wav(wav) => Some(wav),
+            _ => None,
+        }
+    }
+
+    /// Get the format of the audio as a WAV format chunk. This is `None` for
+    /// compressed AIFF-C files.
+    pub fn get_format_chunk(&mut self) -> Result<Option<FormatChunk>> {
+        match self {
+            SampleFile::Wav(wav) => wav.get_format_chunk(),
+            SampleFile::Aiff(aiff) => aiff
+                .get_common_chunk()?
+                .filter(|common| common.encoding().is_ok())
+                .map(|common| format_chunk(&common))
+                .transpose(),
+            SampleFile::Flac(flac) => {
+                let info = flac.get_stream_info()?;
+
+                FormatChunk::new(
+                    SampleFormat::Pcm,
+                    info.channels(),
+                    info.sample_rate(),
+                    info.bits_per_sample(),
+                )
+                .map(Some)
+            }
+        }
+    }
+
+    /// Get the number of sample frames in the audio data, if known.
+    pub fn frame_count(&mut self) -> Result<Option<u64>> {
+        match self {
+            SampleFile::Wav(wav) => wav.frame_count(),
+            SampleFile::Aiff(aiff) => Ok(aiff
+                .get_common_chunk()?
+                .map(|common| common.frame_count() as u64)),
+            SampleFile::Flac(flac) => Ok(flac.get_stream_info()?.total_samples()),
+        }
+    }
+
+    /// Get the sampler chunk. For AIFF files this is made up from the base
+    /// note, detune and loops of the instrument chunk.
+    pub fn get_sampler_chunk(&mut self) -> Result<Option<SamplerChunk>> {
+        match self {
+            SampleFile::Wav(wav) => wav.get_sampler_chunk(),
+            SampleFile::Aiff(aiff) => {
+                let instrument = match aiff.get_instrument_chunk()? {
+                    Some(instrument) => instrument,
+                    None => return Ok(None),
+                };
+                let markers = aiff.get_marker_chunk()?.unwrap_or_default();
+
+                Ok(Some(sampler_chunk(&instrument, &markers)))
+            }
+            SampleFile::Flac(flac) => flac.get_sampler_chunk(),
+        }
+    }
+
+    pub fn get_instrument_chunk(&mut self) -> Result<Option<InstrumentChunk>> {
+        match self {
+            SampleFile::Wav(wav) => wav.get_instrument_chunk(),
+            SampleFile::Aiff(aiff) => Ok(aiff
+                .get_instrument_chunk()?
+                .map(|instrument| instrument_chunk(&instrument))),
+            SampleFile::Flac(flac) => flac.get_instrument_chunk(),
+        }
+    }
+
+    /// Get the markers in the file. For AIFF files this leaves out the markers
+    /// used by the loops, and FLAC files never have any.
+    pub fn get_markers(&mut self) -> Result<Vec<Marker>> {
+        match self {
+            SampleFile::Wav(wav) => wav.get_markers(),
+            SampleFile::Aiff(aiff) => aiff_markers(aiff),
+            SampleFile::Flac(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Get the tags. AIFF text chunks are given as their INFO equivalent, with
+    /// annotations joined into a single comment, and every Vorbis comment
+    /// field is included.
+    pub fn get_tags(&mut self) -> Result<Tags> {
+        let info = match self {
+            SampleFile::Wav(wav) => wav.get_list_info()?,
+            SampleFile::Aiff(aiff) => aiff_list_info(aiff)?,
+            SampleFile::Flac(flac) => return flac_tags(flac),
+        };
+
+        Ok(info.as_ref().map(Tags::from).unwrap_or_default())
+    }
+
+    /// Get the ACID chunk, which only WAV files can have.
+    pub fn get_acid_chunk(&mut self) -> Result<Option<AcidChunk>> {
+        match self {
+            SampleFile::Wav(wav) => wav.get_acid_chunk(),
+            _ => Ok(None),
+        }
+    }
+
+    /// Get the broadcast extension chunk, which only WAV files can have.
+    pub fn get_broadcast_extension_chunk(&mut self) -> Result<Option<BroadcastExtensionChunk>> {
+        match self {
+            SampleFile::Wav(wav) => wav.get_broadcast_extension_chunk(),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<F: Read + Seek + Write> SampleFile<F> {
+    /// Edit the sampler chunk.
+    ///
+    /// AIFF files only keep the root note, pitch fraction and up to two
+    /// forward or alternating loops that play forever, which become the
+    /// sustain and release loops. Anything else is an [`Error::Unsupported`].
+    pub fn update_sampler_chunk(&mut self, f: impl FnOnce(&mut SamplerChunk)) -> Result<()> {
+        match self {
+            SampleFile::Wav(wav) => wav.update_sampler_chunk(f),
+            SampleFile::Aiff(aiff) => {
+                let mut instrument = aiff.get_instrument_chunk()?.unwrap_or_default();
+                let old_markers = aiff.get_marker_chunk()?;
+                let mut markers = old_markers.clone().unwrap_or_default();
+                let mut sampler = sampler_chunk(&instrument, &markers);
+                f(&mut sampler);
+
+                check_aiff_loops(&sampler)?;
+
+                let loop_ids = loop_marker_ids(Some(&instrument));
+                markers
+                    .markers_mut()
+                    .retain(|marker| !loop_ids.contains(&marker.id));
+
+                let (note, detune) = note_from_pitch_fraction(
+                    sampler.midi_unity_note(),
+                    sampler.midi_pitch_fraction(),
+                );
+                instrument.set_base_note(note);
+                instrument.set_detune(detune);
+
+                let mut loops = sampler
+                    .loops()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sample_loop)| {
+                        aiff_loop_from(i, sample_loop, &mut markers, &mut Vec::new())
+                    })
+                    .collect::<Vec<_>>()
+                    .into_iter();
+                instrument.sustain_loop = loops.next().unwrap_or_default();
+                instrument.release_loop = loops.next().unwrap_or_default();
+
+                if old_markers.is_some() || !markers.markers().is_empty() {
+                    aiff.update_marker_chunk(|chunk| *chunk = markers)?;
+                }
+
+                aiff.update_instrument_chunk(|chunk| *chunk = instrument)
+            }
+            SampleFile::Flac(flac) => flac.update_sampler_chunk(f),
+        }
+    }
+
+    /// Edit the instrument chunk. For AIFF files the gain is kept in the AIFF
+    /// instrument chunk, which has a wider range.
+    pub fn update_instrument_chunk(&mut self, f: impl FnOnce(&mut InstrumentChunk)) -> Result<()> {
+        match self {
+            SampleFile::Wav(wav) => wav.update_instrument_chunk(f),
+            SampleFile::Aiff(aiff) => aiff.update_instrument_chunk(|instrument| {
+                let old = instrument_chunk(instrument);
+                let mut chunk = old.clone();
+                f(&mut chunk);
+
+                instrument.set_base_note(chunk.unshifted_note());
+                instrument.set_detune(chunk.fine_tune());
+                instrument.set_low_note(chunk.low_note());
+                instrument.set_high_note(chunk.high_note());
+                instrument.set_low_velocity(chunk.low_velocity());
+                instrument.set_high_velocity(chunk.high_velocity());
+
+                if chunk.gain() != old.gain() {
+                    instrument.set_gain(chunk.gain() as i16);
+                }
+            }),
+            SampleFile::Flac(flac) => flac.update_instrument_chunk(f),
+        }
+    }
+
+    /// Edit the markers in the file.
+    ///
+    /// AIFF markers used by the loops are kept as they are, and markers whose
+    /// IDs don't fit an AIFF marker are given new ones. FLAC files can't
+    /// store markers.
+    pub fn update_markers(&mut self, f: impl FnOnce(&mut Vec<Marker>)) -> Result<()> {
+        match self {
+            SampleFile::Wav(wav) => wav.update_markers(f),
+            SampleFile::Aiff(aiff) => {
+                let instrument = aiff.get_instrument_chunk()?;
+                let old = aiff.get_marker_chunk()?;
+                let mut markers = aiff_markers(aiff)?;
+                f(&mut markers);
+
+                let loop_ids = loop_marker_ids(instrument.as_ref());
+                let mut chunk = aiff::MarkerChunk::default();
+                chunk.markers_mut().extend(
+                    old.iter()
+                        .flat_map(|old| old.markers())
+                        .filter(|marker| loop_ids.contains(&marker.id))
+                        .cloned(),
+                );
+
+                let mut renumbered = Vec::new();
+
+                for marker in markers {
+                    let name = marker.label.unwrap_or_default();
+
+                    match u16::try_from(marker.id) {
+                        Ok(id) if id > 0 && chunk.get(id).is_none() => {
+                            chunk.markers_mut().push(aiff::Marker {
+                                id,
+                                position: marker.position,
+                                name,
+                            })
+                        }
+                        _ => renumbered.push((marker.position, name)),
+                    }
+                }
+
+                for (position, name) in renumbered {
+                    chunk.add(position, &name);
+                }
+
+                if old.is_some() || !chunk.markers().is_empty() {
+                    aiff.update_marker_chunk(|old| *old = chunk)?;
+                }
+
+                Ok(())
+            }
+            SampleFile::Flac(_) => Err(Error::Unsupported(
+                "FLAC files can't store markers".to_owned(),
+            )),
+        }
+    }
+
+    /// Edit the tags. Only tags that changed are written, and tags the format
+    /// has no equivalent for are an [`Error::Unsupported`].
+    pub fn update_tags(&mut self, f: impl FnOnce(&mut Tags)) -> Result<()> {
+        let old = self.get_tags()?;
+        let mut tags = old.clone();
+        f(&mut tags);
+
+        if tags == old {
+            return Ok(());
+        }
+
+        match self {
+            SampleFile::Wav(wav) => {
+                check_tags(&tags, "WAV", |key| matches!(key, TagKey::Info(_)))?;
+
+                wav.update_list_info(|info| {
+                    for (key, value) in changed_tags(&old, &tags) {
+                        if let TagKey::Info(id) = key {
+                            match value {
+                                Some(value) => info.set(id, value),
+                                None => {
+                                    info.remove(id);
+                                }
+                            }
+                        }
+                    }
+                })
+            }
+            SampleFile::Aiff(aiff) => {
+                check_tags(&tags, "AIFF", |key| match key {
+                    TagKey::Info(id) => {
+                        id == COMMENT_TAG || TEXT_TAGS.iter().any(|(_, tag)| *tag == id)
+                    }
+                    TagKey::Vorbis(_) => false,
+                })?;
+
+                for (key, value) in changed_tags(&old, &tags) {
+                    match key {
+                        TagKey::Info(id) if id == COMMENT_TAG => aiff.set_annotation(value)?,
+                        TagKey::Info(id) => {
+                            if let Some((chunk_id, _)) =
+                                TEXT_TAGS.iter().find(|(_, tag)| *tag == id)
+                            {
+                                aiff.set_text(chunk_id, value)?;
+                            }
+                        }
+                        TagKey::Vorbis(_) => {}
+                    }
+                }
+
+                Ok(())
+            }
+            SampleFile::Flac(flac) => flac.update_vorbis_comment(|comment| {
+                for (key, value) in changed_tags(&old, &tags) {
+                    match value {
+                        Some(value) => comment.set(&key.vorbis_field(), value),
+                        None => {
+                            comment.remove(&key.vorbis_field());
+                        }
+                    }
+                }
+            }),
+        }
+    }
+
+    /// Edit the ACID chunk, which only WAV files can have.
+    pub fn update_acid_chunk(&mut self, f: impl FnOnce(&mut AcidChunk)) -> Result<()> {
+        match self {
+            SampleFile::Wav(wav) => wav.update_acid_chunk(f),
+            _ => Err(Error::Unsupported(
+                "only WAV files can store ACID metadata".to_owned(),
+            )),
+        }
+    }
+}
+
+/// Make up a sampler chunk from an AIFF instrument chunk and its loops.
+fn sampler_chunk(instrument: &aiff::InstrumentChunk, markers: &aiff::MarkerChunk) -> SamplerChunk {
+    let mut chunk = SamplerChunk::default();
+    let (note, fraction) = pitch_fraction_from_note(instrument.base_note(), instrument.detune());
+    chunk.set_midi_unity_note(note);
+    chunk.set_midi_pitch_fraction(fraction);
+    chunk.loops_mut().extend(
+        [&instrument.sustain_loop, &instrument.release_loop]
+            .iter()
+            .filter_map(|aiff_loop| sample_loop(aiff_loop, markers)),
+    );
+    chunk
+}
+
+/// Check that the loops of a sampler chunk can be stored in an AIFF file.
+fn check_aiff_loops(sampler: &SamplerChunk) -> Result<()> {
+    if sampler.loops().len() > 2 {
+        return Err(Error::Unsupported(
+            "AIFF files can only have a sustain and a release loop".to_owned(),
+        ));
+    }
+
+    for sample_loop in sampler.loops() {
+        if !matches!(
+            sample_loop.loop_type,
+            LoopType::Forward | LoopType::Alternating
+        ) {
+            return Err(Error::Unsupported(format!(
+                "AIFF files can't store {} loops",
+                sample_loop.loop_type
+            )));
+        }
+
+        if sample_loop.play_count != 0 {
+            return Err(Error::Unsupported(
+                "AIFF loops can't have a play count".to_owned(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// IDs of the markers used by the enabled loops of an AIFF instrument chunk.
+fn loop_marker_ids(instrument: Option<&aiff::InstrumentChunk>) -> Vec<u16> {
+    instrument
+        .iter()
+        .flat_map(|instrument| [&instrument.sustain_loop, &instrument.release_loop])
+        .filter(|aiff_loop| aiff_loop.is_enabled())
+        .flat_map(|aiff_loop| [aiff_loop.begin_marker_id, aiff_loop.end_marker_id])
+        .collect()
+}
+
+fn aiff_markers<F: Read + Seek>(aiff: &mut Aiff<F>) -> Result<Vec<Marker>> {
+    let instrument = aiff.get_instrument_chunk()?;
+    let markers = aiff.get_marker_chunk()?.unwrap_or_default();
+    let loop_ids = loop_marker_ids(instrument.as_ref());
+
+    Ok(markers
+        .markers()
+        .iter()
+        .filter(|marker| !loop_ids.contains(&marker.id))
+        .map(|marker| Marker {
+            id: marker.id as u32,
+            position: marker.position,
+            label: Some(marker.name.clone()).filter(|name| !name.is_empty()),
+        })
+        .collect())
+}
+
+fn aiff_list_info<F: Read + Seek>(aiff: &mut Aiff<F>) -> Result<Option<ListInfo>> {
+    let mut info = ListInfo::default();
+
+    for (id, tag) in TEXT_TAGS.iter() {
+        if let Some(text) = aiff.get_text(id)? {
+            info.set(tag, &text);
+        }
+    }
+
+    let annotations = aiff.get_annotations()?;
+
+    if !annotations.is_empty() {
+        info.set(COMMENT_TAG, &annotations.join("\n"));
+    }
+
+    Ok(Some(info).filter(|info| !info.is_empty()))
+}
+
+fn flac_tags<F: Read + Seek>(flac: &mut Flac<F>) -> Result<Tags> {
+    let mut tags = Tags::default();
+
+    if let Some(comment) = flac.get_vorbis_comment()? {
+        for (name, value) in comment.iter() {
+            let key = TagKey::from_vorbis_field(name);
+
+            // Only the first value of a field is used, as with INFO tags.
+            if tags.get(&key).is_none() {
+                tags.set(key, value);
+            }
+        }
+    }
+
+    Ok(tags)
+}
+
+/// Tags that differ between two sets of tags, along with the new value, or
+/// `None` if the tag was removed.
+fn changed_tags<'a>(old: &'a Tags, new: &'a Tags) -> Vec<(&'a TagKey, Option<&'a str>)> {
+    let removed = old
+        .iter()
+        .filter(|(key, _)| new.get(key).is_none())
+        .map(|(key, _)| (key, None));
+    let set = new
+        .iter()
+        .filter(|(key, value)| old.get(key) != Some(*value))
+        .map(|(key, value)| (key, Some(value)));
+
+    removed.chain(set).collect()
+}
+
+/// Check that every tag is one the format can store.
+fn check_tags(tags: &Tags, format: &str, supported: impl Fn(&TagKey) -> bool) -> Result<()> {
+    match tags.iter().find(|(key, _)| !supported(key)) {
+        Some((key, _)) => Err(Error::Unsupported(format!(
+            "{} files can't store the {} tag",
+            format, key
+        ))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        aiff::{AiffWriter, CommonChunk},
+        midi::Note,
+        wav::SampleLoop,
+    };
+    use std::io::Cursor;
+
+    fn aiff_file() -> Vec<u8> {
+        let mut writer =
+            AiffWriter::new(Cursor::new(Vec::new()), CommonChunk::new(1, 44100, 16)).unwrap();
+        writer.write_text(aiff::NAME, "Pad").unwrap();
+        writer.write_i32(&[0; 100]).unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn edit_aiff_as_wav_chunks() {
+        let mut file = SampleFile::new(Cursor::new(aiff_file())).unwrap();
+        assert_eq!(file.container(), "AIFF");
+        assert_eq!(file.frame_count().unwrap(), Some(100));
+        assert_eq!(
+            file.get_format_chunk().unwrap().unwrap().sample_rate(),
+            44100
+        );
+
+        file.update_sampler_chunk(|sampler| {
+            sampler.set_midi_unity_note(Note::from(48));
+            sampler.loops_mut().push(SampleLoop::new(10, 89));
+        })
+        .unwrap();
+        file.update_markers(|markers| {
+            markers.push(Marker {
+                id: 70000,
+                position: 5,
+                label: Some("Hit".to_owned()),
+            })
+        })
+        .unwrap();
+        file.update_tags(|tags| {
+            tags.remove(&TagKey::Info(*b"INAM"));
+            tags.set(TagKey::Info(*b"ICMT"), "Soft");
+        })
+        .unwrap();
+        file.update_instrument_chunk(|instrument| instrument.set_gain(-6))
+            .unwrap();
+
+        let sampler = file.get_sampler_chunk().unwrap().unwrap();
+        assert_eq!(sampler.midi_unity_note(), Note::from(48));
+        assert_eq!(sampler.loops(), [SampleLoop::new(10, 89)]);
+
+        let markers = file.get_markers().unwrap();
+        assert_eq!(markers.len(), 1);
+        assert_eq!(markers[0].position, 5);
+        assert_eq!(markers[0].label.as_deref(), Some("Hit"));
+
+        let tags = file.get_tags().unwrap();
+        assert_eq!(tags.get(&TagKey::Info(*b"INAM")), None);
+        assert_eq!(tags.get(&TagKey::Info(*b"ICMT")), Some("Soft"));
+        assert_eq!(file.get_instrument_chunk().unwrap().unwrap().gain(), -6);
+
+        assert!(matches!(
+            file.update_tags(|tags| tags.set(TagKey::Info(*b"IGNR"), "Ambient")),
+            Err(Error::Unsupported(_))
+        ));
+        assert!(matches!(
+            file.update_sampler_chunk(|sampler| {
+                sampler.loops_mut()[0].loop_type = LoopType::Backward
+            }),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn aiff_with_oversized_frames() {
+        let aiff = AiffWriter::new(Cursor::new(Vec::new()), CommonChunk::new(30000, 44100, 24))
+            .unwrap()
+            .finish()
+            .unwrap()
+            .into_inner();
+        let mut file = SampleFile::new(Cursor::new(aiff)).unwrap();
+
+        assert!(matches!(
+            file.get_format_chunk(),
+            Err(Error::InvalidChunk { id, .. }) if &id == b"COMM"
+        ));
+    }
+
+    #[test]
+    fn edit_flac_as_wav_chunks() {
+        // Stream info for 44100 Hz, stereo, 16 bits per sample, 1000 samples.
+        let mut bytes = b"fLaC\x80\x00\x00\x22".to_vec();
+        bytes.extend_from_slice(&[0x10, 0x00, 0x10, 0x00, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[0x0a, 0xc4, 0x42, 0xf0, 0x00, 0x00, 0x03, 0xe8]);
+        bytes.extend_from_slice(&[0; 16]);
+        bytes.extend_from_slice(&[0xff, 0xf8, 0x69, 0x08]);
+
+        let mut file = SampleFile::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(file.container(), "FLAC");
+        assert_eq!(file.frame_count().unwrap(), Some(1000));
+        assert_eq!(file.get_format_chunk().unwrap().unwrap().channels(), 2);
+
+        file.update_sampler_chunk(|sampler| sampler.set_midi_unity_note(Note::from(48)))
+            .unwrap();
+        file.update_tags(|tags| {
+            tags.set(TagKey::Info(*b"INAM"), "Kick");
+            tags.set(TagKey::Info(*b"IKEY"), "drums");
+            tags.set("replaygain_track_gain".parse().unwrap(), "-3 dB");
+        })
+        .unwrap();
+
+        assert_eq!(
+            file.get_sampler_chunk().unwrap().unwrap().midi_unity_note(),
+            Note::from(48)
+        );
+
+        let tags = file.get_tags().unwrap();
+        assert_eq!(tags.get(&TagKey::Info(*b"INAM")), Some("Kick"));
+        assert_eq!(tags.get(&TagKey::Info(*b"IKEY")), Some("drums"));
+        assert_eq!(
+            tags.get(&TagKey::Vorbis("REPLAYGAIN_TRACK_GAIN".to_owned())),
+            Some("-3 dB")
+        );
+
+        if let SampleFile::Flac(flac) = &mut file {
+            let comment = flac.get_vorbis_comment().unwrap().unwrap();
+            assert_eq!(comment.get("TITLE"), Some("Kick"));
+            assert_eq!(comment.get("IKEY"), Some("drums"));
+        }
+
+        assert!(matches!(
+            file.update_markers(|markers| markers.clear()),
+            Err(Error::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+//! Metadata tags of any sample file, such as the title and artist.
+//!
+//! WAV files store tags as INFO tags, and AIFF files have text chunks that
+//! are equivalent to some of them. FLAC files store any number of named
+//! fields in their Vorbis comment, some of which are equivalent to INFO tags
+//! and the rest of which are kept by their field name.
+
+use std::{borrow::Cow, convert::TryInto, fmt, str::FromStr};
+
+use crate::wav::ListInfo;
+
+/// INFO tags along with the Vorbis comment fields they are stored in.
+const VORBIS_TAGS: &[(&[u8; 4], &str)] = &[
+    (b"INAM", "TITLE"),
+    (b"IART", "ARTIST"),
+    (b"ICMT", "COMMENT"),
+    (b"IGNR", "GENRE"),
+    (b"ICOP", "COPYRIGHT"),
+    (b"ISFT", "ENCODER"),
+    (b"ICRD", "DATE"),
+    (b"IPRD", "ALBUM"),
+    (b"IPRT", "TRACKNUMBER"),
+];
+
+/// The key of a tag.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TagKey {
+    /// A four-character INFO ID, such as `INAM`.
+    Info([u8; 4]),
+    /// The name of a Vorbis comment field that has no INFO equivalent, in
+    /// uppercase.
+    Vorbis(String),
+}
+
+impl TagKey {
+    /// Get the key of a Vorbis comment field. Fields with an INFO equivalent,
+    /// and fields named like an INFO ID, use the INFO ID.
+    pub fn from_vorbis_field(name: &str) -> Self {
+        if let Some((id, _)) = VORBIS_TAGS
+            .iter()
+            .find(|(_, field)| field.eq_ignore_ascii_case(name))
+        {
+            return TagKey::Info(**id);
+        }
+
+        let name = name.to_ascii_uppercase();
+
+        match name.as_bytes().try_into() {
+            Ok(id) if ListInfo::tag_id(&name) == Some(id) => TagKey::Info(id),
+            _ => TagKey::Vorbis(name),
+        }
+    }
+
+    /// Name of the Vorbis comment field the tag is stored in.
+    pub fn vorbis_field(&self) -> Cow<'_, str> {
+        match self {
+            TagKey::Info(id) => match VORBIS_TAGS.iter().find(|(tag, _)| *tag == id) {
+                Some((_, field)) => Cow::Borrowed(field),
+                None => String::from_utf8_lossy(id),
+            },
+            TagKey::Vorbis(name) => Cow::Borrowed(name),
+        }
+    }
+
+    /// The INFO ID or Vorbis field name, without using any friendly name.
+    pub fn id(&self) -> Cow<'_, str> {
+        match self {
+            TagKey::Info(id) => String::from_utf8_lossy(id),
+            TagKey::Vorbis(name) => Cow::Borrowed(name),
+        }
+    }
+}
+
+/// Parse a key from an INFO ID (such as `INAM`), a friendly name (such as
+/// `title`) or the name of a Vorbis comment field.
+impl FromStr for TagKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(id) = ListInfo::tag_id(s) {
+            return Ok(TagKey::Info(id));
+        }
+
+        // Field names are printable ASCII other than `=`.
+        if !s.is_empty() && s.bytes().all(|b| (0x20..=0x7d).contains(&b) && b != b'=') {
+            Ok(TagKey::from_vorbis_field(s))
+        } else {
+            Err(format!("invalid tag: {}", s))
+        }
+    }
+}
+
+/// Shows the friendly name of the tag if it has one.
+impl fmt::Display for TagKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TagKey::Info(id) => match ListInfo::tag_name(id) {
+                Some(name) => f.write_str(name),
+                None => f.write_str(&self.id()),
+            },
+            TagKey::Vorbis(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Tags of a file, kept in their original order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tags {
+    entries: Vec<(TagKey, String)>,
+}
+
+impl Tags {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the key and value of every tag.
+    pub fn iter(&self) -> impl Iterator<Item = (&TagKey, &str)> + '_ {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key, value.as_str()))
+    }
+
+    pub fn get(&self, key: &TagKey) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(tag_key, _)| tag_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Set the value of a tag, replacing any existing value.
+    pub fn set(&mut self, key: TagKey, value: &str) {
+        match self.entries.iter_mut().find(|(tag_key, _)| *tag_key == key) {
+            Some((_, old)) => *old = value.to_owned(),
+            None => self.entries.push((key, value.to_owned())),
+        }
+    }
+
+    /// Remove a tag, returning true if it was present.
+    pub fn remove(&mut self, key: &TagKey) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(tag_key, _)| tag_key != key);
+        self.entries.len() != len
+    }
+}
+
+impl From<&ListInfo> for Tags {
+    fn from(info: &ListInfo) -> Self {
+        Self {
+            entries: info
+                .iter()
+                .map(|(id, value)| (TagKey::Info(id), value))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tag_keys() {
+        assert_eq!("title".parse(), Ok(TagKey::Info(*b"INAM")));
+        assert_eq!("IXYZ".parse(), Ok(TagKey::Info(*b"IXYZ")));
+        assert_eq!("TrackNumber".parse(), Ok(TagKey::Info(*b"IPRT")));
+        assert_eq!("mood".parse(), Ok(TagKey::Info(*b"MOOD")));
+        assert_eq!(
+            "replaygain_track_gain".parse(),
+            Ok(TagKey::Vorbis("REPLAYGAIN_TRACK_GAIN".to_owned()))
+        );
+        assert!("".parse::<TagKey>().is_err());
+        assert!("a=b".parse::<TagKey>().is_err());
+    }
+
+    #[test]
+    fn vorbis_fields() {
+        assert_eq!(TagKey::Info(*b"IPRT").vorbis_field(), "TRACKNUMBER");
+        assert_eq!(TagKey::Info(*b"MOOD").vorbis_field(), "MOOD");
+        assert_eq!(TagKey::from_vorbis_field("Encoder"), TagKey::Info(*b"ISFT"));
+        assert_eq!(TagKey::Info(*b"ISFT").to_string(), "software");
+        assert_eq!(TagKey::from_vorbis_field("bpm").to_string(), "BPM");
+    }
+}
@@ -0,0 +1,220 @@
+use super::read_u32;
+use crate::{
+    error::{Error, Result},
+    midi,
+};
+use std::convert::TryInto;
+
+/// ACID (`acid`) chunk, containing tempo and loop information used by DAWs to
+/// automatically sync loops to a project.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcidChunk {
+    flags: u32,
+    root_note: u16,
+    unknown1: u16,
+    unknown2: f32,
+    beats: u32,
+    meter_denominator: u16,
+    meter_numerator: u16,
+    tempo: f32,
+
+    /// Any bytes found after the standard fields. These are kept so that the
+    /// chunk can be written back out unchanged.
+    trailer: Vec<u8>,
+}
+
+impl Default for AcidChunk {
+    fn default() -> Self {
+        Self {
+            flags: 0,
+            root_note: 60,
+            unknown1: 0x8000,
+            unknown2: 0.0,
+            beats: 0,
+            meter_denominator: 4,
+            meter_numerator: 4,
+            tempo: 120.0,
+            trailer: Vec::new(),
+        }
+    }
+}
+
+impl AcidChunk {
+    pub(crate) const ID: &[u8; 4] = b"acid";
+    const LEN: usize = 24;
+
+    const FLAG_ONE_SHOT: u32 = 0x01;
+    const FLAG_ROOT_NOTE: u32 = 0x02;
+    const FLAG_STRETCH: u32 = 0x04;
+    const FLAG_DISK_BASED: u32 = 0x08;
+
+    /// Parse an ACID chunk from the chunk contents, not including the chunk ID
+    /// and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::LEN {
+            return Err(Error::invalid_chunk(Self::ID));
+        }
+
+        let read_u16 =
+            |offset: usize| u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap());
+        let read_f32 =
+            |offset: usize| f32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        Ok(Self {
+            flags: read_u32(bytes, 0),
+            root_note: read_u16(4),
+            unknown1: read_u16(6),
+            unknown2: read_f32(8),
+            beats: read_u32(bytes, 12),
+            meter_denominator: read_u16(16),
+            meter_numerator: read_u16(18),
+            tempo: read_f32(20),
+            trailer: bytes[Self::LEN..].to_vec(),
+        })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN + self.trailer.len());
+
+        bytes.extend_from_slice(&self.flags.to_le_bytes());
+        bytes.extend_from_slice(&self.root_note.to_le_bytes());
+        bytes.extend_from_slice(&self.unknown1.to_le_bytes());
+        bytes.extend_from_slice(&self.unknown2.to_le_bytes());
+        bytes.extend_from_slice(&self.beats.to_le_bytes());
+        bytes.extend_from_slice(&self.meter_denominator.to_le_bytes());
+        bytes.extend_from_slice(&self.meter_numerator.to_le_bytes());
+        bytes.extend_from_slice(&self.tempo.to_le_bytes());
+        bytes.extend_from_slice(&self.trailer);
+        bytes
+    }
+
+    /// Whether the sample is a one-shot rather than a loop.
+    pub fn is_one_shot(&self) -> bool {
+        self.flag(Self::FLAG_ONE_SHOT)
+    }
+
+    pub fn set_one_shot(&mut self, one_shot: bool) {
+        self.set_flag(Self::FLAG_ONE_SHOT, one_shot);
+    }
+
+    /// Whether the sample should be time stretched to match the project tempo.
+    pub fn is_stretch(&self) -> bool {
+        self.flag(Self::FLAG_STRETCH)
+    }
+
+    pub fn set_stretch(&mut self, stretch: bool) {
+        self.set_flag(Self::FLAG_STRETCH, stretch);
+    }
+
+    /// Whether the sample should be streamed from disk rather than loaded into
+    /// memory.
+    pub fn is_disk_based(&self) -> bool {
+        self.flag(Self::FLAG_DISK_BASED)
+    }
+
+    pub fn set_disk_based(&mut self, disk_based: bool) {
+        self.set_flag(Self::FLAG_DISK_BASED, disk_based);
+    }
+
+    /// Root note of the sample, used to transpose loops to the project key.
+    pub fn root_note(&self) -> Option<midi::Note> {
+        if self.flag(Self::FLAG_ROOT_NOTE) {
+            Some((self.root_note as u8).into())
+        } else {
+            None
+        }
+    }
+
+    pub fn set_root_note(&mut self, note: Option<midi::Note>) {
+        self.set_flag(Self::FLAG_ROOT_NOTE, note.is_some());
+
+        if let Some(note) = note {
+            self.root_note = u8::from(note).into();
+        }
+    }
+
+    /// Length of the sample in beats.
+    pub fn beats(&self) -> u32 {
+        self.beats
+    }
+
+    pub fn set_beats(&mut self, beats: u32) {
+        self.beats = beats;
+    }
+
+    /// Time signature of the sample as a numerator and denominator.
+    pub fn meter(&self) -> (u16, u16) {
+        (self.meter_numerator, self.meter_denominator)
+    }
+
+    pub fn set_meter(&mut self, numerator: u16, denominator: u16) {
+        self.meter_numerator = numerator;
+        self.meter_denominator = denominator;
+    }
+
+    /// Tempo of the sample in beats per minute.
+    pub fn tempo(&self) -> f32 {
+        self.tempo
+    }
+
+    pub fn set_tempo(&mut self, tempo: f32) {
+        self.tempo = tempo;
+    }
+
+    fn flag(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    fn set_flag(&mut self, flag: u32, value: bool) {
+        if value {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acid_chunk_round_trip() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0x17u32.to_le_bytes());
+        bytes.extend_from_slice(&57u16.to_le_bytes());
+        bytes.extend_from_slice(&0x8000u16.to_le_bytes());
+        bytes.extend_from_slice(&0f32.to_le_bytes());
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        bytes.extend_from_slice(&8u16.to_le_bytes());
+        bytes.extend_from_slice(&6u16.to_le_bytes());
+        bytes.extend_from_slice(&128.5f32.to_le_bytes());
+
+        let chunk = AcidChunk::from_bytes(&bytes).unwrap();
+
+        assert!(chunk.is_one_shot());
+        assert!(chunk.is_stretch());
+        assert!(!chunk.is_disk_based());
+        assert_eq!(chunk.root_note(), Some(midi::Note::from(57)));
+        assert_eq!(chunk.beats(), 8);
+        assert_eq!(chunk.meter(), (6, 8));
+        assert_eq!(chunk.tempo(), 128.5);
+        assert_eq!(chunk.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn set_flags() {
+        let mut chunk = AcidChunk::default();
+        assert_eq!(chunk.root_note(), None);
+
+        chunk.set_root_note(Some(midi::Note::from(48)));
+        chunk.set_one_shot(true);
+        assert_eq!(chunk.root_note(), Some(midi::Note::from(48)));
+        assert_eq!(chunk.flags, 0x03);
+
+        chunk.set_one_shot(false);
+        chunk.set_root_note(None);
+        assert_eq!(chunk.flags, 0);
+    }
+}
@@ -0,0 +1,294 @@
+use super::{read_sub_chunks, read_u32, write_chunk_to};
+use std::convert::TryInto;
+
+/// Associated data list (`LIST` chunk of type `adtl`), containing labels and
+/// other text attached to cue points.
+///
+/// Entries that aren't understood are preserved as-is.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssociatedDataList {
+    entries: Vec<AssociatedData>,
+}
+
+/// A single entry in an associated data list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AssociatedData {
+    /// A `labl` chunk containing the name of a cue point.
+    Label { cue_point_id: u32, text: String },
+    /// A `note` chunk containing a comment about a cue point.
+    Note { cue_point_id: u32, text: String },
+    /// An `ltxt` chunk containing text attached to a region of the sample.
+    LabeledText(LabeledText),
+    /// Any other chunk.
+    Other { id: [u8; 4], data: Vec<u8> },
+}
+
+/// Text attached to a region starting at a cue point.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LabeledText {
+    pub cue_point_id: u32,
+    /// Length of the region in sample frames.
+    pub sample_length: u32,
+    /// Four-character code describing what the text is for, such as `scrp`
+    /// for a script.
+    pub purpose: [u8; 4],
+    pub country: u16,
+    pub language: u16,
+    pub dialect: u16,
+    pub code_page: u16,
+    pub text: String,
+}
+
+impl AssociatedDataList {
+    pub(crate) const LIST_TYPE: &[u8; 4] = b"adtl";
+
+    /// Parse an associated data list from the list contents, not including the
+    /// list type.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            entries: read_sub_chunks(bytes)
+                .into_iter()
+                .map(|(id, data)| AssociatedData::from_chunk(id, data))
+                .collect(),
+        }
+    }
+
+    /// Serialize the list contents, not including the list type.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for entry in &self.entries {
+            let (id, data) = entry.to_chunk();
+            write_chunk_to(&mut bytes, &id, &data).unwrap();
+        }
+
+        bytes
+    }
+
+    pub fn entries(&self) -> &[AssociatedData] {
+        &self.entries
+    }
+
+    pub fn entries_mut(&mut self) -> &mut Vec<AssociatedData> {
+        &mut self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the label of a cue point.
+    pub fn label(&self, cue_point_id: u32) -> Option<&str> {
+        self.entries.iter().find_map(|entry| match entry {
+            AssociatedData::Label {
+                cue_point_id: id,
+                text,
+            } if *id == cue_point_id => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Set or remove the label of a cue point.
+    pub fn set_label(&mut self, cue_point_id: u32, label: Option<&str>) {
+        self.set_text(cue_point_id, label, |cue_point_id, text| {
+            AssociatedData::Label { cue_point_id, text }
+        })
+    }
+
+    /// Get the note attached to a cue point.
+    pub fn note(&self, cue_point_id: u32) -> Option<&str> {
+        self.entries.iter().find_map(|entry| match entry {
+            AssociatedData::Note {
+                cue_point_id: id,
+                text,
+            } if *id == cue_point_id => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Set or remove the note attached to a cue point.
+    pub fn set_note(&mut self, cue_point_id: u32, note: Option<&str>) {
+        self.set_text(cue_point_id, note, |cue_point_id, text| {
+            AssociatedData::Note { cue_point_id, text }
+        })
+    }
+
+    /// Remove all entries attached to a cue point.
+    pub fn remove_cue_point(&mut self, cue_point_id: u32) {
+        self.entries
+            .retain(|entry| entry.cue_point_id() != Some(cue_point_id));
+    }
+
+    fn set_text(
+        &mut self,
+        cue_point_id: u32,
+        text: Option<&str>,
+        new_entry: fn(u32, String) -> AssociatedData,
+    ) {
+        let kind = std::mem::discriminant(&new_entry(0, String::new()));
+        let position = self.entries.iter().position(|entry| {
+            std::mem::discriminant(entry) == kind && entry.cue_point_id() == Some(cue_point_id)
+        });
+
+        match (position, text) {
+            (Some(i), Some(text)) => self.entries[i] = new_entry(cue_point_id, text.to_owned()),
+            (Some(i), None) => {
+                self.entries.remove(i);
+            }
+            (None, Some(text)) => self.entries.push(new_entry(cue_point_id, text.to_owned())),
+            (None, None) => {}
+        }
+    }
+}
+
+impl AssociatedData {
+    /// ID of the cue point this entry is attached to.
+    pub fn cue_point_id(&self) -> Option<u32> {
+        match self {
+            AssociatedData::Label { cue_point_id, .. } => Some(*cue_point_id),
+            AssociatedData::Note { cue_point_id, .. } => Some(*cue_point_id),
+            AssociatedData::LabeledText(text) => Some(text.cue_point_id),
+            AssociatedData::Other { .. } => None,
+        }
+    }
+
+    fn from_chunk(id: [u8; 4], data: &[u8]) -> Self {
+        match (&id, data.len()) {
+            (b"labl", len) if len >= 4 => AssociatedData::Label {
+                cue_point_id: read_u32(data, 0),
+                text: read_text(&data[4..]),
+            },
+            (b"note", len) if len >= 4 => AssociatedData::Note {
+                cue_point_id: read_u32(data, 0),
+                text: read_text(&data[4..]),
+            },
+            (b"ltxt", len) if len >= 20 => AssociatedData::LabeledText(LabeledText {
+                cue_point_id: read_u32(data, 0),
+                sample_length: read_u32(data, 4),
+                purpose: data[8..12].try_into().unwrap(),
+                country: read_u16(data, 12),
+                language: read_u16(data, 14),
+                dialect: read_u16(data, 16),
+                code_page: read_u16(data, 18),
+                text: read_text(&data[20..]),
+            }),
+            _ => AssociatedData::Other {
+                id,
+                data: data.to_vec(),
+            },
+        }
+    }
+
+    fn to_chunk(&self) -> ([u8; 4], Vec<u8>) {
+        match self {
+            AssociatedData::Label { cue_point_id, text } => {
+                let mut data = cue_point_id.to_le_bytes().to_vec();
+                write_text(&mut data, text);
+                (*b"labl", data)
+            }
+            AssociatedData::Note { cue_point_id, text } => {
+                let mut data = cue_point_id.to_le_bytes().to_vec();
+                write_text(&mut data, text);
+                (*b"note", data)
+            }
+            AssociatedData::LabeledText(ltxt) => {
+                let mut data = Vec::new();
+                data.extend_from_slice(&ltxt.cue_point_id.to_le_bytes());
+                data.extend_from_slice(&ltxt.sample_length.to_le_bytes());
+                data.extend_from_slice(&ltxt.purpose);
+                data.extend_from_slice(&ltxt.country.to_le_bytes());
+                data.extend_from_slice(&ltxt.language.to_le_bytes());
+                data.extend_from_slice(&ltxt.dialect.to_le_bytes());
+                data.extend_from_slice(&ltxt.code_page.to_le_bytes());
+
+                // The text is optional in a labeled text chunk.
+                if !ltxt.text.is_empty() {
+                    write_text(&mut data, &ltxt.text);
+                }
+
+                (*b"ltxt", data)
+            }
+            AssociatedData::Other { id, data } => (*id, data.clone()),
+        }
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+/// Read a null-terminated string.
+fn read_text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn write_text(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend_from_slice(text.as_bytes());
+    bytes.push(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn associated_data_list_round_trip() {
+        let mut bytes = Vec::new();
+        write_chunk_to(&mut bytes, b"labl", b"\x01\0\0\0Verse\0").unwrap();
+        write_chunk_to(&mut bytes, b"note", b"\x01\0\0\0Loud\0").unwrap();
+        write_chunk_to(
+            &mut bytes,
+            b"ltxt",
+            b"\x02\0\0\0\x10\0\0\0rgn \0\0\0\0\0\0\0\0Chorus\0",
+        )
+        .unwrap();
+        write_chunk_to(&mut bytes, b"file", b"\x02\0\0\0abc").unwrap();
+
+        let list = AssociatedDataList::from_bytes(&bytes);
+
+        assert_eq!(list.label(1), Some("Verse"));
+        assert_eq!(list.note(1), Some("Loud"));
+        assert_eq!(list.label(2), None);
+        assert_eq!(
+            list.entries()[2],
+            AssociatedData::LabeledText(LabeledText {
+                cue_point_id: 2,
+                sample_length: 16,
+                purpose: *b"rgn ",
+                text: "Chorus".into(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            list.entries()[3],
+            AssociatedData::Other {
+                id: *b"file",
+                data: b"\x02\0\0\0abc".to_vec(),
+            }
+        );
+        assert_eq!(list.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn set_label() {
+        let mut list = AssociatedDataList::default();
+
+        list.set_label(1, Some("Intro"));
+        list.set_note(1, Some("Quiet"));
+        list.set_label(2, Some("Verse"));
+        list.set_label(1, Some("Start"));
+        assert_eq!(list.label(1), Some("Start"));
+        assert_eq!(list.note(1), Some("Quiet"));
+        assert_eq!(list.entries().len(), 3);
+
+        list.set_label(1, None);
+        assert_eq!(list.label(1), None);
+        assert_eq!(list.note(1), Some("Quiet"));
+
+        list.remove_cue_point(1);
+        assert_eq!(list.entries().len(), 1);
+        assert_eq!(list.label(2), Some("Verse"));
+    }
+}
@@ -0,0 +1,347 @@
+use super::read_u32;
+use crate::error::{Error, Result};
+use std::{convert::TryInto, str};
+
+/// Broadcast audio extension (`bext`) chunk, as defined by the EBU Broadcast
+/// Wave Format (BWF).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BroadcastExtensionChunk {
+    description: [u8; 256],
+    originator: [u8; 32],
+    originator_reference: [u8; 32],
+    origination_date: [u8; 10],
+    origination_time: [u8; 8],
+    time_reference: u64,
+    version: u16,
+    umid: [u8; 64],
+    loudness_value: i16,
+    loudness_range: i16,
+    max_true_peak_level: i16,
+    max_momentary_loudness: i16,
+    max_short_term_loudness: i16,
+    reserved: [u8; 180],
+    coding_history: Vec<u8>,
+}
+
+impl Default for BroadcastExtensionChunk {
+    fn default() -> Self {
+        Self {
+            description: [0; 256],
+            originator: [0; 32],
+            originator_reference: [0; 32],
+            origination_date: [0; 10],
+            origination_time: [0; 8],
+            time_reference: 0,
+            version: 2,
+            umid: [0; 64],
+            loudness_value: Self::LOUDNESS_UNSET,
+            loudness_range: Self::LOUDNESS_UNSET,
+            max_true_peak_level: Self::LOUDNESS_UNSET,
+            max_momentary_loudness: Self::LOUDNESS_UNSET,
+            max_short_term_loudness: Self::LOUDNESS_UNSET,
+            reserved: [0; 180],
+            coding_history: Vec::new(),
+        }
+    }
+}
+
+impl BroadcastExtensionChunk {
+    pub(crate) const ID: &[u8; 4] = b"bext";
+    const LEN: usize = 602;
+
+    /// Loudness values are stored as hundredths of a unit, with this value
+    /// meaning that the value has not been set.
+    const LOUDNESS_UNSET: i16 = 0x7fff;
+
+    /// Parse a broadcast extension chunk from the chunk contents, not
+    /// including the chunk ID and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::LEN {
+            return Err(Error::invalid_chunk(Self::ID));
+        }
+
+        let read_i16 = |offset: usize| i16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+
+        Ok(Self {
+            description: bytes[0..256].try_into().unwrap(),
+            originator: bytes[256..288].try_into().unwrap(),
+            originator_reference: bytes[288..320].try_into().unwrap(),
+            origination_date: bytes[320..330].try_into().unwrap(),
+            origination_time: bytes[330..338].try_into().unwrap(),
+            time_reference: read_u32(bytes, 338) as u64 | (read_u32(bytes, 342) as u64) << 32,
+            version: u16::from_le_bytes([bytes[346], bytes[347]]),
+            umid: bytes[348..412].try_into().unwrap(),
+            loudness_value: read_i16(412),
+            loudness_range: read_i16(414),
+            max_true_peak_level: read_i16(416),
+            max_momentary_loudness: read_i16(418),
+            max_short_term_loudness: read_i16(420),
+            reserved: bytes[422..602].try_into().unwrap(),
+            coding_history: bytes[602..].to_vec(),
+        })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN + self.coding_history.len());
+
+        bytes.extend_from_slice(&self.description);
+        bytes.extend_from_slice(&self.originator);
+        bytes.extend_from_slice(&self.originator_reference);
+        bytes.extend_from_slice(&self.origination_date);
+        bytes.extend_from_slice(&self.origination_time);
+        bytes.extend_from_slice(&(self.time_reference as u32).to_le_bytes());
+        bytes.extend_from_slice(&((self.time_reference >> 32) as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.umid);
+
+        for value in &[
+            self.loudness_value,
+            self.loudness_range,
+            self.max_true_peak_level,
+            self.max_momentary_loudness,
+            self.max_short_term_loudness,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&self.reserved);
+        bytes.extend_from_slice(&self.coding_history);
+        bytes
+    }
+
+    /// Free text description of the sound sequence.
+    pub fn description(&self) -> String {
+        read_text(&self.description)
+    }
+
+    /// Set the description. Text longer than 256 bytes is cut short.
+    pub fn set_description(&mut self, description: &str) {
+        write_text(&mut self.description, description);
+    }
+
+    /// Name of the originator or producer of the audio.
+    pub fn originator(&self) -> String {
+        read_text(&self.originator)
+    }
+
+    /// Set the originator. Text longer than 32 bytes is cut short.
+    pub fn set_originator(&mut self, originator: &str) {
+        write_text(&mut self.originator, originator);
+    }
+
+    /// Unique reference assigned by the originator.
+    pub fn originator_reference(&self) -> String {
+        read_text(&self.originator_reference)
+    }
+
+    /// Set the originator reference. Text longer than 32 bytes is cut short.
+    pub fn set_originator_reference(&mut self, reference: &str) {
+        write_text(&mut self.originator_reference, reference);
+    }
+
+    /// Date the audio was created, formatted as `yyyy-mm-dd`.
+    pub fn origination_date(&self) -> String {
+        read_text(&self.origination_date)
+    }
+
+    pub fn set_origination_date(&mut self, date: &str) {
+        write_text(&mut self.origination_date, date);
+    }
+
+    /// Time of day the audio was created, formatted as `hh:mm:ss`.
+    pub fn origination_time(&self) -> String {
+        read_text(&self.origination_time)
+    }
+
+    pub fn set_origination_time(&mut self, time: &str) {
+        write_text(&mut self.origination_time, time);
+    }
+
+    /// Timecode of the start of the audio, measured in sample frames since
+    /// midnight.
+    pub fn time_reference(&self) -> u64 {
+        self.time_reference
+    }
+
+    pub fn set_time_reference(&mut self, time_reference: u64) {
+        self.time_reference = time_reference;
+    }
+
+    /// Version of the BWF specification the chunk conforms to. Version 1 adds
+    /// the UMID and version 2 adds loudness metadata.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: u16) {
+        self.version = version;
+    }
+
+    /// SMPTE unique material identifier, if set.
+    pub fn umid(&self) -> Option<&[u8; 64]> {
+        if self.version >= 1 && self.umid.iter().any(|b| *b != 0) {
+            Some(&self.umid)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_umid(&mut self, umid: Option<[u8; 64]>) {
+        self.umid = umid.unwrap_or([0; 64]);
+
+        if umid.is_some() {
+            self.version = self.version.max(1);
+        }
+    }
+
+    /// Integrated loudness of the audio in LUFS.
+    pub fn loudness_value(&self) -> Option<f32> {
+        self.loudness(self.loudness_value)
+    }
+
+    pub fn set_loudness_value(&mut self, value: Option<f32>) {
+        self.loudness_value = self.encode_loudness(value);
+    }
+
+    /// Loudness range of the audio in LU.
+    pub fn loudness_range(&self) -> Option<f32> {
+        self.loudness(self.loudness_range)
+    }
+
+    pub fn set_loudness_range(&mut self, value: Option<f32>) {
+        self.loudness_range = self.encode_loudness(value);
+    }
+
+    /// Maximum true peak level of the audio in dBTP.
+    pub fn max_true_peak_level(&self) -> Option<f32> {
+        self.loudness(self.max_true_peak_level)
+    }
+
+    pub fn set_max_true_peak_level(&mut self, value: Option<f32>) {
+        self.max_true_peak_level = self.encode_loudness(value);
+    }
+
+    /// Highest momentary loudness of the audio in LUFS.
+    pub fn max_momentary_loudness(&self) -> Option<f32> {
+        self.loudness(self.max_momentary_loudness)
+    }
+
+    pub fn set_max_momentary_loudness(&mut self, value: Option<f32>) {
+        self.max_momentary_loudness = self.encode_loudness(value);
+    }
+
+    /// Highest short-term loudness of the audio in LUFS.
+    pub fn max_short_term_loudness(&self) -> Option<f32> {
+        self.loudness(self.max_short_term_loudness)
+    }
+
+    pub fn set_max_short_term_loudness(&mut self, value: Option<f32>) {
+        self.max_short_term_loudness = self.encode_loudness(value);
+    }
+
+    /// History of the coding processes applied to the audio, one per line.
+    pub fn coding_history(&self) -> String {
+        read_text(&self.coding_history)
+    }
+
+    pub fn set_coding_history(&mut self, history: &str) {
+        self.coding_history = history.as_bytes().to_vec();
+    }
+
+    fn loudness(&self, value: i16) -> Option<f32> {
+        if self.version >= 2 && value != Self::LOUDNESS_UNSET {
+            Some(value as f32 / 100.0)
+        } else {
+            None
+        }
+    }
+
+    fn encode_loudness(&mut self, value: Option<f32>) -> i16 {
+        match value {
+            Some(value) => {
+                self.version = self.version.max(2);
+                (value * 100.0)
+                    .round()
+                    .clamp(i16::MIN as f32, (Self::LOUDNESS_UNSET - 1) as f32)
+                    as i16
+            }
+            None => Self::LOUDNESS_UNSET,
+        }
+    }
+}
+
+/// Read a fixed-size text field, which is null-padded if shorter than the
+/// field.
+fn read_text(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+
+    match str::from_utf8(&bytes[..end]) {
+        Ok(s) => s.to_owned(),
+        Err(_) => bytes[..end].iter().map(|b| *b as char).collect(),
+    }
+}
+
+fn write_text(field: &mut [u8], text: &str) {
+    let mut len = text.len().min(field.len());
+
+    while !text.is_char_boundary(len) {
+        len -= 1;
+    }
+
+    field.iter_mut().for_each(|b| *b = 0);
+    field[..len].copy_from_slice(&text.as_bytes()[..len]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bext_chunk_round_trip() {
+        let mut chunk = BroadcastExtensionChunk::default();
+        chunk.set_description("Field recording");
+        chunk.set_originator("Recorder");
+        chunk.set_origination_date("2021-06-01");
+        chunk.set_origination_time("12:34:56");
+        chunk.set_time_reference(0x1_0000_0002);
+        chunk.set_loudness_value(Some(-23.5));
+        chunk.set_coding_history("A=PCM,F=48000,W=24,M=stereo\r\n");
+
+        let bytes = chunk.to_bytes();
+        assert_eq!(bytes.len(), 602 + 29);
+
+        let chunk = BroadcastExtensionChunk::from_bytes(&bytes).unwrap();
+        assert_eq!(chunk.description(), "Field recording");
+        assert_eq!(chunk.originator(), "Recorder");
+        assert_eq!(chunk.originator_reference(), "");
+        assert_eq!(chunk.origination_date(), "2021-06-01");
+        assert_eq!(chunk.origination_time(), "12:34:56");
+        assert_eq!(chunk.time_reference(), 0x1_0000_0002);
+        assert_eq!(chunk.umid(), None);
+        assert_eq!(chunk.loudness_value(), Some(-23.5));
+        assert_eq!(chunk.loudness_range(), None);
+        assert_eq!(chunk.coding_history(), "A=PCM,F=48000,W=24,M=stereo\r\n");
+        assert_eq!(chunk.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn version_0_has_no_loudness() {
+        let mut bytes = BroadcastExtensionChunk::default().to_bytes();
+        bytes[346] = 0;
+        bytes[412] = 1;
+        bytes[413] = 0;
+
+        let chunk = BroadcastExtensionChunk::from_bytes(&bytes).unwrap();
+        assert_eq!(chunk.version(), 0);
+        assert_eq!(chunk.loudness_value(), None);
+    }
+
+    #[test]
+    fn long_text_is_truncated() {
+        let mut chunk = BroadcastExtensionChunk::default();
+        chunk.set_originator("ééééééééééééééééééé");
+
+        assert_eq!(chunk.originator(), "éééééééééééééééé");
+    }
+}
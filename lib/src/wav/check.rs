@@ -0,0 +1,429 @@
+use std::{
+    fmt,
+    io::{Read, Seek, SeekFrom, Write},
+};
+
+use super::{Chunk, Container, SamplerChunk, Wav};
+use crate::error::Result;
+
+/// IDs of chunks that should only appear once in a file.
+const UNIQUE_CHUNKS: &[&[u8; 4]] = &[
+    b"ds64", b"fmt ", b"fact", b"data", b"smpl", b"inst", b"acid", b"cue ", b"bext",
+];
+
+/// A problem with the layout of a WAV file, found by [`Wav::check`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// The size of the RIFF chunk in the header doesn't match the size of the
+    /// chunks in it.
+    FormSizeMismatch { header: u64, actual: u64 },
+    /// A chunk with an odd size isn't followed by a pad byte.
+    MissingPadByte { id: [u8; 4], offset: u64 },
+    /// A chunk that should only appear once appears again. Only the first one
+    /// is used. `LIST` chunks are duplicates if they have the same list type.
+    DuplicateChunk { id: [u8; 4], offset: u64 },
+    /// A chunk is cut short by the end of the file.
+    TruncatedChunk {
+        id: [u8; 4],
+        offset: u64,
+        len: u64,
+        available: u64,
+    },
+    /// Data after the last chunk that isn't part of any chunk.
+    TrailingData { offset: u64, len: u64 },
+    /// The sampler chunk claims more sampler-specific data than it holds.
+    SamplerDataTruncated {
+        offset: u64,
+        declared: u32,
+        available: u64,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::FormSizeMismatch { header, actual } => write!(
+                f,
+                "RIFF size is {} bytes, but the chunks take up {} bytes",
+                header, actual
+            ),
+            Problem::MissingPadByte { id, offset } => write!(
+                f,
+                "{} chunk at offset {} has an odd size but no pad byte",
+                String::from_utf8_lossy(id),
+                offset
+            ),
+            Problem::DuplicateChunk { id, offset } => write!(
+                f,
+                "duplicate {} chunk at offset {}",
+                String::from_utf8_lossy(id),
+                offset
+            ),
+            Problem::TruncatedChunk {
+                id,
+                offset,
+                len,
+                available,
+            } => write!(
+                f,
+                "{} chunk at offset {} is {} bytes, but only {} bytes are in the file",
+                String::from_utf8_lossy(id),
+                offset,
+                len,
+                available
+            ),
+            Problem::TrailingData { offset, len } => write!(
+                f,
+                "{} bytes of unknown data at offset {} after the last chunk",
+                len, offset
+            ),
+            Problem::SamplerDataTruncated {
+                offset,
+                declared,
+                available,
+            } => write!(
+                f,
+                "smpl chunk at offset {} claims {} bytes of sampler data, but holds {} bytes",
+                offset, declared, available
+            ),
+        }
+    }
+}
+
+impl<F: Read + Seek> Wav<F> {
+    /// Check the layout of the chunks in the file, returning any problems
+    /// found.
+    ///
+    /// Unlike reading, which only looks inside the RIFF chunk, this walks
+    /// through every chunk in the file until it finds something that isn't a
+    /// chunk.
+    pub fn check(&mut self) -> Result<Vec<Problem>> {
+        Ok(self.scan()?.1)
+    }
+
+    /// Write a copy of the file with all problems found by
+    /// [`check`](Self::check) fixed.
+    ///
+    /// Duplicate chunks and trailing data are left out, missing pad bytes are
+    /// added and the RIFF size is corrected. A truncated `data` chunk is cut
+    /// down to the last whole frame, and any other truncated chunk is left
+    /// out. A sampler chunk claiming more sampler data than it holds is given
+    /// the length of the data it does hold.
+    pub fn write_repaired(&mut self, output: impl Write + Seek) -> Result<()> {
+        let (chunks, problems) = self.scan()?;
+        let block_align = self
+            .get_format_chunk()?
+            .map(|format| format.block_align() as u64)
+            .unwrap_or(1)
+            .max(1);
+        let mut kept = Vec::with_capacity(chunks.len());
+
+        for mut chunk in chunks {
+            let problem = problems.iter().find(|problem| {
+                matches!(problem,
+                    Problem::DuplicateChunk { offset, .. } | Problem::TruncatedChunk { offset, .. }
+                    if *offset == chunk.offset)
+            });
+
+            match problem {
+                Some(Problem::TruncatedChunk { .. }) if &chunk.id == b"data" => {
+                    chunk.len -= chunk.len % block_align;
+                }
+                Some(_) => continue,
+                None => {}
+            }
+
+            let contents = if problems.iter().any(|problem| {
+                matches!(problem, Problem::SamplerDataTruncated { offset, .. }
+                    if *offset == chunk.offset)
+            }) {
+                let mut sampler = SamplerChunk::from_bytes(&self.read_contents(&chunk)?)?;
+                sampler.set_sampler_data(sampler.sampler_data().to_vec());
+                Some(sampler.to_bytes())
+            } else {
+                None
+            };
+
+            kept.push((chunk, contents));
+        }
+
+        self.write_chunks(output, &kept)
+    }
+
+    /// Walk through every chunk in the file, returning the chunks found and
+    /// any problems with them. Truncated chunks are given the length of what
+    /// is actually in the file.
+    fn scan(&mut self) -> Result<(Vec<Chunk>, Vec<Problem>)> {
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+        let mut offset = self.first_chunk_offset();
+        let mut form_end = offset;
+        let mut chunks = Vec::new();
+        let mut problems = Vec::new();
+
+        while self.is_chunk_at(offset, file_len)? {
+            let mut chunk = self.read_chunk_header(offset)?;
+            let contents_end = self.contents_offset(&chunk).saturating_add(chunk.len);
+            let padded_end = self.padded_end(&chunk);
+            form_end = padded_end;
+
+            if contents_end > file_len {
+                let available = file_len - self.contents_offset(&chunk);
+
+                problems.push(Problem::TruncatedChunk {
+                    id: chunk.id,
+                    offset,
+                    len: chunk.len,
+                    available,
+                });
+                chunk.len = available;
+                chunks.push(chunk);
+                offset = file_len;
+                break;
+            }
+
+            chunks.push(chunk);
+
+            // Some software leaves out the pad byte, in which case the next
+            // chunk starts straight after this one.
+            offset = if padded_end != contents_end
+                && (padded_end > file_len
+                    || !self.is_chunk_at(padded_end, file_len)?
+                        && self.is_chunk_at(contents_end, file_len)?)
+            {
+                problems.push(Problem::MissingPadByte {
+                    id: chunk.id,
+                    offset: chunk.offset,
+                });
+                contents_end
+            } else {
+                padded_end
+            };
+        }
+
+        if offset < file_len {
+            problems.push(Problem::TrailingData {
+                offset,
+                len: file_len - offset,
+            });
+        }
+
+        let actual = match self.container {
+            Container::Wave64 => form_end,
+            _ => form_end - 8,
+        };
+
+        if actual != self.form_len {
+            problems.insert(
+                0,
+                Problem::FormSizeMismatch {
+                    header: self.form_len,
+                    actual,
+                },
+            );
+        }
+
+        let mut seen = Vec::new();
+
+        for chunk in &chunks {
+            let key = match self.list_type(chunk)? {
+                Some(list_type) => (chunk.id, Some(list_type)),
+                None if UNIQUE_CHUNKS.contains(&&chunk.id) => (chunk.id, None),
+                None => continue,
+            };
+
+            if seen.contains(&key) {
+                problems.push(Problem::DuplicateChunk {
+                    id: chunk.id,
+                    offset: chunk.offset,
+                });
+            } else {
+                seen.push(key);
+            }
+        }
+
+        if let Some(chunk) = chunks.iter().find(|chunk| &chunk.id == SamplerChunk::ID) {
+            // A sampler chunk that can't be parsed at all is left for reading
+            // to report.
+            if let Ok(sampler) = SamplerChunk::from_bytes(&self.read_contents(chunk)?) {
+                if let Some(declared) = sampler.declared_sampler_data_len() {
+                    problems.push(Problem::SamplerDataTruncated {
+                        offset: chunk.offset,
+                        declared,
+                        available: sampler.sampler_data().len() as u64,
+                    });
+                }
+            }
+        }
+
+        Ok((chunks, problems))
+    }
+
+    /// Check whether there looks to be a chunk header at the given offset,
+    /// going by whether the chunk ID is printable ASCII.
+    fn is_chunk_at(&mut self, offset: u64, file_len: u64) -> Result<bool> {
+        if offset + self.container.header_len() > file_len {
+            return Ok(false);
+        }
+
+        let chunk = self.read_chunk_header(offset)?;
+
+        Ok(chunk.id.iter().all(|b| (0x20..0x7f).contains(b)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wav::{write_chunk_to, FormatChunk, SampleFormat};
+    use std::io::Cursor;
+
+    fn format_bytes() -> Vec<u8> {
+        FormatChunk::new(SampleFormat::Pcm, 1, 44100, 16)
+            .unwrap()
+            .to_bytes()
+    }
+
+    fn riff(form_len: u32, body: &[u8]) -> Vec<u8> {
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&form_len.to_le_bytes());
+        file.extend_from_slice(b"WAVE");
+        file.extend_from_slice(body);
+        file
+    }
+
+    fn repair(bytes: Vec<u8>) -> Vec<u8> {
+        let mut output = Cursor::new(Vec::new());
+        Wav::new(Cursor::new(bytes))
+            .unwrap()
+            .write_repaired(&mut output)
+            .unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn valid_file_has_no_problems() {
+        let mut body = Vec::new();
+        write_chunk_to(&mut body, b"fmt ", &format_bytes()).unwrap();
+        write_chunk_to(&mut body, b"data", &[1, 2, 3]).unwrap();
+        let bytes = riff(4 + body.len() as u32, &body);
+
+        let mut wav = Wav::new(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(wav.check().unwrap(), vec![]);
+        assert_eq!(repair(bytes.clone()), bytes);
+
+        let wrong_size = riff(4 + body.len() as u32 + 10, &body);
+        let mut wav = Wav::new(Cursor::new(wrong_size.clone())).unwrap();
+        assert_eq!(
+            wav.check().unwrap(),
+            vec![Problem::FormSizeMismatch {
+                header: 4 + body.len() as u64 + 10,
+                actual: 4 + body.len() as u64,
+            }]
+        );
+        assert_eq!(repair(wrong_size), bytes);
+    }
+
+    #[test]
+    fn missing_pad_bytes_and_trailing_data() {
+        let mut body = Vec::new();
+        write_chunk_to(&mut body, b"fmt ", &format_bytes()).unwrap();
+        body.extend_from_slice(b"abcd\x03\0\0\0xyz");
+        write_chunk_to(&mut body, b"data", &[1, 2, 3, 4]).unwrap();
+        let form_len = 4 + body.len() as u32;
+        body.extend_from_slice(b"\0\0\xff\xfe\xfd\xfc\xfb");
+
+        let bytes = riff(form_len, &body);
+        let mut wav = Wav::new(Cursor::new(bytes.clone())).unwrap();
+
+        assert_eq!(
+            wav.check().unwrap(),
+            vec![
+                Problem::MissingPadByte {
+                    id: *b"abcd",
+                    offset: 36,
+                },
+                Problem::TrailingData {
+                    offset: 12 + form_len as u64 - 4,
+                    len: 7,
+                },
+            ]
+        );
+
+        let repaired = repair(bytes);
+        let mut wav = Wav::new(Cursor::new(repaired.clone())).unwrap();
+        assert_eq!(wav.check().unwrap(), vec![]);
+        assert_eq!(wav.chunk_ids().unwrap(), vec![*b"fmt ", *b"abcd", *b"data"]);
+        assert_eq!(repaired.len(), 12 + 24 + 12 + 12);
+    }
+
+    #[test]
+    fn duplicate_and_truncated_chunks() {
+        let mut body = Vec::new();
+        write_chunk_to(&mut body, b"fmt ", &format_bytes()).unwrap();
+        write_chunk_to(&mut body, b"smpl", &[0; 36]).unwrap();
+        write_chunk_to(&mut body, b"LIST", b"INFO").unwrap();
+        write_chunk_to(&mut body, b"LIST", b"adtl").unwrap();
+        write_chunk_to(&mut body, b"LIST", b"INFO").unwrap();
+        write_chunk_to(&mut body, b"smpl", &[0; 36]).unwrap();
+        body.extend_from_slice(b"data\x10\0\0\0\x01\x02\x03\x04\x05");
+        let bytes = riff(4 + body.len() as u32 + 11, &body);
+
+        let mut wav = Wav::new(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(
+            wav.check().unwrap(),
+            vec![
+                Problem::TruncatedChunk {
+                    id: *b"data",
+                    offset: 12 + body.len() as u64 - 13,
+                    len: 16,
+                    available: 5,
+                },
+                Problem::DuplicateChunk {
+                    id: *b"LIST",
+                    offset: 12 + 24 + 44 + 24,
+                },
+                Problem::DuplicateChunk {
+                    id: *b"smpl",
+                    offset: 12 + 24 + 44 + 36,
+                },
+            ]
+        );
+
+        let mut wav = Wav::new(Cursor::new(repair(bytes))).unwrap();
+        assert_eq!(wav.check().unwrap(), vec![]);
+        assert_eq!(
+            wav.chunk_ids().unwrap(),
+            vec![*b"fmt ", *b"smpl", *b"LIST", *b"LIST", *b"data"]
+        );
+        assert_eq!(wav.frame_count().unwrap(), Some(2));
+    }
+
+    #[test]
+    fn truncated_sampler_data() {
+        // A sampler chunk with no loops claiming 8 bytes of sampler data, of
+        // which only 2 are there.
+        let mut smpl = [0; 38];
+        smpl[32] = 8;
+        let mut body = Vec::new();
+        write_chunk_to(&mut body, b"fmt ", &format_bytes()).unwrap();
+        write_chunk_to(&mut body, b"smpl", &smpl).unwrap();
+        write_chunk_to(&mut body, b"data", &[1, 2]).unwrap();
+        let bytes = riff(4 + body.len() as u32, &body);
+
+        let mut wav = Wav::new(Cursor::new(bytes.clone())).unwrap();
+        assert_eq!(
+            wav.check().unwrap(),
+            vec![Problem::SamplerDataTruncated {
+                offset: 12 + 24,
+                declared: 8,
+                available: 2,
+            }]
+        );
+
+        let mut wav = Wav::new(Cursor::new(repair(bytes))).unwrap();
+        assert_eq!(wav.check().unwrap(), vec![]);
+        let sampler = wav.get_sampler_chunk().unwrap().unwrap();
+        assert_eq!(sampler.sampler_data(), &[0, 0]);
+        assert_eq!(sampler.declared_sampler_data_len(), None);
+    }
+}
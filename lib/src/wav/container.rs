@@ -0,0 +1,235 @@
+use super::read_u32;
+use crate::error::{Error, Result};
+use std::{convert::TryInto, fmt, io};
+
+/// The container format a WAV file is stored in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Container {
+    /// A standard RIFF file, which is limited to 4 GiB.
+    Riff,
+    /// An RF64 file, where sizes that don't fit in 32 bits are stored in a
+    /// `ds64` chunk at the start of the file.
+    Rf64,
+    /// A BW64 file, which is laid out the same as an RF64 file.
+    Bw64,
+    /// A Sony Wave64 file, which uses GUIDs as chunk IDs and 64-bit sizes.
+    Wave64,
+}
+
+impl Container {
+    /// Size of each chunk header.
+    pub(crate) fn header_len(self) -> u64 {
+        match self {
+            Container::Wave64 => 24,
+            _ => 8,
+        }
+    }
+
+    /// Size of chunk contents including padding. Chunks are aligned to 2-byte
+    /// boundaries, or 8-byte boundaries in Wave64 files.
+    pub(crate) fn padded_len(self, len: u64) -> u64 {
+        match self {
+            Container::Wave64 => len.div_ceil(8) * 8,
+            _ => len + len % 2,
+        }
+    }
+
+    /// Write a complete chunk including its header and any padding.
+    pub(crate) fn write_chunk_to(
+        self,
+        mut writer: impl io::Write,
+        id: &[u8; 4],
+        data: &[u8],
+    ) -> Result<()> {
+        let len = data.len() as u64;
+
+        self.write_chunk_header(&mut writer, id, len)?;
+        writer.write_all(data)?;
+        writer.write_all(&vec![0; (self.padded_len(len) - len) as usize])?;
+
+        Ok(())
+    }
+
+    /// Write the header of a chunk whose contents are `len` bytes long.
+    pub(crate) fn write_chunk_header(
+        self,
+        mut writer: impl io::Write,
+        id: &[u8; 4],
+        len: u64,
+    ) -> Result<()> {
+        match self {
+            Container::Wave64 => {
+                writer.write_all(&wave64_guid(id))?;
+                writer.write_all(&(len + 24).to_le_bytes())?;
+            }
+            _ => {
+                let len: u32 = len
+                    .try_into()
+                    .map_err(|_| Error::TooLarge("chunk too large"))?;
+
+                writer.write_all(id)?;
+                writer.write_all(&len.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Container {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Container::Riff => "RIFF",
+            Container::Rf64 => "RF64",
+            Container::Bw64 => "BW64",
+            Container::Wave64 => "Wave64",
+        })
+    }
+}
+
+/// Data size (`ds64`) chunk of an RF64 file, holding the sizes of any chunks
+/// that are too large for the 32-bit size in their header. Such chunks have
+/// their size set to `0xffffffff`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Ds64Chunk {
+    pub(crate) riff_size: u64,
+    pub(crate) data_size: u64,
+    pub(crate) sample_count: u64,
+    /// Sizes of large chunks other than the `data` chunk.
+    pub(crate) table: Vec<([u8; 4], u64)>,
+}
+
+impl Ds64Chunk {
+    pub(crate) const ID: &[u8; 4] = b"ds64";
+    pub(crate) const LEN: usize = 28;
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || Error::invalid_chunk(Self::ID);
+
+        if bytes.len() < Self::LEN {
+            return Err(invalid());
+        }
+
+        let count = read_u32(bytes, 24) as usize;
+        let table = bytes[Self::LEN..]
+            .chunks_exact(12)
+            .take(count)
+            .map(|entry| (entry[..4].try_into().unwrap(), read_u64(entry, 4)))
+            .collect::<Vec<_>>();
+
+        if table.len() != count {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            riff_size: read_u64(bytes, 0),
+            data_size: read_u64(bytes, 8),
+            sample_count: read_u64(bytes, 16),
+            table,
+        })
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN + self.table.len() * 12);
+        bytes.extend_from_slice(&self.riff_size.to_le_bytes());
+        bytes.extend_from_slice(&self.data_size.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_count.to_le_bytes());
+        bytes.extend_from_slice(&(self.table.len() as u32).to_le_bytes());
+
+        for (id, size) in &self.table {
+            bytes.extend_from_slice(id);
+            bytes.extend_from_slice(&size.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Get the real size of a chunk whose header size is `0xffffffff`.
+    pub(crate) fn chunk_size(&self, id: &[u8; 4]) -> Option<u64> {
+        if id == b"data" {
+            return Some(self.data_size);
+        }
+
+        self.table
+            .iter()
+            .find(|(table_id, _)| table_id == id)
+            .map(|(_, size)| *size)
+    }
+}
+
+/// GUID of the RIFF header of a Wave64 file.
+pub(crate) const WAVE64_RIFF: [u8; 16] = [
+    0x72, 0x69, 0x66, 0x66, 0x2e, 0x91, 0xcf, 0x11, 0xa5, 0xd6, 0x28, 0xdb, 0x04, 0xc1, 0x00, 0x00,
+];
+
+/// GUID of the form type of a Wave64 file.
+pub(crate) const WAVE64_WAVE: [u8; 16] = [
+    0x77, 0x61, 0x76, 0x65, 0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+
+/// GUID of a Wave64 list chunk.
+const WAVE64_LIST: [u8; 16] = [
+    0x6c, 0x69, 0x73, 0x74, 0x2f, 0x91, 0xcf, 0x11, 0xa5, 0xd6, 0x28, 0xdb, 0x04, 0xc1, 0x00, 0x00,
+];
+
+/// Most Wave64 chunk GUIDs are the chunk's FourCC followed by these bytes.
+const WAVE64_GUID_SUFFIX: [u8; 12] = [
+    0xf3, 0xac, 0xd3, 0x11, 0x8c, 0xd1, 0x00, 0xc0, 0x4f, 0x8e, 0xdb, 0x8a,
+];
+
+/// Get the RIFF chunk ID equivalent to a Wave64 chunk GUID. Chunks with a GUID
+/// that doesn't follow the usual pattern keep the first four bytes of it.
+pub(crate) fn wave64_id(guid: &[u8; 16]) -> [u8; 4] {
+    match (guid, &guid[..4]) {
+        (&WAVE64_LIST, _) => *b"LIST",
+        (_, b"junk") if guid[4..] == WAVE64_GUID_SUFFIX => *b"JUNK",
+        _ => guid[..4].try_into().unwrap(),
+    }
+}
+
+/// The reverse of [`wave64_id`].
+pub(crate) fn wave64_guid(id: &[u8; 4]) -> [u8; 16] {
+    if id == b"LIST" {
+        return WAVE64_LIST;
+    }
+
+    let mut guid = [0; 16];
+    guid[..4].copy_from_slice(if id == b"JUNK" { b"junk" } else { id });
+    guid[4..].copy_from_slice(&WAVE64_GUID_SUFFIX);
+    guid
+}
+
+pub(crate) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ds64_chunk_round_trip() {
+        let chunk = Ds64Chunk {
+            riff_size: 0x1_0000_0000,
+            data_size: 0x1_0000_0010,
+            sample_count: 0x4000_0004,
+            table: vec![(*b"bext", 0x1_0000_0001)],
+        };
+        let bytes = chunk.to_bytes();
+        assert_eq!(bytes.len(), 28 + 12);
+        assert_eq!(Ds64Chunk::from_bytes(&bytes).unwrap(), chunk);
+        assert_eq!(chunk.chunk_size(b"bext"), Some(0x1_0000_0001));
+        assert_eq!(chunk.chunk_size(b"data"), Some(0x1_0000_0010));
+        assert!(Ds64Chunk::from_bytes(&bytes[..36]).is_err());
+    }
+
+    #[test]
+    fn wave64_guids() {
+        for id in &[b"fmt ", b"data", b"LIST", b"JUNK"] {
+            assert_eq!(&wave64_id(&wave64_guid(id)), *id);
+        }
+
+        assert_eq!(wave64_guid(b"LIST"), WAVE64_LIST);
+        assert_eq!(&wave64_guid(b"JUNK")[..4], b"junk");
+    }
+}
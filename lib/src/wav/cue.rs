@@ -0,0 +1,196 @@
+use super::read_u32;
+use crate::error::{Error, Result};
+use std::convert::TryInto;
+
+/// Cue point (`cue `) chunk, containing a list of marked positions in the
+/// sample, such as slice markers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CueChunk {
+    points: Vec<CuePoint>,
+
+    /// Any bytes found after the declared cue points. These are kept so that
+    /// the chunk can be written back out unchanged.
+    trailer: Vec<u8>,
+}
+
+impl CueChunk {
+    pub(crate) const ID: &[u8; 4] = b"cue ";
+
+    /// Parse a cue chunk from the chunk contents, not including the chunk ID
+    /// and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 4 {
+            return Err(Error::invalid_chunk(Self::ID));
+        }
+
+        let count = read_u32(bytes, 0) as usize;
+        let points_end = count
+            .checked_mul(CuePoint::LEN)
+            .and_then(|len| len.checked_add(4))
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| Error::truncated_chunk(Self::ID))?;
+
+        Ok(Self {
+            points: bytes[4..points_end]
+                .chunks_exact(CuePoint::LEN)
+                .map(CuePoint::from_bytes)
+                .collect(),
+            trailer: bytes[points_end..].to_vec(),
+        })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.points.len() * CuePoint::LEN);
+        bytes.extend_from_slice(&(self.points.len() as u32).to_le_bytes());
+
+        for point in &self.points {
+            point.write_to(&mut bytes);
+        }
+
+        bytes.extend_from_slice(&self.trailer);
+        bytes
+    }
+
+    pub fn points(&self) -> &[CuePoint] {
+        &self.points
+    }
+
+    pub fn points_mut(&mut self) -> &mut Vec<CuePoint> {
+        &mut self.points
+    }
+}
+
+/// A single cue point inside a cue chunk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CuePoint {
+    /// Unique ID of the cue point, used to refer to it from other chunks.
+    pub id: u32,
+    /// Position of the cue point in sample frames from the start of playback.
+    pub position: u32,
+    /// ID of the chunk containing the cue point, usually `data`.
+    pub data_chunk_id: [u8; 4],
+    /// Offset of the chunk containing the cue point when the audio is stored
+    /// in a wave list. Zero for a plain `data` chunk.
+    pub chunk_start: u32,
+    /// Offset of the block containing the cue point for compressed audio. Zero
+    /// for uncompressed audio.
+    pub block_start: u32,
+    /// Offset of the cue point within its block, in sample frames.
+    pub sample_offset: u32,
+}
+
+impl CuePoint {
+    const LEN: usize = 24;
+
+    /// Create a new cue point at the given sample frame in the `data` chunk.
+    pub fn new(id: u32, position: u32) -> Self {
+        Self {
+            id,
+            position,
+            data_chunk_id: *b"data",
+            chunk_start: 0,
+            block_start: 0,
+            sample_offset: position,
+        }
+    }
+
+    /// Move this cue point to a new sample frame.
+    pub fn set_position(&mut self, position: u32) {
+        if &self.data_chunk_id == b"data" && self.chunk_start == 0 && self.block_start == 0 {
+            self.sample_offset = position;
+        }
+
+        self.position = position;
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            id: read_u32(bytes, 0),
+            position: read_u32(bytes, 4),
+            data_chunk_id: bytes[8..12].try_into().unwrap(),
+            chunk_start: read_u32(bytes, 12),
+            block_start: read_u32(bytes, 16),
+            sample_offset: read_u32(bytes, 20),
+        }
+    }
+
+    fn write_to(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.id.to_le_bytes());
+        bytes.extend_from_slice(&self.position.to_le_bytes());
+        bytes.extend_from_slice(&self.data_chunk_id);
+        bytes.extend_from_slice(&self.chunk_start.to_le_bytes());
+        bytes.extend_from_slice(&self.block_start.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_offset.to_le_bytes());
+    }
+}
+
+/// A cue point along with its label, combining the `cue ` chunk and the
+/// `LIST/adtl` chunk into something more convenient to work with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Marker {
+    pub id: u32,
+    /// Position of the marker in sample frames.
+    pub position: u32,
+    pub label: Option<String>,
+}
+
+impl Marker {
+    /// Get an ID for a new marker that doesn't conflict with any of the given
+    /// markers.
+    pub fn next_id(markers: &[Marker]) -> u32 {
+        markers.iter().map(|marker| marker.id).max().unwrap_or(0) + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cue_chunk_round_trip() {
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+
+        for value in &[1u32, 100] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(b"data");
+        for value in &[0u32, 0, 100, 2, 200] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(b"slnt");
+        for value in &[16u32, 0, 50] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let chunk = CueChunk::from_bytes(&bytes).unwrap();
+
+        assert_eq!(chunk.points()[0], CuePoint::new(1, 100));
+        assert_eq!(chunk.points()[1].id, 2);
+        assert_eq!(chunk.points()[1].position, 200);
+        assert_eq!(&chunk.points()[1].data_chunk_id, b"slnt");
+        assert_eq!(chunk.points()[1].chunk_start, 16);
+        assert_eq!(chunk.points()[1].sample_offset, 50);
+        assert_eq!(chunk.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn cue_chunk_rejects_truncated_points() {
+        let mut bytes = 2u32.to_le_bytes().to_vec();
+        CuePoint::new(1, 100).write_to(&mut bytes);
+
+        assert!(CueChunk::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn set_position_only_moves_sample_offset_in_data_chunk() {
+        let mut point = CuePoint::new(1, 100);
+        point.set_position(200);
+        assert_eq!(point.sample_offset, 200);
+
+        point.data_chunk_id = *b"slnt";
+        point.set_position(300);
+        assert_eq!(point.position, 300);
+        assert_eq!(point.sample_offset, 200);
+    }
+}
@@ -0,0 +1,466 @@
+use super::read_u32;
+use crate::{
+    error::{Error, Result},
+    pcm::{Encoding, SampleType},
+};
+use std::{
+    convert::{TryFrom, TryInto},
+    fmt,
+};
+
+/// Encoding of the samples in the audio data, resolved from the format tag and
+/// the sub-format of extensible formats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// Integer linear PCM.
+    Pcm,
+    /// IEEE 754 floating point.
+    IeeeFloat,
+    ALaw,
+    MuLaw,
+    Other(u16),
+}
+
+impl From<u16> for SampleFormat {
+    fn from(tag: u16) -> Self {
+        match tag {
+            FormatChunk::WAVE_FORMAT_PCM => Self::Pcm,
+            FormatChunk::WAVE_FORMAT_IEEE_FLOAT => Self::IeeeFloat,
+            FormatChunk::WAVE_FORMAT_ALAW => Self::ALaw,
+            FormatChunk::WAVE_FORMAT_MULAW => Self::MuLaw,
+            tag => Self::Other(tag),
+        }
+    }
+}
+
+impl From<SampleFormat> for u16 {
+    fn from(format: SampleFormat) -> Self {
+        match format {
+            SampleFormat::Pcm => FormatChunk::WAVE_FORMAT_PCM,
+            SampleFormat::IeeeFloat => FormatChunk::WAVE_FORMAT_IEEE_FLOAT,
+            SampleFormat::ALaw => FormatChunk::WAVE_FORMAT_ALAW,
+            SampleFormat::MuLaw => FormatChunk::WAVE_FORMAT_MULAW,
+            SampleFormat::Other(tag) => tag,
+        }
+    }
+}
+
+impl fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pcm => f.write_str("PCM"),
+            Self::IeeeFloat => f.write_str("IEEE float"),
+            Self::ALaw => f.write_str("A-law"),
+            Self::MuLaw => f.write_str("µ-law"),
+            Self::Other(tag) => write!(f, "format 0x{:04x}", tag),
+        }
+    }
+}
+
+/// The sub-format GUID of an extensible format is a format tag followed by
+/// these bytes.
+const SUB_FORMAT_GUID_SUFFIX: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+
+/// Format (`fmt `) chunk, describing how the audio data is encoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatChunk {
+    format_tag: u16,
+    channels: u16,
+    sample_rate: u32,
+    byte_rate: u32,
+    block_align: u16,
+    bits_per_sample: u16,
+
+    /// Extension bytes following the basic fields, starting with the size of
+    /// the extension. Extensible formats store their sub-format here.
+    extension: Vec<u8>,
+}
+
+impl FormatChunk {
+    pub(crate) const ID: &[u8; 4] = b"fmt ";
+    const LEN: usize = 16;
+
+    pub const WAVE_FORMAT_PCM: u16 = 0x0001;
+    pub const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+    pub const WAVE_FORMAT_ALAW: u16 = 0x0006;
+    pub const WAVE_FORMAT_MULAW: u16 = 0x0007;
+    pub const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+    /// Create a format chunk for uncompressed audio, with each sample stored
+    /// in the smallest whole number of bytes that fits `bits_per_sample`.
+    ///
+    /// `WAVE_FORMAT_EXTENSIBLE` is used for more than two channels or bit
+    /// depths that aren't a multiple of 8, as required by the format. Fails
+    /// if the frame size or byte rate is too large to be stored.
+    pub fn new(
+        format: SampleFormat,
+        channels: u16,
+        sample_rate: u32,
+        bits_per_sample: u16,
+    ) -> Result<Self> {
+        let too_large = || {
+            Error::InvalidInput(format!(
+                "{} channels of {}-bit samples at {} Hz is too large for a WAV file",
+                channels, bits_per_sample, sample_rate
+            ))
+        };
+        let container_bytes = (bits_per_sample as u32).div_ceil(8);
+        let container_bits = u16::try_from(container_bytes * 8).map_err(|_| too_large())?;
+        let block_align =
+            u16::try_from(channels as u32 * container_bytes).map_err(|_| too_large())?;
+        let byte_rate = sample_rate
+            .checked_mul(block_align as u32)
+            .ok_or_else(too_large)?;
+        let mut chunk = Self {
+            format_tag: format.into(),
+            channels,
+            sample_rate,
+            byte_rate,
+            block_align,
+            bits_per_sample: container_bits,
+            extension: Vec::new(),
+        };
+
+        if channels > 2 || container_bits != bits_per_sample {
+            chunk.make_extensible(bits_per_sample, 0);
+        } else if format != SampleFormat::Pcm {
+            // Formats other than PCM must declare the size of the extension,
+            // even if it is empty.
+            chunk.extension = vec![0, 0];
+        }
+
+        Ok(chunk)
+    }
+
+    /// Parse a format chunk from the chunk contents, not including the chunk
+    /// ID and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::LEN {
+            return Err(Error::invalid_chunk(Self::ID));
+        }
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+
+        Ok(Self {
+            format_tag: read_u16(0),
+            channels: read_u16(2),
+            sample_rate: read_u32(bytes, 4),
+            byte_rate: read_u32(bytes, 8),
+            block_align: read_u16(12),
+            bits_per_sample: read_u16(14),
+            extension: bytes[Self::LEN..].to_vec(),
+        })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::LEN + self.extension.len());
+
+        bytes.extend_from_slice(&self.format_tag.to_le_bytes());
+        bytes.extend_from_slice(&self.channels.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&self.byte_rate.to_le_bytes());
+        bytes.extend_from_slice(&self.block_align.to_le_bytes());
+        bytes.extend_from_slice(&self.bits_per_sample.to_le_bytes());
+        bytes.extend_from_slice(&self.extension);
+        bytes
+    }
+
+    /// The raw format tag, such as [`WAVE_FORMAT_PCM`](Self::WAVE_FORMAT_PCM).
+    pub fn format_tag(&self) -> u16 {
+        self.format_tag
+    }
+
+    /// The encoding of the samples. For extensible formats this is taken from
+    /// the sub-format.
+    pub fn sample_format(&self) -> SampleFormat {
+        match self.sub_format() {
+            Some(sub_format) => u16::from_le_bytes([sub_format[0], sub_format[1]]).into(),
+            None => self.format_tag.into(),
+        }
+    }
+
+    /// Whether the chunk uses `WAVE_FORMAT_EXTENSIBLE`.
+    pub fn is_extensible(&self) -> bool {
+        self.format_tag == Self::WAVE_FORMAT_EXTENSIBLE
+    }
+
+    /// Get the same format without `WAVE_FORMAT_EXTENSIBLE`, for software
+    /// that doesn't support it.
+    ///
+    /// Returns `None` if the format isn't extensible, or if something would be
+    /// lost: a sub-format other than PCM or IEEE float, more than two
+    /// channels, fewer valid bits than stored bits, or a channel mask other
+    /// than the default for mono or stereo.
+    pub fn to_plain(&self) -> Option<Self> {
+        let fields = self.extensible_fields()?;
+        let format = self.sample_format();
+        let default_mask = match self.channels {
+            1 => 0x4,
+            2 => 0x3,
+            _ => return None,
+        };
+
+        if !matches!(format, SampleFormat::Pcm | SampleFormat::IeeeFloat)
+            || self.extension.len() != fields.len()
+            || self.valid_bits_per_sample() != self.bits_per_sample
+            || !self.bits_per_sample.is_multiple_of(8)
+            || self.block_align != self.channels * self.bits_per_sample / 8
+            || !matches!(self.channel_mask(), Some(mask) if mask == 0 || mask == default_mask)
+        {
+            return None;
+        }
+
+        Some(Self {
+            format_tag: format.into(),
+            // Formats other than PCM must declare an empty extension.
+            extension: match format {
+                SampleFormat::Pcm => Vec::new(),
+                _ => vec![0, 0],
+            },
+            ..self.clone()
+        })
+    }
+
+    /// Number of interleaved channels.
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    /// Number of sample frames per second.
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Average number of bytes per second.
+    pub fn byte_rate(&self) -> u32 {
+        self.byte_rate
+    }
+
+    /// Size of a single sample frame in bytes, across all channels.
+    pub fn block_align(&self) -> u16 {
+        self.block_align
+    }
+
+    /// Size of a single sample in bits, as stored in the file.
+    pub fn bits_per_sample(&self) -> u16 {
+        self.bits_per_sample
+    }
+
+    /// Number of bits actually used in each sample, which may be less than
+    /// [`bits_per_sample`](Self::bits_per_sample) for extensible formats.
+    pub fn valid_bits_per_sample(&self) -> u16 {
+        match self.extensible_fields() {
+            Some(fields) if fields[2] != 0 || fields[3] != 0 => {
+                u16::from_le_bytes([fields[2], fields[3]])
+            }
+            _ => self.bits_per_sample,
+        }
+    }
+
+    /// Bit mask of the speaker positions the channels map to, if the format is
+    /// extensible.
+    pub fn channel_mask(&self) -> Option<u32> {
+        self.extensible_fields().map(|fields| read_u32(fields, 4))
+    }
+
+    /// Set the speaker positions the channels map to, switching to an
+    /// extensible format if necessary.
+    pub fn set_channel_mask(&mut self, mask: u32) {
+        let valid_bits = self.valid_bits_per_sample();
+        self.make_extensible(valid_bits, mask);
+    }
+
+    /// GUID identifying the encoding of an extensible format.
+    pub fn sub_format(&self) -> Option<&[u8; 16]> {
+        self.extensible_fields()
+            .map(|fields| fields[8..24].try_into().unwrap())
+    }
+
+    /// Get the encoding of samples in the audio data, or an error if the
+    /// format isn't supported.
+    pub(crate) fn encoding(&self) -> Result<Encoding> {
+        let channels = self.channels as usize;
+        let bytes = self.block_align as usize / channels.max(1);
+
+        if channels == 0 || bytes * channels != self.block_align as usize {
+            return Err(Error::invalid_chunk(Self::ID));
+        }
+
+        let bits = (self.valid_bits_per_sample() as u32).min(bytes as u32 * 8);
+        let sample_type = match (self.sample_format(), bytes) {
+            (SampleFormat::Pcm, 1..=4) if bits > 0 => SampleType::Int { bytes, bits },
+            (SampleFormat::IeeeFloat, 4) => SampleType::Float32,
+            (SampleFormat::IeeeFloat, 8) => SampleType::Float64,
+            (format, _) => {
+                return Err(Error::Unsupported(format!(
+                    "unsupported sample format: {} with {}-bit samples",
+                    format,
+                    bytes * 8
+                )))
+            }
+        };
+
+        Ok(Encoding {
+            sample_type,
+            big_endian: false,
+        })
+    }
+
+    fn make_extensible(&mut self, valid_bits: u16, mask: u32) {
+        let format_tag = u16::from(self.sample_format());
+
+        self.extension.clear();
+        self.extension.extend_from_slice(&22u16.to_le_bytes());
+        self.extension.extend_from_slice(&valid_bits.to_le_bytes());
+        self.extension.extend_from_slice(&mask.to_le_bytes());
+        self.extension.extend_from_slice(&format_tag.to_le_bytes());
+        self.extension.extend_from_slice(&SUB_FORMAT_GUID_SUFFIX);
+        self.format_tag = Self::WAVE_FORMAT_EXTENSIBLE;
+    }
+
+    /// The extensible fields, including the extension size, if present.
+    fn extensible_fields(&self) -> Option<&[u8]> {
+        if self.is_extensible() && self.extension.len() >= 24 {
+            Some(&self.extension[..24])
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcm_format_chunk() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&1u16.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&44100u32.to_le_bytes());
+        bytes.extend_from_slice(&176400u32.to_le_bytes());
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(&16u16.to_le_bytes());
+
+        let chunk = FormatChunk::from_bytes(&bytes).unwrap();
+
+        assert_eq!(chunk.sample_format(), SampleFormat::Pcm);
+        assert!(!chunk.is_extensible());
+        assert_eq!(chunk.channels(), 2);
+        assert_eq!(chunk.sample_rate(), 44100);
+        assert_eq!(chunk.block_align(), 4);
+        assert_eq!(chunk.valid_bits_per_sample(), 16);
+        assert_eq!(chunk.channel_mask(), None);
+        assert_eq!(chunk.to_bytes(), bytes);
+
+        assert!(FormatChunk::from_bytes(&bytes[..14]).is_err());
+    }
+
+    #[test]
+    fn extensible_format_chunk() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0xfffeu16.to_le_bytes());
+        bytes.extend_from_slice(&6u16.to_le_bytes());
+        bytes.extend_from_slice(&48000u32.to_le_bytes());
+        bytes.extend_from_slice(&(48000u32 * 24).to_le_bytes());
+        bytes.extend_from_slice(&24u16.to_le_bytes());
+        bytes.extend_from_slice(&32u16.to_le_bytes());
+        bytes.extend_from_slice(&22u16.to_le_bytes());
+        bytes.extend_from_slice(&24u16.to_le_bytes());
+        bytes.extend_from_slice(&0x3fu32.to_le_bytes());
+        bytes.extend_from_slice(&[
+            0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38,
+            0x9b, 0x71,
+        ]);
+
+        let chunk = FormatChunk::from_bytes(&bytes).unwrap();
+
+        assert!(chunk.is_extensible());
+        assert_eq!(chunk.sample_format(), SampleFormat::IeeeFloat);
+        assert_eq!(chunk.bits_per_sample(), 32);
+        assert_eq!(chunk.valid_bits_per_sample(), 24);
+        assert_eq!(chunk.channel_mask(), Some(0x3f));
+        assert_eq!(chunk.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn new_format_chunk() {
+        let chunk = FormatChunk::new(SampleFormat::Pcm, 2, 44100, 16).unwrap();
+        assert_eq!(chunk.to_bytes().len(), 16);
+        assert_eq!(chunk.block_align(), 4);
+        assert_eq!(chunk.byte_rate(), 176400);
+
+        let chunk = FormatChunk::new(SampleFormat::IeeeFloat, 1, 48000, 32).unwrap();
+        assert_eq!(chunk.to_bytes().len(), 18);
+        assert_eq!(chunk.format_tag(), FormatChunk::WAVE_FORMAT_IEEE_FLOAT);
+
+        let mut chunk = FormatChunk::new(SampleFormat::Pcm, 2, 48000, 20).unwrap();
+        chunk.set_channel_mask(0x3);
+        assert!(chunk.is_extensible());
+        assert_eq!(chunk.sample_format(), SampleFormat::Pcm);
+        assert_eq!(chunk.bits_per_sample(), 24);
+        assert_eq!(chunk.valid_bits_per_sample(), 20);
+        assert_eq!(chunk.block_align(), 6);
+        assert_eq!(chunk.channel_mask(), Some(0x3));
+        assert_eq!(FormatChunk::from_bytes(&chunk.to_bytes()).unwrap(), chunk);
+    }
+
+    #[test]
+    fn new_format_chunk_too_large() {
+        let chunk = FormatChunk::new(SampleFormat::Pcm, 3000, 44100, 32).unwrap();
+        assert_eq!(chunk.block_align(), 12000);
+
+        assert!(FormatChunk::new(SampleFormat::Pcm, 20000, 44100, 32).is_err());
+        assert!(FormatChunk::new(SampleFormat::Pcm, 2, u32::MAX, 16).is_err());
+        assert!(FormatChunk::new(SampleFormat::Pcm, 1, 44100, u16::MAX).is_err());
+    }
+
+    #[test]
+    fn plain_format_chunk() {
+        let plain = FormatChunk::new(SampleFormat::Pcm, 2, 44100, 24).unwrap();
+
+        let mut chunk = plain.clone();
+        chunk.set_channel_mask(0x3);
+        assert!(chunk.is_extensible());
+        assert_eq!(chunk.to_plain(), Some(plain.clone()));
+        assert_eq!(plain.to_plain(), None);
+
+        let mut chunk = FormatChunk::new(SampleFormat::IeeeFloat, 1, 48000, 32).unwrap();
+        chunk.set_channel_mask(0);
+        assert_eq!(
+            chunk.to_plain(),
+            Some(FormatChunk::new(SampleFormat::IeeeFloat, 1, 48000, 32).unwrap())
+        );
+
+        let mut chunk = plain;
+        chunk.set_channel_mask(0x6);
+        assert_eq!(chunk.to_plain(), None);
+
+        assert_eq!(
+            FormatChunk::new(SampleFormat::Pcm, 2, 48000, 20)
+                .unwrap()
+                .to_plain(),
+            None
+        );
+        assert_eq!(
+            FormatChunk::new(SampleFormat::Pcm, 4, 48000, 16)
+                .unwrap()
+                .to_plain(),
+            None
+        );
+    }
+
+    #[test]
+    fn unsupported_encodings() {
+        let format =
+            |format, channels, bits| FormatChunk::new(format, channels, 8000, bits).unwrap();
+
+        assert!(format(SampleFormat::Pcm, 2, 24).encoding().is_ok());
+        assert!(format(SampleFormat::ALaw, 1, 8).encoding().is_err());
+        assert!(format(SampleFormat::IeeeFloat, 1, 16).encoding().is_err());
+        assert!(format(SampleFormat::Pcm, 0, 16).encoding().is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use super::{read_sub_chunks, write_chunk_to};
+use std::{convert::TryInto, str};
+
+/// Well-known INFO tags along with friendlier names for them.
+const TAG_NAMES: &[(&[u8; 4], &str)] = &[
+    (b"INAM", "title"),
+    (b"IART", "artist"),
+    (b"ICMT", "comment"),
+    (b"IGNR", "genre"),
+    (b"ICOP", "copyright"),
+    (b"ISFT", "software"),
+    (b"ICRD", "date"),
+    (b"IPRD", "album"),
+    (b"IPRT", "track"),
+    (b"IKEY", "keywords"),
+    (b"IENG", "engineer"),
+    (b"ISBJ", "subject"),
+    (b"ISRC", "source"),
+];
+
+/// Metadata tags (`LIST` chunk of type `INFO`), such as the title and artist.
+///
+/// Tags are kept in their original order, and the raw value of any tag that
+/// isn't modified is preserved as-is.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ListInfo {
+    entries: Vec<([u8; 4], Vec<u8>)>,
+}
+
+impl ListInfo {
+    pub(crate) const LIST_TYPE: &[u8; 4] = b"INFO";
+
+    /// Parse tags from the list contents, not including the list type.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            entries: read_sub_chunks(bytes)
+                .into_iter()
+                .map(|(id, data)| (id, data.to_vec()))
+                .collect(),
+        }
+    }
+
+    /// Serialize the list contents, not including the list type.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for (id, data) in &self.entries {
+            write_chunk_to(&mut bytes, id, data).unwrap();
+        }
+
+        bytes
+    }
+
+    /// Get the four-character ID of a tag from either the ID itself (such as
+    /// `INAM`) or a friendly name (such as `title`). IDs must be made up of
+    /// uppercase letters and digits.
+    pub fn tag_id(name: &str) -> Option<[u8; 4]> {
+        if let Some((id, _)) = TAG_NAMES
+            .iter()
+            .find(|(_, tag_name)| tag_name.eq_ignore_ascii_case(name))
+        {
+            return Some(**id);
+        }
+
+        match name.as_bytes().try_into() {
+            Ok(id)
+                if name
+                    .bytes()
+                    .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit()) =>
+            {
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the friendly name of a tag, if it has one.
+    pub fn tag_name(id: &[u8; 4]) -> Option<&'static str> {
+        TAG_NAMES
+            .iter()
+            .find(|(tag_id, _)| *tag_id == id)
+            .map(|(_, name)| *name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over the ID and value of every tag.
+    pub fn iter(&self) -> impl Iterator<Item = ([u8; 4], String)> + '_ {
+        self.entries.iter().map(|(id, data)| (*id, decode(data)))
+    }
+
+    pub fn get(&self, id: &[u8; 4]) -> Option<String> {
+        self.entries
+            .iter()
+            .find(|(tag_id, _)| tag_id == id)
+            .map(|(_, data)| decode(data))
+    }
+
+    /// Set the value of a tag, replacing any existing value.
+    pub fn set(&mut self, id: &[u8; 4], value: &str) {
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+
+        match self.entries.iter().position(|(tag_id, _)| tag_id == id) {
+            Some(i) => {
+                self.entries[i].1 = data;
+
+                // Drop any duplicate entries after the first one.
+                let mut seen = false;
+                self.entries.retain(|(tag_id, _)| {
+                    let duplicate = tag_id == id && seen;
+                    seen |= tag_id == id;
+                    !duplicate
+                });
+            }
+            None => self.entries.push((*id, data)),
+        }
+    }
+
+    /// Remove a tag, returning true if it was present.
+    pub fn remove(&mut self, id: &[u8; 4]) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(tag_id, _)| tag_id != id);
+        self.entries.len() != len
+    }
+}
+
+/// Decode a tag value, which is a null-terminated string that may also have
+/// extra padding.
+fn decode(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+
+    match str::from_utf8(&bytes[..end]) {
+        Ok(s) => s.to_owned(),
+        // Older software commonly writes tags in Latin-1.
+        Err(_) => bytes[..end].iter().map(|b| *b as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_info_round_trip() {
+        let mut bytes = Vec::new();
+        write_chunk_to(&mut bytes, b"INAM", b"Kick\0").unwrap();
+        write_chunk_to(&mut bytes, b"IXYZ", b"custom\0\0").unwrap();
+        write_chunk_to(&mut bytes, b"ICMT", b"Caf\xe9\0").unwrap();
+
+        let info = ListInfo::from_bytes(&bytes);
+
+        assert_eq!(info.get(b"INAM").as_deref(), Some("Kick"));
+        assert_eq!(info.get(b"IXYZ").as_deref(), Some("custom"));
+        assert_eq!(info.get(b"ICMT").as_deref(), Some("Café"));
+        assert_eq!(info.get(b"IART"), None);
+        assert_eq!(info.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn set_and_remove_tags() {
+        let mut info = ListInfo::default();
+        info.set(b"INAM", "Kick");
+        info.set(b"IART", "Someone");
+        info.entries.push((*b"INAM", b"Old\0".to_vec()));
+
+        info.set(b"INAM", "Snare");
+        assert_eq!(
+            info.iter().collect::<Vec<_>>(),
+            vec![(*b"INAM", "Snare".into()), (*b"IART", "Someone".into())]
+        );
+
+        assert!(info.remove(b"IART"));
+        assert!(!info.remove(b"IART"));
+        assert_eq!(info.iter().count(), 1);
+    }
+
+    #[test]
+    fn tag_ids() {
+        assert_eq!(ListInfo::tag_id("title"), Some(*b"INAM"));
+        assert_eq!(ListInfo::tag_id("Genre"), Some(*b"IGNR"));
+        assert_eq!(ListInfo::tag_id("ISFT"), Some(*b"ISFT"));
+        assert_eq!(ListInfo::tag_id("IXYZ"), Some(*b"IXYZ"));
+        assert_eq!(ListInfo::tag_id("nope"), None);
+        assert_eq!(ListInfo::tag_id("I Z"), None);
+        assert_eq!(ListInfo::tag_name(b"IART"), Some("artist"));
+        assert_eq!(ListInfo::tag_name(b"IXYZ"), None);
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    error::{Error, Result},
+    midi,
+};
+
+/// Instrument (`inst`) chunk, describing how a sample should be mapped when
+/// used as part of an instrument.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstrumentChunk {
+    unshifted_note: u8,
+    fine_tune: i8,
+    gain: i8,
+    low_note: u8,
+    high_note: u8,
+    low_velocity: u8,
+    high_velocity: u8,
+
+    /// Any bytes found after the standard fields. These are kept so that the
+    /// chunk can be written back out unchanged.
+    trailer: Vec<u8>,
+}
+
+impl Default for InstrumentChunk {
+    fn default() -> Self {
+        Self {
+            unshifted_note: 60,
+            fine_tune: 0,
+            gain: 0,
+            low_note: 0,
+            high_note: 127,
+            low_velocity: 1,
+            high_velocity: 127,
+            trailer: Vec::new(),
+        }
+    }
+}
+
+impl InstrumentChunk {
+    pub(crate) const ID: &[u8; 4] = b"inst";
+    const LEN: usize = 7;
+
+    /// Parse an instrument chunk from the chunk contents, not including the
+    /// chunk ID and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::LEN {
+            return Err(Error::invalid_chunk(Self::ID));
+        }
+
+        Ok(Self {
+            unshifted_note: bytes[0],
+            fine_tune: bytes[1] as i8,
+            gain: bytes[2] as i8,
+            low_note: bytes[3],
+            high_note: bytes[4],
+            low_velocity: bytes[5],
+            high_velocity: bytes[6],
+            trailer: bytes[Self::LEN..].to_vec(),
+        })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![
+            self.unshifted_note,
+            self.fine_tune as u8,
+            self.gain as u8,
+            self.low_note,
+            self.high_note,
+            self.low_velocity,
+            self.high_velocity,
+        ];
+
+        bytes.extend_from_slice(&self.trailer);
+        bytes
+    }
+
+    /// The note at which the sample plays back without any pitch shifting.
+    pub fn unshifted_note(&self) -> midi::Note {
+        self.unshifted_note.into()
+    }
+
+    pub fn set_unshifted_note(&mut self, note: midi::Note) {
+        self.unshifted_note = note.into();
+    }
+
+    /// Pitch shift to apply when playing back the sample, in cents. Should be
+    /// in the range -50 to +50.
+    pub fn fine_tune(&self) -> i8 {
+        self.fine_tune
+    }
+
+    pub fn set_fine_tune(&mut self, cents: i8) {
+        self.fine_tune = cents;
+    }
+
+    /// Gain to apply when playing back the sample, in decibels.
+    pub fn gain(&self) -> i8 {
+        self.gain
+    }
+
+    pub fn set_gain(&mut self, decibels: i8) {
+        self.gain = decibels;
+    }
+
+    /// Lowest note in the key range the sample should be played for.
+    pub fn low_note(&self) -> midi::Note {
+        self.low_note.into()
+    }
+
+    pub fn set_low_note(&mut self, note: midi::Note) {
+        self.low_note = note.into();
+    }
+
+    /// Highest note in the key range the sample should be played for.
+    pub fn high_note(&self) -> midi::Note {
+        self.high_note.into()
+    }
+
+    pub fn set_high_note(&mut self, note: midi::Note) {
+        self.high_note = note.into();
+    }
+
+    /// Lowest velocity the sample should be played for.
+    pub fn low_velocity(&self) -> u8 {
+        self.low_velocity
+    }
+
+    pub fn set_low_velocity(&mut self, velocity: u8) {
+        self.low_velocity = velocity;
+    }
+
+    /// Highest velocity the sample should be played for.
+    pub fn high_velocity(&self) -> u8 {
+        self.high_velocity
+    }
+
+    pub fn set_high_velocity(&mut self, velocity: u8) {
+        self.high_velocity = velocity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instrument_chunk_round_trip() {
+        let bytes = [62, 0xfb, 3, 36, 48, 1, 100];
+        let chunk = InstrumentChunk::from_bytes(&bytes).unwrap();
+
+        assert_eq!(chunk.unshifted_note(), midi::Note::from(62));
+        assert_eq!(chunk.fine_tune(), -5);
+        assert_eq!(chunk.gain(), 3);
+        assert_eq!(chunk.low_note(), midi::Note::from(36));
+        assert_eq!(chunk.high_note(), midi::Note::from(48));
+        assert_eq!(chunk.low_velocity(), 1);
+        assert_eq!(chunk.high_velocity(), 100);
+        assert_eq!(chunk.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn instrument_chunk_rejects_short_chunk() {
+        assert!(InstrumentChunk::from_bytes(&[60, 0, 0]).is_err());
+    }
+}
@@ -0,0 +1,1630 @@
+//! WAV format reading and writing routines.
+
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write},
+};
+
+mod acid;
+mod adtl;
+mod bext;
+mod check;
+mod container;
+mod cue;
+mod format;
+mod info;
+mod instrument;
+mod sampler;
+mod strip;
+mod writer;
+
+pub use crate::pcm::FrameReader;
+
+use crate::error::{Error, Result};
+
+use self::container::{read_u64, wave64_guid, wave64_id, Ds64Chunk, WAVE64_RIFF, WAVE64_WAVE};
+
+pub use self::{
+    acid::AcidChunk,
+    adtl::{AssociatedData, AssociatedDataList, LabeledText},
+    bext::BroadcastExtensionChunk,
+    check::Problem,
+    container::Container,
+    cue::{CueChunk, CuePoint, Marker},
+    format::{FormatChunk, SampleFormat},
+    info::ListInfo,
+    instrument::InstrumentChunk,
+    sampler::{LoopType, SampleLoop, SamplerChunk},
+    writer::WavWriter,
+};
+
+pub struct Wav<F> {
+    file: F,
+    container: Container,
+    /// Size of the RIFF chunk contents. Wave64 sizes include the header.
+    form_len: u64,
+    ds64: Option<Ds64Chunk>,
+}
+
+/// Location of a top-level chunk in the file.
+#[derive(Copy, Clone, Debug)]
+struct Chunk {
+    id: [u8; 4],
+    /// Offset of the chunk header.
+    offset: u64,
+    len: u64,
+}
+
+/// Location and size of a chunk in a WAV file, as listed by
+/// [`Wav::layout`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkInfo {
+    pub id: [u8; 4],
+    /// List type of a `LIST` chunk, such as `INFO`.
+    pub list_type: Option<[u8; 4]>,
+    /// List type of the `LIST` chunk this chunk is inside, or `None` for a
+    /// top-level chunk.
+    pub parent: Option<[u8; 4]>,
+    /// Offset of the chunk header from the start of the file.
+    pub offset: u64,
+    /// Size of the chunk contents, not including the header or any padding.
+    pub len: u64,
+    /// Whether the chunk runs past the end of the file or its `LIST` chunk.
+    pub truncated: bool,
+}
+
+impl<F: Read + Seek> Wav<F> {
+    pub fn new(mut file: F) -> Result<Self> {
+        let not_wav = || Error::NotRecognized { format: "WAV" };
+        let mut header = [0; 40];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut header[..12])
+            .map_err(Error::header("WAV"))?;
+
+        let container = match (&header[..4], &header[8..12]) {
+            (b"RIFF", b"WAVE") => Container::Riff,
+            (b"RF64", b"WAVE") => Container::Rf64,
+            (b"BW64", b"WAVE") => Container::Bw64,
+            (b"riff", _) => {
+                file.read_exact(&mut header[12..])
+                    .map_err(Error::header("WAV"))?;
+
+                if header[..16] != WAVE64_RIFF || header[24..] != WAVE64_WAVE {
+                    return Err(not_wav());
+                }
+
+                Container::Wave64
+            }
+            _ => return Err(not_wav()),
+        };
+
+        let mut wav = Self {
+            file,
+            container,
+            form_len: read_u32(&header, 4) as u64,
+            ds64: None,
+        };
+
+        match container {
+            Container::Rf64 | Container::Bw64 => {
+                let ds64 = match wav.chunks()?.first() {
+                    Some(chunk) if &chunk.id == Ds64Chunk::ID => {
+                        Ds64Chunk::from_bytes(&wav.read_contents(chunk)?)
+                            .map_err(|e| e.at_offset(chunk.offset))?
+                    }
+                    _ => return Err(Error::MissingChunk { id: *b"ds64" }),
+                };
+
+                wav.form_len = ds64.riff_size;
+                wav.ds64 = Some(ds64);
+            }
+            Container::Wave64 => wav.form_len = read_u64(&header, 16),
+            Container::Riff => {}
+        }
+
+        Ok(wav)
+    }
+
+    /// The container format of the file.
+    pub fn container(&self) -> Container {
+        self.container
+    }
+
+    pub fn get_sampler_chunk(&mut self) -> Result<Option<SamplerChunk>> {
+        self.parse_chunk(SamplerChunk::ID, SamplerChunk::from_bytes)
+    }
+
+    pub fn get_instrument_chunk(&mut self) -> Result<Option<InstrumentChunk>> {
+        self.parse_chunk(InstrumentChunk::ID, InstrumentChunk::from_bytes)
+    }
+
+    pub fn get_acid_chunk(&mut self) -> Result<Option<AcidChunk>> {
+        self.parse_chunk(AcidChunk::ID, AcidChunk::from_bytes)
+    }
+
+    pub fn get_cue_chunk(&mut self) -> Result<Option<CueChunk>> {
+        self.parse_chunk(CueChunk::ID, CueChunk::from_bytes)
+    }
+
+    pub fn get_associated_data_list(&mut self) -> Result<Option<AssociatedDataList>> {
+        Ok(self
+            .read_list(AssociatedDataList::LIST_TYPE)?
+            .map(|data| AssociatedDataList::from_bytes(&data)))
+    }
+
+    pub fn get_broadcast_extension_chunk(&mut self) -> Result<Option<BroadcastExtensionChunk>> {
+        self.parse_chunk(
+            BroadcastExtensionChunk::ID,
+            BroadcastExtensionChunk::from_bytes,
+        )
+    }
+
+    pub fn get_list_info(&mut self) -> Result<Option<ListInfo>> {
+        Ok(self
+            .read_list(ListInfo::LIST_TYPE)?
+            .map(|data| ListInfo::from_bytes(&data)))
+    }
+
+    /// Get all cue points in the file along with their labels.
+    pub fn get_markers(&mut self) -> Result<Vec<Marker>> {
+        let cue = self.get_cue_chunk()?.unwrap_or_default();
+        let adtl = self.get_associated_data_list()?.unwrap_or_default();
+
+        Ok(cue
+            .points()
+            .iter()
+            .map(|point| Marker {
+                id: point.id,
+                position: point.position,
+                label: adtl.label(point.id).map(ToOwned::to_owned),
+            })
+            .collect())
+    }
+
+    pub fn get_format_chunk(&mut self) -> Result<Option<FormatChunk>> {
+        self.parse_chunk(FormatChunk::ID, FormatChunk::from_bytes)
+    }
+
+    /// Get the number of sample frames in the audio data, based on the size of
+    /// the `data` chunk and the frame size declared in the `fmt ` chunk.
+    pub fn frame_count(&mut self) -> Result<Option<u64>> {
+        let format = match self.get_format_chunk()? {
+            Some(format) if format.block_align() > 0 => format,
+            _ => return Ok(None),
+        };
+
+        Ok(self
+            .find_chunk(b"data")?
+            .map(|chunk| chunk.len / format.block_align() as u64))
+    }
+
+    /// Get a reader that streams sample frames from the `data` chunk, decoded
+    /// according to the `fmt ` chunk.
+    pub fn frames(&mut self) -> Result<FrameReader<impl Read + '_>> {
+        let format = self
+            .get_format_chunk()?
+            .ok_or(Error::MissingChunk { id: *b"fmt " })?;
+        let data = self
+            .find_chunk(b"data")?
+            .ok_or(Error::MissingChunk { id: *b"data" })?;
+        let frames = match format.block_align() {
+            0 => 0,
+            block_align => data.len / block_align as u64,
+        };
+
+        self.file
+            .seek(SeekFrom::Start(self.contents_offset(&data)))?;
+
+        let encoding = format.encoding()?;
+
+        Ok(FrameReader::new(
+            BufReader::new((&mut self.file).take(data.len)),
+            encoding,
+            format.channels() as usize,
+            format.sample_rate(),
+            frames,
+        ))
+    }
+
+    /// Get the IDs of all top-level chunks, in the order they appear.
+    pub fn chunk_ids(&mut self) -> Result<Vec<[u8; 4]>> {
+        Ok(self.chunks()?.into_iter().map(|chunk| chunk.id).collect())
+    }
+
+    /// List every top-level chunk in the order they appear, with the
+    /// sub-chunks of each `LIST` chunk straight after it.
+    ///
+    /// Chunks that run past the end of the file are marked as truncated, and
+    /// a truncated `LIST` chunk lists the sub-chunks that are there.
+    pub fn layout(&mut self) -> Result<Vec<ChunkInfo>> {
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+        let mut layout = Vec::new();
+
+        for chunk in self.chunks()? {
+            let contents_offset = self.contents_offset(&chunk);
+            let available = chunk.len.min(file_len.saturating_sub(contents_offset));
+            let mut info = ChunkInfo {
+                id: chunk.id,
+                list_type: None,
+                parent: None,
+                offset: chunk.offset,
+                len: chunk.len,
+                truncated: available < chunk.len,
+            };
+
+            if &chunk.id != b"LIST" || available < 4 {
+                layout.push(info);
+                continue;
+            }
+
+            let mut contents = vec![0; available as usize];
+            self.file.seek(SeekFrom::Start(contents_offset))?;
+            self.file.read_exact(&mut contents)?;
+
+            let list_type = contents[..4].try_into().unwrap();
+            let mut offset = 4;
+            info.list_type = Some(list_type);
+            layout.push(info);
+
+            // Sub-chunks are found the same way as in `read_sub_chunks`.
+            while contents.len() - offset >= 8 {
+                let declared_len = read_u32(&contents, offset + 4) as usize;
+                let len = declared_len.min(contents.len() - offset - 8);
+
+                layout.push(ChunkInfo {
+                    id: contents[offset..offset + 4].try_into().unwrap(),
+                    list_type: None,
+                    parent: Some(list_type),
+                    offset: contents_offset + offset as u64,
+                    len: declared_len as u64,
+                    truncated: len < declared_len,
+                });
+                offset = (offset + 8 + padded_len(len as u64) as usize).min(contents.len());
+            }
+        }
+
+        Ok(layout)
+    }
+
+    /// Parse the contents of the first top-level chunk with the given ID,
+    /// adding the offset of the chunk to any error.
+    fn parse_chunk<T>(
+        &mut self,
+        id: &[u8; 4],
+        parse: impl FnOnce(&[u8]) -> Result<T>,
+    ) -> Result<Option<T>> {
+        match self.find_chunk(id)? {
+            Some(chunk) => parse(&self.read_contents(&chunk)?)
+                .map(Some)
+                .map_err(|e| e.at_offset(chunk.offset)),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the contents of the first top-level chunk with the given ID.
+    pub fn read_chunk(&mut self, id: &[u8; 4]) -> Result<Option<Vec<u8>>> {
+        match self.find_chunk(id)? {
+            Some(chunk) => self.read_contents(&chunk).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the contents of the first top-level `LIST` chunk with the given
+    /// list type, not including the list type.
+    pub fn read_list(&mut self, list_type: &[u8; 4]) -> Result<Option<Vec<u8>>> {
+        match self.find_list(list_type)? {
+            Some(chunk) => Ok(Some(self.read_contents(&chunk)?.split_off(4))),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the contents of a chunk, which must all be in the file.
+    fn read_contents(&mut self, chunk: &Chunk) -> Result<Vec<u8>> {
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+
+        if self.contents_offset(chunk).saturating_add(chunk.len) > file_len {
+            return Err(Error::truncated_chunk(&chunk.id).at_offset(chunk.offset));
+        }
+
+        let len = chunk
+            .len
+            .try_into()
+            .map_err(|_| Error::TooLarge("chunk too large"))?;
+        let mut data = vec![0; len];
+        self.file
+            .seek(SeekFrom::Start(self.contents_offset(chunk)))?;
+        self.file.read_exact(&mut data)?;
+
+        Ok(data)
+    }
+
+    fn find_list(&mut self, list_type: &[u8; 4]) -> Result<Option<Chunk>> {
+        for chunk in self.chunks()? {
+            if self.list_type(&chunk)?.as_ref() == Some(list_type) {
+                return Ok(Some(chunk));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Read the list type of a `LIST` chunk, or `None` for any other chunk.
+    fn list_type(&mut self, chunk: &Chunk) -> Result<Option<[u8; 4]>> {
+        if &chunk.id != b"LIST" || chunk.len < 4 {
+            return Ok(None);
+        }
+
+        let mut list_type = [0; 4];
+        self.file
+            .seek(SeekFrom::Start(self.contents_offset(chunk)))?;
+        self.file.read_exact(&mut list_type)?;
+
+        Ok(Some(list_type))
+    }
+
+    fn find_chunk(&mut self, id: &[u8; 4]) -> Result<Option<Chunk>> {
+        Ok(self.chunks()?.into_iter().find(|chunk| &chunk.id == id))
+    }
+
+    /// List the top-level chunks. A chunk header cut short by the end of the
+    /// RIFF chunk or file ends the list.
+    fn chunks(&mut self) -> Result<Vec<Chunk>> {
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+        let header_len = self.container.header_len();
+        let end = self.form_end().min(file_len);
+        let mut offset = self.first_chunk_offset();
+        let mut chunks = Vec::new();
+
+        while offset + header_len <= end {
+            let chunk = self.read_chunk_header(offset)?;
+
+            offset = self.padded_end(&chunk);
+            chunks.push(chunk);
+        }
+
+        Ok(chunks)
+    }
+
+    /// Read the header of the chunk at the given offset.
+    fn read_chunk_header(&mut self, offset: u64) -> Result<Chunk> {
+        let mut header = [0; 24];
+        let header = &mut header[..self.container.header_len() as usize];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(header)?;
+
+        Ok(match self.container {
+            Container::Wave64 => Chunk {
+                id: wave64_id(header[..16].try_into().unwrap()),
+                offset,
+                len: read_u64(header, 16).saturating_sub(24),
+            },
+            _ => {
+                let id = header[..4].try_into().unwrap();
+                let len = match (read_u32(header, 4), self.ds64.as_ref()) {
+                    (u32::MAX, Some(ds64)) => ds64.chunk_size(&id).unwrap_or(u32::MAX as u64),
+                    (len, _) => len as u64,
+                };
+
+                Chunk { id, offset, len }
+            }
+        })
+    }
+
+    /// Write a new file made up of the given chunks, in order, with the same
+    /// container as this file.
+    ///
+    /// Each chunk is copied from this file up to its length, unless it is
+    /// given new contents. Pad bytes are added where needed, and the RIFF size
+    /// and any `ds64` chunk are updated to match.
+    fn write_chunks(
+        &mut self,
+        mut output: impl Write + Seek,
+        chunks: &[(Chunk, Option<Vec<u8>>)],
+    ) -> Result<()> {
+        let container = self.container;
+        let block_align = self
+            .get_format_chunk()?
+            .map(|format| format.block_align() as u64)
+            .unwrap_or(1)
+            .max(1);
+
+        match container {
+            Container::Wave64 => {
+                output.write_all(&WAVE64_RIFF)?;
+                output.write_all(&[0; 8])?;
+                output.write_all(&WAVE64_WAVE)?;
+            }
+            _ => {
+                output.write_all(match container {
+                    Container::Rf64 => b"RF64",
+                    Container::Bw64 => b"BW64",
+                    _ => b"RIFF",
+                })?;
+                output.write_all(&[0; 4])?;
+                output.write_all(b"WAVE")?;
+            }
+        }
+
+        let mut ds64_offset = None;
+        let mut data_len = None;
+
+        for (chunk, contents) in chunks {
+            let len = match contents {
+                Some(contents) => contents.len() as u64,
+                None => chunk.len,
+            };
+
+            match &chunk.id {
+                b"data" => data_len = Some(len),
+                id if id == Ds64Chunk::ID => ds64_offset = Some(output.stream_position()?),
+                _ => {}
+            }
+
+            // Sizes too large for the chunk header are kept in the ds64 chunk.
+            let header_size = match container {
+                Container::Rf64 | Container::Bw64 => len.min(u32::MAX as u64),
+                _ => len,
+            };
+
+            container.write_chunk_header(&mut output, &chunk.id, header_size)?;
+
+            match contents {
+                Some(contents) => output.write_all(contents)?,
+                None => {
+                    self.file
+                        .seek(SeekFrom::Start(self.contents_offset(chunk)))?;
+
+                    if io::copy(&mut (&mut self.file).take(len), &mut output)? != len {
+                        return Err(Error::truncated_chunk(&chunk.id).at_offset(chunk.offset));
+                    }
+                }
+            }
+
+            output.write_all(&vec![0; (container.padded_len(len) - len) as usize])?;
+        }
+
+        let end = output.stream_position()?;
+
+        match container {
+            Container::Riff => {
+                let form_len: u32 = (end - 8)
+                    .try_into()
+                    .map_err(|_| Error::TooLarge("RIFF file too large"))?;
+
+                output.seek(SeekFrom::Start(4))?;
+                output.write_all(&form_len.to_le_bytes())?;
+            }
+            Container::Rf64 | Container::Bw64 => {
+                let mut ds64 = self.ds64.clone().unwrap_or_default();
+                ds64.riff_size = end - 8;
+
+                if let Some(len) = data_len {
+                    ds64.data_size = len;
+                    ds64.sample_count = len / block_align;
+                }
+
+                if let Some(offset) = ds64_offset {
+                    output.seek(SeekFrom::Start(offset + 8))?;
+                    output.write_all(&ds64.to_bytes())?;
+                }
+
+                output.seek(SeekFrom::Start(4))?;
+                output.write_all(&u32::MAX.to_le_bytes())?;
+            }
+            Container::Wave64 => {
+                output.seek(SeekFrom::Start(16))?;
+                output.write_all(&end.to_le_bytes())?;
+            }
+        }
+
+        output.seek(SeekFrom::Start(end))?;
+
+        Ok(())
+    }
+
+    /// Offset of the first chunk, just after the RIFF header.
+    fn first_chunk_offset(&self) -> u64 {
+        match self.container {
+            Container::Wave64 => 40,
+            _ => 12,
+        }
+    }
+
+    /// Offset of the end of the RIFF chunk according to its header.
+    fn form_end(&self) -> u64 {
+        match self.container {
+            Container::Wave64 => self.form_len,
+            _ => 8 + self.form_len,
+        }
+    }
+
+    fn contents_offset(&self, chunk: &Chunk) -> u64 {
+        chunk.offset + self.container.header_len()
+    }
+
+    /// Offset just past the end of a chunk, including any padding.
+    fn padded_end(&self, chunk: &Chunk) -> u64 {
+        self.contents_offset(chunk)
+            .saturating_add(self.container.padded_len(chunk.len))
+    }
+}
+
+impl<F: Read + Seek + Write> Wav<F> {
+    pub fn update_sampler_chunk(&mut self, f: impl FnOnce(&mut SamplerChunk)) -> Result<()> {
+        let mut chunk = self.get_sampler_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+
+        self.write_chunk(SamplerChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn update_instrument_chunk(&mut self, f: impl FnOnce(&mut InstrumentChunk)) -> Result<()> {
+        let mut chunk = self.get_instrument_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+
+        self.write_chunk(InstrumentChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn update_acid_chunk(&mut self, f: impl FnOnce(&mut AcidChunk)) -> Result<()> {
+        let mut chunk = self.get_acid_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+
+        self.write_chunk(AcidChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn update_cue_chunk(&mut self, f: impl FnOnce(&mut CueChunk)) -> Result<()> {
+        let mut chunk = self.get_cue_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+
+        self.write_chunk(CueChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn update_associated_data_list(
+        &mut self,
+        f: impl FnOnce(&mut AssociatedDataList),
+    ) -> Result<()> {
+        let mut list = self.get_associated_data_list()?.unwrap_or_default();
+        f(&mut list);
+
+        self.write_list(AssociatedDataList::LIST_TYPE, &list.to_bytes())
+    }
+
+    pub fn update_broadcast_extension_chunk(
+        &mut self,
+        f: impl FnOnce(&mut BroadcastExtensionChunk),
+    ) -> Result<()> {
+        let mut chunk = self.get_broadcast_extension_chunk()?.unwrap_or_default();
+        f(&mut chunk);
+
+        self.write_chunk(BroadcastExtensionChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn update_list_info(&mut self, f: impl FnOnce(&mut ListInfo)) -> Result<()> {
+        let mut info = self.get_list_info()?.unwrap_or_default();
+        f(&mut info);
+
+        self.write_list(ListInfo::LIST_TYPE, &info.to_bytes())
+    }
+
+    /// Edit the cue points in the file along with their labels.
+    ///
+    /// Markers that are removed also have all of their associated data
+    /// removed, and any existing cue point details not represented by a
+    /// marker are preserved.
+    pub fn update_markers(&mut self, f: impl FnOnce(&mut Vec<Marker>)) -> Result<()> {
+        let mut cue = self.get_cue_chunk()?;
+        let mut adtl = self.get_associated_data_list()?;
+        let mut markers = self.get_markers()?;
+        f(&mut markers);
+
+        let had_cue = cue.is_some();
+        let had_adtl = adtl.is_some();
+        let cue = cue.get_or_insert_with(Default::default);
+        let adtl = adtl.get_or_insert_with(Default::default);
+
+        for point in cue.points() {
+            if !markers.iter().any(|marker| marker.id == point.id) {
+                adtl.remove_cue_point(point.id);
+            }
+        }
+
+        let points = markers
+            .iter()
+            .map(|marker| {
+                let mut point = cue
+                    .points()
+                    .iter()
+                    .find(|point| point.id == marker.id)
+                    .cloned()
+                    .unwrap_or_else(|| CuePoint::new(marker.id, marker.position));
+
+                point.set_position(marker.position);
+                adtl.set_label(marker.id, marker.label.as_deref());
+
+                point
+            })
+            .collect();
+        *cue.points_mut() = points;
+
+        if had_cue || !cue.points().is_empty() {
+            self.write_chunk(CueChunk::ID, &cue.to_bytes())?;
+        }
+
+        if had_adtl || !adtl.is_empty() {
+            self.write_list(AssociatedDataList::LIST_TYPE, &adtl.to_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the contents of the first top-level chunk with the given ID,
+    /// or add the chunk after the last chunk if there isn't one.
+    ///
+    /// Unlike the typed `update_*` methods, the chunk keeps its place in the
+    /// file. If it grows, everything after it is moved along to make room.
+    pub fn set_chunk(&mut self, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        let existing = self.find_chunk(id)?;
+
+        self.set_chunk_in_place(existing, id, data)
+    }
+
+    /// Replace the contents of the first top-level `LIST` chunk with the
+    /// given list type, not including the list type, in the same way as
+    /// [`set_chunk`](Self::set_chunk).
+    pub fn set_list(&mut self, list_type: &[u8; 4], data: &[u8]) -> Result<()> {
+        let existing = self.find_list(list_type)?;
+        let mut contents = Vec::with_capacity(4 + data.len());
+        contents.extend_from_slice(list_type);
+        contents.extend_from_slice(data);
+
+        self.set_chunk_in_place(existing, b"LIST", &contents)
+    }
+
+    /// Add a new chunk after the last chunk, even if there is already a chunk
+    /// with the same ID.
+    pub fn insert_chunk(&mut self, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        self.append_chunk(id, data)
+    }
+
+    /// Turn the first top-level chunk with the given ID into a `JUNK` chunk,
+    /// which is ignored by readers, returning whether there was one.
+    ///
+    /// Unlike [`remove_chunk`](Wav::remove_chunk), nothing is moved.
+    pub fn junk_chunk(&mut self, id: &[u8; 4]) -> Result<bool> {
+        match self.find_chunk(id)? {
+            Some(chunk) => self.write_junk_id(&chunk).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Turn the first top-level `LIST` chunk with the given list type into a
+    /// `JUNK` chunk, in the same way as [`junk_chunk`](Self::junk_chunk).
+    pub fn junk_list(&mut self, list_type: &[u8; 4]) -> Result<bool> {
+        match self.find_list(list_type)? {
+            Some(chunk) => self.write_junk_id(&chunk).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Write a top-level chunk, replacing the first existing chunk with the
+    /// same ID if there is one.
+    fn write_chunk(&mut self, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        let existing = self.find_chunk(id)?;
+
+        self.replace_chunk(existing, id, data)
+    }
+
+    /// Write a top-level `LIST` chunk, replacing the first existing list with
+    /// the same list type if there is one.
+    fn write_list(&mut self, list_type: &[u8; 4], data: &[u8]) -> Result<()> {
+        let existing = self.find_list(list_type)?;
+        let mut contents = Vec::with_capacity(4 + data.len());
+        contents.extend_from_slice(list_type);
+        contents.extend_from_slice(data);
+
+        self.replace_chunk(existing, b"LIST", &contents)
+    }
+
+    /// Write a top-level chunk, either in place of an existing chunk or as a
+    /// new chunk.
+    ///
+    /// The file is never shifted around. If the new contents fit in the space
+    /// of the old chunk then they are written in place and any leftover space
+    /// is filled with a `JUNK` chunk. Otherwise the old chunk is turned into a
+    /// `JUNK` chunk and the new one is added after the last chunk.
+    fn replace_chunk(&mut self, existing: Option<Chunk>, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        let container = self.container;
+        let header_len = container.header_len();
+        let new_size = header_len + container.padded_len(data.len() as u64);
+
+        if let Some(existing) = existing {
+            let old_size = header_len + container.padded_len(existing.len);
+
+            if old_size == new_size || old_size >= new_size + header_len {
+                self.file.seek(SeekFrom::Start(existing.offset))?;
+                container.write_chunk_to(&mut self.file, id, data)?;
+
+                if old_size > new_size {
+                    container.write_chunk_to(
+                        &mut self.file,
+                        b"JUNK",
+                        &vec![0; (old_size - new_size - header_len) as usize],
+                    )?;
+                }
+
+                return Ok(());
+            }
+
+            self.write_junk_id(&existing)?;
+        }
+
+        self.append_chunk(id, data)
+    }
+
+    /// Write a top-level chunk in place of an existing chunk, moving the rest
+    /// of the file along if there isn't enough room, or add it after the last
+    /// chunk if there is no existing chunk.
+    fn set_chunk_in_place(
+        &mut self,
+        existing: Option<Chunk>,
+        id: &[u8; 4],
+        data: &[u8],
+    ) -> Result<()> {
+        let mut existing = match existing {
+            Some(existing) => existing,
+            None => return self.append_chunk(id, data),
+        };
+
+        let container = self.container;
+        let header_len = container.header_len();
+        let old_size = header_len + container.padded_len(existing.len);
+        let new_size = header_len + container.padded_len(data.len() as u64);
+
+        if old_size != new_size && old_size < new_size + header_len {
+            // Make room for the new chunk, or for the new chunk and a `JUNK`
+            // chunk if it has shrunk by less than the size of a header.
+            let distance = if new_size > old_size {
+                new_size - old_size
+            } else {
+                new_size + header_len - old_size
+            };
+
+            existing.offset += self.reserve_form_len(self.form_len + distance)?;
+            move_tail(&mut self.file, existing.offset + old_size, distance)?;
+            self.set_form_len(self.form_len + distance)?;
+            existing.len += distance;
+        }
+
+        self.replace_chunk(Some(existing), id, data)
+    }
+
+    /// Turn a chunk into a `JUNK` chunk, which is ignored by readers.
+    fn write_junk_id(&mut self, chunk: &Chunk) -> Result<()> {
+        self.file.seek(SeekFrom::Start(chunk.offset))?;
+
+        match self.container {
+            Container::Wave64 => self.file.write_all(&wave64_guid(b"JUNK"))?,
+            _ => self.file.write_all(b"JUNK")?,
+        }
+
+        Ok(())
+    }
+
+    /// Add a chunk after the last chunk in the RIFF chunk.
+    ///
+    /// This isn't always the end of the file. An odd-sized last chunk may be
+    /// missing its pad byte, in which case it is added, and there may be other
+    /// data after the RIFF chunk, which is moved along to follow the new chunk.
+    fn append_chunk(&mut self, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+        let (offset, trailer_offset) = match self.chunks()?.last() {
+            Some(last) if self.contents_offset(last) + last.len > file_len => {
+                return Err(Error::truncated_chunk(&last.id).at_offset(last.offset))
+            }
+            Some(last) => {
+                let contents_end = self.contents_offset(last) + last.len;
+                let padded_end = self.padded_end(last);
+                let mut padding = vec![0; (padded_end.min(file_len) - contents_end) as usize];
+                self.file.seek(SeekFrom::Start(contents_end))?;
+                self.file.read_exact(&mut padding)?;
+
+                // Padding is always zero, so anything else is the start of
+                // data written straight after the chunk without padding.
+                let trailer_offset = match padding.iter().position(|b| *b != 0) {
+                    Some(i) => contents_end + i as u64,
+                    None => padded_end,
+                };
+
+                (padded_end, trailer_offset)
+            }
+            None => (self.first_chunk_offset(), self.first_chunk_offset()),
+        };
+
+        let end =
+            offset + self.container.header_len() + self.container.padded_len(data.len() as u64);
+        let form_len = match self.container {
+            Container::Wave64 => end,
+            _ => end - 8,
+        };
+
+        // Nothing has been written yet, so if the chunks had to move to make
+        // room for RF64 sizes, start again from their new positions.
+        if self.reserve_form_len(form_len)? > 0 {
+            return self.append_chunk(id, data);
+        }
+
+        let mut trailer = Vec::new();
+
+        if trailer_offset < file_len {
+            self.file.seek(SeekFrom::Start(trailer_offset))?;
+            self.file.read_to_end(&mut trailer)?;
+        }
+
+        let start = trailer_offset.min(file_len);
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.write_all(&vec![0; (offset - start) as usize])?;
+        self.container.write_chunk_to(&mut self.file, id, data)?;
+        self.file.write_all(&trailer)?;
+
+        self.set_form_len(form_len)
+    }
+
+    /// Make sure the RIFF chunk can grow to the given size, switching to RF64
+    /// first if it would be too large for a RIFF file. This must be called
+    /// before anything is written, so that nothing is left half-written.
+    ///
+    /// Returns how far the chunks were moved to make room for a `ds64` chunk.
+    fn reserve_form_len(&mut self, form_len: u64) -> Result<u64> {
+        match self.container {
+            Container::Riff if form_len > u32::MAX as u64 => self.convert_to_rf64(),
+            _ => Ok(0),
+        }
+    }
+
+    /// Update the size of the RIFF chunk. A RIFF file must already have been
+    /// switched to RF64 with [`reserve_form_len`](Self::reserve_form_len) if
+    /// it has grown too large.
+    fn set_form_len(&mut self, form_len: u64) -> Result<()> {
+        match self.container {
+            Container::Riff => {
+                let riff_size: u32 = form_len
+                    .try_into()
+                    .map_err(|_| Error::TooLarge("RIFF file too large"))?;
+
+                self.file.seek(SeekFrom::Start(4))?;
+                self.file.write_all(&riff_size.to_le_bytes())?;
+            }
+            Container::Rf64 | Container::Bw64 => {
+                let ds64 = self.ds64.as_mut().expect("RF64 file without ds64 chunk");
+                ds64.riff_size = form_len;
+
+                self.file.seek(SeekFrom::Start(12 + 8))?;
+                self.file.write_all(&form_len.to_le_bytes())?;
+            }
+            Container::Wave64 => {
+                self.file.seek(SeekFrom::Start(16))?;
+                self.file.write_all(&form_len.to_le_bytes())?;
+            }
+        }
+
+        self.form_len = form_len;
+
+        Ok(())
+    }
+
+    /// Turn a RIFF file into an RF64 file.
+    ///
+    /// The `ds64` chunk takes the place of a `JUNK` chunk at the start of the
+    /// file, as recommended by the RF64 specification. If there isn't one,
+    /// the chunks are moved along to make room for it, and how far they moved
+    /// is returned.
+    fn convert_to_rf64(&mut self) -> Result<u64> {
+        let ds64_len = Ds64Chunk::LEN as u64;
+        let (offset, len, distance) = match self.chunks()?.first() {
+            Some(chunk) if &chunk.id == b"JUNK" && chunk.len >= ds64_len => {
+                (chunk.offset, chunk.len, 0)
+            }
+            _ => (self.first_chunk_offset(), ds64_len, 8 + ds64_len),
+        };
+
+        let frame_count = self.frame_count()?.unwrap_or_default();
+        let data_size = self
+            .find_chunk(b"data")?
+            .map(|chunk| chunk.len)
+            .unwrap_or_default();
+
+        if distance > 0 {
+            move_tail(&mut self.file, offset, distance)?;
+            self.form_len += distance;
+        }
+
+        let ds64 = Ds64Chunk {
+            riff_size: self.form_len,
+            data_size,
+            sample_count: frame_count,
+            table: Vec::new(),
+        };
+        let mut contents = ds64.to_bytes();
+        contents.resize(len as usize, 0);
+
+        self.file.seek(SeekFrom::Start(offset))?;
+        write_chunk_to(&mut self.file, Ds64Chunk::ID, &contents)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(b"RF64")?;
+        self.file.write_all(&u32::MAX.to_le_bytes())?;
+
+        self.container = Container::Rf64;
+        self.ds64 = Some(ds64);
+
+        Ok(distance)
+    }
+}
+
+impl<F: Read + Seek + Write + Truncate> Wav<F> {
+    /// Remove the first top-level chunk with the given ID, returning whether
+    /// there was one.
+    ///
+    /// Everything after the chunk is moved back to take its place, and the
+    /// file is made shorter.
+    pub fn remove_chunk(&mut self, id: &[u8; 4]) -> Result<bool> {
+        match self.find_chunk(id)? {
+            Some(chunk) => self.cut_chunk(&chunk).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Remove the first top-level `LIST` chunk with the given list type, in
+    /// the same way as [`remove_chunk`](Self::remove_chunk).
+    pub fn remove_list(&mut self, list_type: &[u8; 4]) -> Result<bool> {
+        match self.find_list(list_type)? {
+            Some(chunk) => self.cut_chunk(&chunk).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    fn cut_chunk(&mut self, chunk: &Chunk) -> Result<()> {
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+        let end = self.padded_end(chunk).min(file_len);
+        let distance = end - chunk.offset;
+
+        move_tail_back(&mut self.file, end, distance)?;
+        self.file.truncate(file_len - distance)?;
+        self.set_form_len(self.form_len.saturating_sub(distance))
+    }
+}
+
+/// A file that can be cut short, which is needed to remove chunks.
+pub trait Truncate {
+    /// Cut the file short at the given length.
+    fn truncate(&mut self, len: u64) -> io::Result<()>;
+}
+
+impl Truncate for File {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.set_len(len)
+    }
+}
+
+impl Truncate for Cursor<Vec<u8>> {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        self.get_mut().truncate(len as usize);
+        Ok(())
+    }
+}
+
+impl<T: Truncate + ?Sized> Truncate for &mut T {
+    fn truncate(&mut self, len: u64) -> io::Result<()> {
+        (**self).truncate(len)
+    }
+}
+
+/// Write a complete chunk including its header and a trailing pad byte if the
+/// data has an odd length.
+pub(crate) fn write_chunk_to(mut writer: impl Write, id: &[u8; 4], data: &[u8]) -> Result<()> {
+    let len: u32 = data
+        .len()
+        .try_into()
+        .map_err(|_| Error::TooLarge("chunk too large"))?;
+
+    writer.write_all(id)?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(data)?;
+
+    if data.len() % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+
+    Ok(())
+}
+
+/// Move everything from the given offset to the end of the file towards the
+/// end of the file, starting from the end so nothing is overwritten before it
+/// has been copied.
+pub(crate) fn move_tail(
+    mut file: impl Read + Write + Seek,
+    offset: u64,
+    distance: u64,
+) -> Result<()> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut pos = file.seek(SeekFrom::End(0))?;
+
+    while pos > offset {
+        let len = (pos - offset).min(buf.len() as u64) as usize;
+        pos -= len as u64;
+
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buf[..len])?;
+        file.seek(SeekFrom::Start(pos + distance))?;
+        file.write_all(&buf[..len])?;
+    }
+
+    Ok(())
+}
+
+/// Move everything from the given offset to the end of the file towards the
+/// start of the file, starting from the offset so nothing is overwritten
+/// before it has been copied.
+fn move_tail_back(mut file: impl Read + Write + Seek, offset: u64, distance: u64) -> Result<()> {
+    let mut buf = vec![0; 64 * 1024];
+    let end = file.seek(SeekFrom::End(0))?;
+    let mut pos = offset;
+
+    while pos < end {
+        let len = (end - pos).min(buf.len() as u64) as usize;
+
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buf[..len])?;
+        file.seek(SeekFrom::Start(pos - distance))?;
+        file.write_all(&buf[..len])?;
+
+        pos += len as u64;
+    }
+
+    Ok(())
+}
+
+/// Split a sequence of chunks, such as the contents of a `LIST` chunk, into
+/// the ID and contents of each chunk.
+///
+/// Parsing is lenient; a chunk claiming to be longer than the remaining bytes
+/// is cut short, and any incomplete chunk header at the end is ignored.
+fn read_sub_chunks(mut bytes: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut chunks = Vec::new();
+
+    while bytes.len() >= 8 {
+        let id = bytes[..4].try_into().unwrap();
+        let len = (read_u32(bytes, 4) as usize).min(bytes.len() - 8);
+
+        chunks.push((id, &bytes[8..8 + len]));
+        bytes = &bytes[(8 + padded_len(len as u64) as usize).min(bytes.len())..];
+    }
+
+    chunks
+}
+
+/// Chunks are aligned to 2-byte boundaries.
+pub(crate) fn padded_len(len: u64) -> u64 {
+    len + len % 2
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi;
+    use std::io::Cursor;
+
+    fn wav_with_chunks(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut body = b"WAVE".to_vec();
+
+        for (id, data) in chunks {
+            write_chunk_to(&mut body, id, data).unwrap();
+        }
+
+        let mut file = Vec::new();
+        write_chunk_to(&mut file, b"RIFF", &body).unwrap();
+        file
+    }
+
+    fn sampler_chunk_bytes() -> Vec<u8> {
+        let mut chunk = SamplerChunk::default();
+        chunk.loops_mut().push(SampleLoop::new(100, 200));
+        chunk.loops_mut().push(SampleLoop::new(300, 400));
+        chunk.set_sampler_data(vec![9, 8, 7]);
+        chunk.to_bytes()
+    }
+
+    #[test]
+    fn new_rejects_other_files() {
+        for file in [&b"hi\n"[..], b"RIFF\x04\0\0\0AVI ", b"riff"] {
+            assert!(matches!(
+                Wav::new(Cursor::new(file)),
+                Err(Error::NotRecognized { format: "WAV" })
+            ));
+        }
+    }
+
+    #[test]
+    fn update_sampler_chunk_appends_new_chunk() {
+        let file = wav_with_chunks(&[(b"data", &[1, 2, 3, 4])]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        wav.update_sampler_chunk(|chunk| {
+            chunk.set_midi_unity_note(midi::Note::from(48));
+            chunk.loops_mut().push(SampleLoop::new(0, 3));
+        })
+        .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(read_u32(&file, 4) as usize, file.len() - 8);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        let chunk = wav.get_sampler_chunk().unwrap().unwrap();
+        assert_eq!(chunk.midi_unity_note(), midi::Note::from(48));
+        assert_eq!(chunk.loops(), &[SampleLoop::new(0, 3)]);
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn update_sampler_chunk_after_unpadded_chunk_and_trailing_data() {
+        // The data chunk is missing its pad byte, and the RIFF size doesn't
+        // count it either.
+        let mut file = wav_with_chunks(&[(b"data", &[1, 2, 3])]);
+        file.pop();
+        file[4..8].copy_from_slice(&15u32.to_le_bytes());
+        file.extend_from_slice(b"ID3\x03tag");
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        wav.update_sampler_chunk(|chunk| chunk.set_midi_unity_note(midi::Note::from(48)))
+            .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(&file[12..24], b"data\x03\0\0\0\x01\x02\x03\0");
+        assert_eq!(&file[file.len() - 7..], b"ID3\x03tag");
+        assert_eq!(read_u32(&file, 4) as usize, file.len() - 8 - 7);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(
+            wav.check().unwrap()[..],
+            [Problem::TrailingData { offset: 68, len: 7 }]
+        );
+        assert_eq!(wav.chunk_ids().unwrap(), vec![*b"data", *b"smpl"]);
+    }
+
+    #[test]
+    fn layout_lists_sub_chunks() {
+        let mut info = Vec::new();
+        write_chunk_to(&mut info, b"INAM", b"Kick\0").unwrap();
+        write_chunk_to(&mut info, b"IART", b"Me\0").unwrap();
+        let file = wav_with_chunks(&[
+            (b"fmt ", &pcm_format_bytes()),
+            (b"LIST", &[&b"INFO"[..], &info].concat()),
+            (b"data", &[1, 2, 3, 4]),
+        ]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        let info = |id: &[u8; 4], list_type, parent, offset, len| ChunkInfo {
+            id: *id,
+            list_type,
+            parent,
+            offset,
+            len,
+            truncated: false,
+        };
+
+        assert_eq!(
+            wav.layout().unwrap(),
+            vec![
+                info(b"fmt ", None, None, 12, 16),
+                info(b"LIST", Some(*b"INFO"), None, 36, 30),
+                info(b"INAM", None, Some(*b"INFO"), 48, 5),
+                info(b"IART", None, Some(*b"INFO"), 62, 3),
+                info(b"data", None, None, 74, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn layout_of_truncated_list() {
+        let mut file = wav_with_chunks(&[
+            (b"fmt ", &pcm_format_bytes()),
+            (b"LIST", b"INFOINAM\x05\0\0\0Hello\0IART\x03\0\0\0Me\0\0"),
+        ]);
+        file.truncate(file.len() - 3);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        let layout = wav.layout().unwrap();
+        assert_eq!(layout.len(), 4);
+        assert_eq!((layout[1].list_type, layout[1].len), (Some(*b"INFO"), 30));
+        assert!(layout[1].truncated);
+        assert!(!layout[2].truncated);
+        assert_eq!((layout[3].id, layout[3].len), (*b"IART", 3));
+        assert!(layout[3].truncated);
+    }
+
+    #[test]
+    fn set_chunk_keeps_chunk_order() {
+        let file = wav_with_chunks(&[(b"abcd", &[1; 4]), (b"efgh", &[2; 4]), (b"data", &[3; 4])]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        wav.set_chunk(b"abcd", &[4; 11]).unwrap();
+        wav.set_chunk(b"efgh", &[5; 2]).unwrap();
+        wav.set_chunk(b"ijkl", &[6; 2]).unwrap();
+        wav.insert_chunk(b"abcd", &[7; 2]).unwrap();
+        assert!(wav.remove_chunk(b"efgh").unwrap());
+        assert!(!wav.remove_chunk(b"mnop").unwrap());
+
+        let file = wav.file.into_inner();
+        assert_eq!(read_u32(&file, 4) as usize, file.len() - 8);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(
+            wav.chunk_ids().unwrap(),
+            vec![*b"abcd", *b"JUNK", *b"data", *b"ijkl", *b"abcd"]
+        );
+        assert_eq!(wav.read_chunk(b"abcd").unwrap().unwrap(), vec![4; 11]);
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), vec![3; 4]);
+        assert!(wav.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn remove_chunk_shortens_file() {
+        let file = wav_with_chunks(&[
+            (b"fmt ", &[1; 16]),
+            (b"LIST", b"INFOINAM\x03\0\0\0Pad\0"),
+            (b"data", &[2; 5]),
+        ]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        assert!(wav.remove_list(b"INFO").unwrap());
+        assert!(!wav.remove_list(b"adtl").unwrap());
+        assert!(wav.junk_chunk(b"fmt ").unwrap());
+
+        let file = wav.file.into_inner();
+        assert_eq!(file.len(), 12 + 24 + 14);
+        assert_eq!(read_u32(&file, 4) as usize, file.len() - 8);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.chunk_ids().unwrap(), vec![*b"JUNK", *b"data"]);
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), vec![2; 5]);
+    }
+
+    #[test]
+    fn update_sampler_chunk_grows_existing_chunk() {
+        let file = wav_with_chunks(&[(b"smpl", &sampler_chunk_bytes()), (b"data", &[1, 2, 3, 4])]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        wav.update_sampler_chunk(|chunk| {
+            chunk.loops_mut().push(SampleLoop::new(500, 600));
+        })
+        .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(read_u32(&file, 4) as usize, file.len() - 8);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        let chunk = wav.get_sampler_chunk().unwrap().unwrap();
+        assert_eq!(chunk.loops().len(), 3);
+        assert_eq!(chunk.sampler_data(), &[9, 8, 7]);
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn update_sampler_chunk_shrinks_existing_chunk() {
+        let file = wav_with_chunks(&[(b"smpl", &sampler_chunk_bytes()), (b"data", &[1, 2, 3, 4])]);
+        let len = file.len();
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        wav.update_sampler_chunk(|chunk| {
+            chunk.loops_mut().clear();
+        })
+        .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(file.len(), len);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert!(wav.get_sampler_chunk().unwrap().unwrap().loops().is_empty());
+        assert!(wav.find_chunk(b"JUNK").unwrap().is_some());
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn update_instrument_chunk_pads_odd_sized_chunk() {
+        let file = wav_with_chunks(&[(b"data", &[1, 2, 3, 4])]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        wav.update_instrument_chunk(|chunk| {
+            chunk.set_low_note(midi::Note::from(36));
+            chunk.set_high_note(midi::Note::from(47));
+        })
+        .unwrap();
+        wav.update_sampler_chunk(|chunk| {
+            chunk.set_midi_unity_note(midi::Note::from(40));
+        })
+        .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(read_u32(&file, 4) as usize, file.len() - 8);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        let chunk = wav.get_instrument_chunk().unwrap().unwrap();
+        assert_eq!(chunk.low_note(), midi::Note::from(36));
+        assert_eq!(chunk.high_note(), midi::Note::from(47));
+        assert_eq!(
+            wav.get_sampler_chunk().unwrap().unwrap().midi_unity_note(),
+            midi::Note::from(40)
+        );
+    }
+
+    #[test]
+    fn update_markers() {
+        let mut info = b"INFO".to_vec();
+        write_chunk_to(&mut info, b"INAM", b"Title\0").unwrap();
+        let file = wav_with_chunks(&[(b"LIST", &info), (b"data", &[0; 16])]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        wav.update_markers(|markers| {
+            markers.push(Marker {
+                id: 1,
+                position: 2,
+                label: Some("Verse".into()),
+            });
+            markers.push(Marker {
+                id: 2,
+                position: 6,
+                label: None,
+            });
+        })
+        .unwrap();
+        wav.update_associated_data_list(|list| list.set_note(2, Some("Loud")))
+            .unwrap();
+
+        assert_eq!(
+            wav.get_markers().unwrap(),
+            vec![
+                Marker {
+                    id: 1,
+                    position: 2,
+                    label: Some("Verse".into()),
+                },
+                Marker {
+                    id: 2,
+                    position: 6,
+                    label: None,
+                },
+            ]
+        );
+
+        wav.update_markers(|markers| {
+            markers.remove(0);
+            markers[0].position = 4;
+            markers[0].label = Some("Chorus".into());
+        })
+        .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(read_u32(&file, 4) as usize, file.len() - 8);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        let adtl = wav.get_associated_data_list().unwrap().unwrap();
+        assert_eq!(adtl.label(1), None);
+        assert_eq!(adtl.label(2), Some("Chorus"));
+        assert_eq!(adtl.note(2), Some("Loud"));
+        assert_eq!(
+            wav.get_cue_chunk().unwrap().unwrap().points(),
+            &[CuePoint::new(2, 4)]
+        );
+        assert_eq!(
+            wav.get_list_info()
+                .unwrap()
+                .unwrap()
+                .get(b"INAM")
+                .as_deref(),
+            Some("Title")
+        );
+    }
+
+    #[test]
+    fn update_broadcast_extension_chunk_keeps_other_chunks() {
+        let file = wav_with_chunks(&[
+            (b"fmt ", &[1; 16]),
+            (b"bext", &BroadcastExtensionChunk::default().to_bytes()),
+            (b"data", &[1, 2, 3, 4]),
+        ]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        wav.update_broadcast_extension_chunk(|chunk| {
+            chunk.set_description("Rain on a tin roof");
+            chunk.set_coding_history("A=PCM,F=48000,W=24,M=mono,T=original\r\n");
+        })
+        .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(read_u32(&file, 4) as usize, file.len() - 8);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        let chunk = wav.get_broadcast_extension_chunk().unwrap().unwrap();
+        assert_eq!(chunk.description(), "Rain on a tin roof");
+        assert_eq!(wav.read_chunk(b"fmt ").unwrap().unwrap(), &[1; 16]);
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn frame_count() {
+        let mut format = Vec::new();
+        format.extend_from_slice(&1u16.to_le_bytes());
+        format.extend_from_slice(&2u16.to_le_bytes());
+        format.extend_from_slice(&8000u32.to_le_bytes());
+        format.extend_from_slice(&32000u32.to_le_bytes());
+        format.extend_from_slice(&4u16.to_le_bytes());
+        format.extend_from_slice(&16u16.to_le_bytes());
+
+        let file = wav_with_chunks(&[(b"fmt ", &format), (b"data", &[0; 40])]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.get_format_chunk().unwrap().unwrap().sample_rate(), 8000);
+        assert_eq!(wav.frame_count().unwrap(), Some(10));
+
+        let file = wav_with_chunks(&[(b"data", &[0; 40])]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.frame_count().unwrap(), None);
+    }
+
+    #[test]
+    fn read_frames() {
+        let mut format = Vec::new();
+        format.extend_from_slice(&1u16.to_le_bytes());
+        format.extend_from_slice(&2u16.to_le_bytes());
+        format.extend_from_slice(&8000u32.to_le_bytes());
+        format.extend_from_slice(&32000u32.to_le_bytes());
+        format.extend_from_slice(&4u16.to_le_bytes());
+        format.extend_from_slice(&16u16.to_le_bytes());
+
+        let file = wav_with_chunks(&[
+            (b"fmt ", &format),
+            (
+                b"data",
+                &[0x00, 0x40, 0x00, 0xc0, 0xff, 0x7f, 0x00, 0x80, 0x01],
+            ),
+            (b"LIST", b"INFO"),
+        ]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        let mut frames = wav.frames().unwrap();
+
+        assert_eq!(frames.channels(), 2);
+        assert_eq!(frames.frames_remaining(), 2);
+
+        let mut samples = [0; 8];
+        assert_eq!(frames.read_i32(&mut samples).unwrap(), 2);
+        assert_eq!(&samples[..4], &[16384, -16384, 32767, -32768]);
+        assert_eq!(frames.read_i32(&mut samples).unwrap(), 0);
+    }
+
+    fn pcm_format_bytes() -> Vec<u8> {
+        FormatChunk::new(SampleFormat::Pcm, 1, 8000, 16).unwrap().to_bytes()
+    }
+
+    #[test]
+    fn read_and_update_rf64() {
+        let ds64 = Ds64Chunk {
+            riff_size: 0,
+            data_size: 4,
+            sample_count: 2,
+            table: vec![(*b"smpl", 0)],
+        };
+        let mut file = b"RF64\xff\xff\xff\xffWAVE".to_vec();
+        write_chunk_to(&mut file, Ds64Chunk::ID, &ds64.to_bytes()).unwrap();
+        write_chunk_to(&mut file, b"fmt ", &pcm_format_bytes()).unwrap();
+        file.extend_from_slice(b"data\xff\xff\xff\xff\x01\x00\x02\x00");
+        let riff_size = file.len() as u64 - 8;
+        file[20..28].copy_from_slice(&riff_size.to_le_bytes());
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.container(), Container::Rf64);
+        assert_eq!(wav.frame_count().unwrap(), Some(2));
+
+        wav.update_sampler_chunk(|chunk| chunk.loops_mut().push(SampleLoop::new(0, 1)))
+            .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(read_u32(&file, 4), u32::MAX);
+        assert_eq!(read_u64(&file, 20) as usize, file.len() - 8);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.get_sampler_chunk().unwrap().unwrap().loops().len(), 1);
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), &[1, 0, 2, 0]);
+    }
+
+    #[test]
+    fn read_and_update_wave64() {
+        let mut file = WAVE64_RIFF.to_vec();
+        file.extend_from_slice(&0u64.to_le_bytes());
+        file.extend_from_slice(&WAVE64_WAVE);
+        Container::Wave64
+            .write_chunk_to(&mut file, b"fmt ", &pcm_format_bytes())
+            .unwrap();
+        Container::Wave64
+            .write_chunk_to(&mut file, b"data", &[1, 0, 2, 0, 3, 0])
+            .unwrap();
+        let len = file.len() as u64;
+        file[16..24].copy_from_slice(&len.to_le_bytes());
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.container(), Container::Wave64);
+        assert_eq!(wav.chunk_ids().unwrap(), vec![*b"fmt ", *b"data"]);
+        assert_eq!(wav.frame_count().unwrap(), Some(3));
+
+        wav.update_list_info(|info| info.set(b"INAM", "Bell"))
+            .unwrap();
+
+        let file = wav.file.into_inner();
+        assert_eq!(read_u64(&file, 16) as usize, file.len());
+        assert_eq!(file.len() % 8, 0);
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(
+            wav.get_list_info()
+                .unwrap()
+                .unwrap()
+                .get(b"INAM")
+                .as_deref(),
+            Some("Bell")
+        );
+
+        let mut samples = [0; 3];
+        wav.frames().unwrap().read_i32(&mut samples).unwrap();
+        assert_eq!(samples, [1, 2, 3]);
+    }
+
+    #[test]
+    fn convert_large_riff_to_rf64() {
+        let file = wav_with_chunks(&[(b"data", &[1, 2, 3, 4])]);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert!(wav.set_form_len(u32::MAX as u64 + 1).is_err());
+
+        let file = wav_with_chunks(&[
+            (b"JUNK", &[0; 28]),
+            (b"fmt ", &pcm_format_bytes()),
+            (b"data", &[1, 2, 3, 4]),
+        ]);
+        let len = file.len() as u64 - 8;
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.reserve_form_len(u32::MAX as u64 + 1).unwrap(), 0);
+
+        let mut wav = Wav::new(Cursor::new(wav.file.into_inner())).unwrap();
+        assert_eq!(wav.container(), Container::Rf64);
+        assert_eq!(wav.ds64.as_ref().unwrap().riff_size, len);
+        assert_eq!(wav.ds64.as_ref().unwrap().sample_count, 2);
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn convert_large_riff_without_junk_to_rf64() {
+        let file = wav_with_chunks(&[(b"fmt ", &pcm_format_bytes()), (b"data", &[1, 2, 3, 4])]);
+        let len = file.len() as u64 - 8;
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.reserve_form_len(u32::MAX as u64 + 1).unwrap(), 36);
+        assert_eq!(wav.reserve_form_len(u32::MAX as u64 + 1).unwrap(), 0);
+
+        let mut wav = Wav::new(Cursor::new(wav.file.into_inner())).unwrap();
+        assert_eq!(wav.container(), Container::Rf64);
+        assert_eq!(wav.ds64.as_ref().unwrap().riff_size, len + 36);
+        assert_eq!(wav.chunk_ids().unwrap(), vec![*b"ds64", *b"fmt ", *b"data"]);
+        assert_eq!(wav.read_chunk(b"data").unwrap().unwrap(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn read_truncated_chunk() {
+        let mut file = wav_with_chunks(&[(b"fmt ", &pcm_format_bytes()), (b"data", &[1, 2, 3, 4])]);
+        file.truncate(file.len() - 20);
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+
+        assert!(matches!(
+            wav.get_format_chunk(),
+            Err(Error::TruncatedChunk { id, .. }) if &id == b"fmt "
+        ));
+    }
+}
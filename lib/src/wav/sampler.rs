@@ -0,0 +1,410 @@
+use super::read_u32;
+use crate::{
+    error::{Error, Result},
+    midi,
+};
+use std::{fmt, str::FromStr};
+
+/// Sampler (`smpl`) chunk, containing information used by samplers to play
+/// back a sample, such as its root note and loop points.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SamplerChunk {
+    manufacturer: u32,
+    product: u32,
+    sample_period: u32,
+    midi_unity_note: u32,
+    midi_pitch_fraction: u32,
+    smpte_format: u32,
+    smpte_offset: u32,
+    loops: Vec<SampleLoop>,
+    sampler_data: Vec<u8>,
+
+    /// Length of the sampler data given in the chunk, if it runs past the end
+    /// of the chunk. This is written back as-is so that the chunk round-trips
+    /// unchanged.
+    declared_sampler_data_len: Option<u32>,
+
+    /// Any bytes found after the declared end of the chunk contents. These
+    /// are kept so that the chunk can be written back out unchanged.
+    trailer: Vec<u8>,
+}
+
+impl SamplerChunk {
+    pub(crate) const ID: &[u8; 4] = b"smpl";
+    const HEADER_LEN: usize = 36;
+
+    /// Parse a sampler chunk from the chunk contents, not including the chunk
+    /// ID and size.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < Self::HEADER_LEN {
+            return Err(Error::invalid_chunk(Self::ID));
+        }
+
+        let loop_count = read_u32(bytes, 28) as usize;
+        let sampler_data_len = read_u32(bytes, 32);
+        let loops_end = loop_count
+            .checked_mul(SampleLoop::LEN)
+            .and_then(|len| len.checked_add(Self::HEADER_LEN))
+            .filter(|end| *end <= bytes.len())
+            .ok_or_else(|| Error::truncated_chunk(Self::ID))?;
+        // Some files claim more sampler data than the chunk holds, in which
+        // case the data runs to the end of the chunk.
+        let declared_sampler_data_len =
+            Some(sampler_data_len).filter(|len| *len as usize > bytes.len() - loops_end);
+        let sampler_data_end = match declared_sampler_data_len {
+            Some(_) => bytes.len(),
+            None => loops_end + sampler_data_len as usize,
+        };
+
+        Ok(Self {
+            manufacturer: read_u32(bytes, 0),
+            product: read_u32(bytes, 4),
+            sample_period: read_u32(bytes, 8),
+            midi_unity_note: read_u32(bytes, 12),
+            midi_pitch_fraction: read_u32(bytes, 16),
+            smpte_format: read_u32(bytes, 20),
+            smpte_offset: read_u32(bytes, 24),
+            loops: bytes[Self::HEADER_LEN..loops_end]
+                .chunks_exact(SampleLoop::LEN)
+                .map(SampleLoop::from_bytes)
+                .collect(),
+            sampler_data: bytes[loops_end..sampler_data_end].to_vec(),
+            declared_sampler_data_len,
+            trailer: bytes[sampler_data_end..].to_vec(),
+        })
+    }
+
+    /// Serialize the chunk contents, not including the chunk ID and size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            Self::HEADER_LEN
+                + self.loops.len() * SampleLoop::LEN
+                + self.sampler_data.len()
+                + self.trailer.len(),
+        );
+
+        for value in &[
+            self.manufacturer,
+            self.product,
+            self.sample_period,
+            self.midi_unity_note,
+            self.midi_pitch_fraction,
+            self.smpte_format,
+            self.smpte_offset,
+            self.loops.len() as u32,
+            self.declared_sampler_data_len
+                .unwrap_or(self.sampler_data.len() as u32),
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for sample_loop in &self.loops {
+            sample_loop.write_to(&mut bytes);
+        }
+
+        bytes.extend_from_slice(&self.sampler_data);
+        bytes.extend_from_slice(&self.trailer);
+        bytes
+    }
+
+    /// MMA manufacturer code of the sampler this chunk is intended for, or 0
+    /// if there is no specific manufacturer.
+    pub fn manufacturer(&self) -> u32 {
+        self.manufacturer
+    }
+
+    pub fn set_manufacturer(&mut self, manufacturer: u32) {
+        self.manufacturer = manufacturer;
+    }
+
+    /// Manufacturer-specific product code, or 0 if there is no specific
+    /// product.
+    pub fn product(&self) -> u32 {
+        self.product
+    }
+
+    pub fn set_product(&mut self, product: u32) {
+        self.product = product;
+    }
+
+    /// Duration of a single sample frame in nanoseconds.
+    pub fn sample_period(&self) -> u32 {
+        self.sample_period
+    }
+
+    pub fn set_sample_period(&mut self, sample_period: u32) {
+        self.sample_period = sample_period;
+    }
+
+    pub fn midi_unity_note(&self) -> midi::Note {
+        (self.midi_unity_note as u8).into()
+    }
+
+    pub fn set_midi_unity_note(&mut self, note: midi::Note) {
+        self.midi_unity_note = u8::from(note).into();
+    }
+
+    /// Fraction of a semitone above the unity note that the sample is tuned
+    /// to, where `0x80000000` is half a semitone.
+    pub fn midi_pitch_fraction(&self) -> u32 {
+        self.midi_pitch_fraction
+    }
+
+    pub fn set_midi_pitch_fraction(&mut self, fraction: u32) {
+        self.midi_pitch_fraction = fraction;
+    }
+
+    /// SMPTE frame rate used by [`smpte_offset`](Self::smpte_offset). One of
+    /// 0, 24, 25, 29 or 30.
+    pub fn smpte_format(&self) -> u32 {
+        self.smpte_format
+    }
+
+    pub fn set_smpte_format(&mut self, format: u32) {
+        self.smpte_format = format;
+    }
+
+    /// SMPTE time offset at which the sample should start playing, packed as
+    /// `0xhhmmssff`.
+    pub fn smpte_offset(&self) -> u32 {
+        self.smpte_offset
+    }
+
+    pub fn set_smpte_offset(&mut self, offset: u32) {
+        self.smpte_offset = offset;
+    }
+
+    pub fn loops(&self) -> &[SampleLoop] {
+        &self.loops
+    }
+
+    pub fn loops_mut(&mut self) -> &mut Vec<SampleLoop> {
+        &mut self.loops
+    }
+
+    /// Sampler-specific data following the loop list.
+    pub fn sampler_data(&self) -> &[u8] {
+        &self.sampler_data
+    }
+
+    /// Length of the sampler data given in the chunk, if it is more than the
+    /// chunk actually holds.
+    pub fn declared_sampler_data_len(&self) -> Option<u32> {
+        self.declared_sampler_data_len
+    }
+
+    pub fn set_sampler_data(&mut self, data: impl Into<Vec<u8>>) {
+        self.sampler_data = data.into();
+        self.declared_sampler_data_len = None;
+    }
+}
+
+/// A single loop record inside a sampler chunk.
+///
+/// Start and end are both measured in sample frames, and the end is
+/// inclusive.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SampleLoop {
+    /// ID of a cue point associated with this loop, if any.
+    pub cue_point_id: u32,
+    pub loop_type: LoopType,
+    pub start: u32,
+    pub end: u32,
+    /// Fractional sample frame position of the loop end.
+    pub fraction: u32,
+    /// Number of times to play the loop, or 0 for infinite.
+    pub play_count: u32,
+}
+
+impl SampleLoop {
+    const LEN: usize = 24;
+
+    /// Create a new forward loop between two sample frames.
+    pub fn new(start: u32, end: u32) -> Self {
+        Self {
+            start,
+            end,
+            ..Default::default()
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            cue_point_id: read_u32(bytes, 0),
+            loop_type: read_u32(bytes, 4).into(),
+            start: read_u32(bytes, 8),
+            end: read_u32(bytes, 12),
+            fraction: read_u32(bytes, 16),
+            play_count: read_u32(bytes, 20),
+        }
+    }
+
+    fn write_to(&self, bytes: &mut Vec<u8>) {
+        for value in &[
+            self.cue_point_id,
+            self.loop_type.into(),
+            self.start,
+            self.end,
+            self.fraction,
+            self.play_count,
+        ] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// How a sample loop is played back.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LoopType {
+    /// Play from start to end and jump back to the start.
+    #[default]
+    Forward,
+    /// Play from start to end and then back again, like a ping-pong.
+    Alternating,
+    /// Play from end to start.
+    Backward,
+    /// A sampler-specific loop type.
+    Other(u32),
+}
+
+impl From<u32> for LoopType {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => LoopType::Forward,
+            1 => LoopType::Alternating,
+            2 => LoopType::Backward,
+            value => LoopType::Other(value),
+        }
+    }
+}
+
+impl From<LoopType> for u32 {
+    fn from(loop_type: LoopType) -> Self {
+        match loop_type {
+            LoopType::Forward => 0,
+            LoopType::Alternating => 1,
+            LoopType::Backward => 2,
+            LoopType::Other(value) => value,
+        }
+    }
+}
+
+impl FromStr for LoopType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "forward" => Ok(LoopType::Forward),
+            "alternating" | "pingpong" | "ping-pong" => Ok(LoopType::Alternating),
+            "backward" | "reverse" => Ok(LoopType::Backward),
+            // Other loop types are displayed as `type N`.
+            s => s
+                .strip_prefix("type ")
+                .unwrap_or(s)
+                .trim()
+                .parse::<u32>()
+                .map(LoopType::from)
+                .map_err(|_| "invalid loop type".into()),
+        }
+    }
+}
+
+impl fmt::Display for LoopType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopType::Forward => f.write_str("forward"),
+            LoopType::Alternating => f.write_str("alternating"),
+            LoopType::Backward => f.write_str("backward"),
+            LoopType::Other(value) => write!(f, "type {}", value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler_chunk_bytes() -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for value in &[1u32, 2, 22675, 60, 0x8000_0000, 25, 0x0102_0304, 2, 3] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        for value in &[7u32, 0, 100, 200, 0, 0, 8, 1, 300, 400, 5, 3] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&[9, 8, 7]);
+        bytes
+    }
+
+    #[test]
+    fn sampler_chunk_round_trip() {
+        let bytes = sampler_chunk_bytes();
+        let chunk = SamplerChunk::from_bytes(&bytes).unwrap();
+
+        assert_eq!(chunk.manufacturer(), 1);
+        assert_eq!(chunk.product(), 2);
+        assert_eq!(chunk.sample_period(), 22675);
+        assert_eq!(chunk.midi_unity_note(), midi::Note::from(60));
+        assert_eq!(chunk.midi_pitch_fraction(), 0x8000_0000);
+        assert_eq!(chunk.smpte_format(), 25);
+        assert_eq!(chunk.smpte_offset(), 0x0102_0304);
+        assert_eq!(
+            chunk.loops(),
+            &[
+                SampleLoop {
+                    cue_point_id: 7,
+                    ..SampleLoop::new(100, 200)
+                },
+                SampleLoop {
+                    cue_point_id: 8,
+                    loop_type: LoopType::Alternating,
+                    start: 300,
+                    end: 400,
+                    fraction: 5,
+                    play_count: 3,
+                },
+            ]
+        );
+        assert_eq!(chunk.sampler_data(), &[9, 8, 7]);
+        assert_eq!(chunk.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn sampler_chunk_rejects_truncated_loops() {
+        let bytes = sampler_chunk_bytes();
+
+        assert!(SamplerChunk::from_bytes(&bytes[..50]).is_err());
+    }
+
+    #[test]
+    fn sampler_chunk_with_short_sampler_data() {
+        let bytes = sampler_chunk_bytes();
+        let mut chunk = SamplerChunk::from_bytes(&bytes[..bytes.len() - 1]).unwrap();
+
+        assert_eq!(chunk.sampler_data(), &[9, 8]);
+        assert_eq!(chunk.declared_sampler_data_len(), Some(3));
+        assert_eq!(chunk.to_bytes(), &bytes[..bytes.len() - 1]);
+
+        chunk.set_sampler_data(chunk.sampler_data().to_vec());
+        assert_eq!(chunk.declared_sampler_data_len(), None);
+        assert_eq!(read_u32(&chunk.to_bytes(), 32), 2);
+    }
+
+    #[test]
+    fn loop_type_round_trip() {
+        for loop_type in &[
+            LoopType::Forward,
+            LoopType::Alternating,
+            LoopType::Backward,
+            LoopType::Other(32),
+        ] {
+            assert_eq!(loop_type.to_string().parse(), Ok(*loop_type));
+        }
+
+        assert_eq!("7".parse(), Ok(LoopType::Other(7)));
+        assert!("type".parse::<LoopType>().is_err());
+    }
+}
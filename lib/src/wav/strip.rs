@@ -0,0 +1,137 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use super::{ChunkInfo, FormatChunk, Wav};
+use crate::error::Result;
+
+/// IDs of chunks needed to play the audio, which are never stripped.
+const REQUIRED_CHUNKS: &[&[u8; 4]] = &[b"ds64", b"fmt ", b"data"];
+
+impl<F: Read + Seek> Wav<F> {
+    /// Write a copy of the file with only the top-level chunks that `keep`
+    /// returns `true` for, returning the chunks that were left out.
+    ///
+    /// The `ds64`, `fmt ` and `data` chunks are always kept. If
+    /// `plain_format` is set, an extensible format is replaced with the plain
+    /// format given by [`FormatChunk::to_plain`] where nothing would be lost.
+    pub fn write_stripped(
+        &mut self,
+        output: impl Write + Seek,
+        mut keep: impl FnMut(&ChunkInfo) -> bool,
+        plain_format: bool,
+    ) -> Result<Vec<ChunkInfo>> {
+        let mut plain = if plain_format {
+            self.get_format_chunk()?
+                .and_then(|format| format.to_plain())
+        } else {
+            None
+        };
+        let file_len = self.file.seek(SeekFrom::End(0))?;
+        let mut kept = Vec::new();
+        let mut removed = Vec::new();
+
+        for chunk in self.chunks()? {
+            let info = ChunkInfo {
+                id: chunk.id,
+                list_type: self.list_type(&chunk)?,
+                parent: None,
+                offset: chunk.offset,
+                len: chunk.len,
+                truncated: self.contents_offset(&chunk) + chunk.len > file_len,
+            };
+
+            if REQUIRED_CHUNKS.contains(&&chunk.id) {
+                let contents = match &chunk.id {
+                    id if id == FormatChunk::ID => plain.take().map(|format| format.to_bytes()),
+                    _ => None,
+                };
+
+                kept.push((chunk, contents));
+            } else if keep(&info) {
+                kept.push((chunk, None));
+            } else {
+                removed.push(info);
+            }
+        }
+
+        self.write_chunks(output, &kept)?;
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wav::{write_chunk_to, SampleFormat};
+    use std::io::Cursor;
+
+    fn wav_bytes(format: &FormatChunk) -> Vec<u8> {
+        let mut body = b"WAVE".to_vec();
+        write_chunk_to(&mut body, b"fmt ", &format.to_bytes()).unwrap();
+        write_chunk_to(&mut body, b"LIST", b"INFOINAM\x05\0\0\0Kick\0\0").unwrap();
+        write_chunk_to(&mut body, b"JUNK", &[0; 7]).unwrap();
+        write_chunk_to(&mut body, b"smpl", &[0; 36]).unwrap();
+        write_chunk_to(&mut body, b"data", &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        write_chunk_to(&mut body, b"id3 ", b"ID3").unwrap();
+
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend_from_slice(&body);
+        file
+    }
+
+    fn strip(
+        bytes: Vec<u8>,
+        keep: impl FnMut(&ChunkInfo) -> bool,
+        plain_format: bool,
+    ) -> (Vec<u8>, Vec<ChunkInfo>) {
+        let mut output = Cursor::new(Vec::new());
+        let removed = Wav::new(Cursor::new(bytes))
+            .unwrap()
+            .write_stripped(&mut output, keep, plain_format)
+            .unwrap();
+
+        (output.into_inner(), removed)
+    }
+
+    #[test]
+    fn strip_denied_chunks() {
+        let mut format = FormatChunk::new(SampleFormat::Pcm, 2, 44100, 16).unwrap();
+        format.set_channel_mask(0x3);
+
+        let (stripped, removed) = strip(
+            wav_bytes(&format),
+            |info| !matches!(&info.id, b"LIST" | b"JUNK" | b"id3 "),
+            true,
+        );
+
+        assert_eq!(
+            removed.iter().map(|info| info.id).collect::<Vec<_>>(),
+            vec![*b"LIST", *b"JUNK", *b"id3 "]
+        );
+        assert_eq!(removed[0].list_type, Some(*b"INFO"));
+
+        let mut wav = Wav::new(Cursor::new(stripped)).unwrap();
+        assert_eq!(wav.chunk_ids().unwrap(), vec![*b"fmt ", *b"smpl", *b"data"]);
+        assert_eq!(
+            wav.get_format_chunk().unwrap(),
+            Some(FormatChunk::new(SampleFormat::Pcm, 2, 44100, 16).unwrap())
+        );
+        assert_eq!(wav.frame_count().unwrap(), Some(2));
+        assert!(wav.check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn strip_keeps_required_chunks() {
+        let mut format = FormatChunk::new(SampleFormat::Pcm, 2, 44100, 16).unwrap();
+        format.set_channel_mask(0x33);
+
+        let (stripped, removed) = strip(wav_bytes(&format), |_| false, true);
+        assert_eq!(removed.len(), 4);
+
+        let mut wav = Wav::new(Cursor::new(stripped)).unwrap();
+        assert_eq!(wav.chunk_ids().unwrap(), vec![*b"fmt ", *b"data"]);
+        assert_eq!(wav.get_format_chunk().unwrap(), Some(format));
+        assert!(wav.check().unwrap().is_empty());
+    }
+}
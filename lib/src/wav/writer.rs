@@ -0,0 +1,363 @@
+use super::{
+    container::Ds64Chunk, write_chunk_to, AcidChunk, AssociatedDataList, BroadcastExtensionChunk,
+    CueChunk, FormatChunk, InstrumentChunk, ListInfo, SamplerChunk,
+};
+use crate::{
+    error::{Error, Result},
+    pcm::Encoding,
+};
+use std::io::{Seek, SeekFrom, Write};
+
+/// Writes a new WAV file from a format, a stream of sample frames, and any
+/// number of metadata chunks.
+///
+/// Chunks written before the first frame are placed before the `data` chunk,
+/// and chunks written after any frames are placed after it. Once a chunk has
+/// been written after the audio data no more frames can be written.
+///
+/// The RIFF and `data` chunk sizes are only filled in by
+/// [`finish`](Self::finish), so it must be called once everything has been
+/// written. If the file turns out to be larger than 4 GiB it is written as an
+/// RF64 file instead, using space reserved at the start of the file.
+pub struct WavWriter<W> {
+    writer: W,
+    format: FormatChunk,
+    encoding: Encoding,
+    state: State,
+    data_len: u64,
+    buffer: Vec<u8>,
+
+    /// Largest size that can be stored in a RIFF chunk header.
+    max_size: u64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum State {
+    Header,
+    /// Frames are being written to the `data` chunk whose contents begin at
+    /// the given offset.
+    Frames {
+        start: u64,
+        len: u64,
+    },
+    Trailer,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Start a new file, writing the RIFF header and the `fmt ` chunk.
+    ///
+    /// Returns an error if the format isn't one that frames can be encoded to.
+    pub fn new(mut writer: W, format: FormatChunk) -> Result<Self> {
+        let encoding = format.encoding()?;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        // Reserve space for a ds64 chunk in case the file needs to be RF64.
+        write_chunk_to(&mut writer, b"JUNK", &[0; Ds64Chunk::LEN])?;
+        write_chunk_to(&mut writer, FormatChunk::ID, &format.to_bytes())?;
+
+        Ok(Self {
+            writer,
+            format,
+            encoding,
+            state: State::Header,
+            data_len: 0,
+            buffer: Vec::new(),
+            max_size: u32::MAX as u64,
+        })
+    }
+
+    pub fn format(&self) -> &FormatChunk {
+        &self.format
+    }
+
+    /// Write interleaved normalized samples. Samples outside of `-1.0..=1.0`
+    /// are clipped when writing integer formats.
+    ///
+    /// The number of samples must be a multiple of the channel count.
+    pub fn write_f32(&mut self, samples: &[f32]) -> Result<()> {
+        self.check_frames(samples.len())?;
+        self.buffer.clear();
+
+        for sample in samples {
+            self.encoding.encode_f64(*sample as f64, &mut self.buffer);
+        }
+
+        self.write_buffer()
+    }
+
+    /// Like [`write_f32`](Self::write_f32), but with double precision so that
+    /// 64-bit float and 32-bit integer samples are written without any loss.
+    pub fn write_f64(&mut self, samples: &[f64]) -> Result<()> {
+        self.check_frames(samples.len())?;
+        self.buffer.clear();
+
+        for sample in samples {
+            self.encoding.encode_f64(*sample, &mut self.buffer);
+        }
+
+        self.write_buffer()
+    }
+
+    /// Write interleaved integer samples in the native range of the format,
+    /// such as `-32768..=32767` for 16-bit audio.
+    ///
+    /// Returns an error if the format isn't an integer format.
+    pub fn write_i32(&mut self, samples: &[i32]) -> Result<()> {
+        if !self.encoding.is_integer() {
+            return Err(Error::InvalidInput("samples are not integers".to_owned()));
+        }
+
+        self.check_frames(samples.len())?;
+        self.buffer.clear();
+
+        for sample in samples {
+            self.encoding.encode_int(*sample, &mut self.buffer);
+        }
+
+        self.write_buffer()
+    }
+
+    pub fn write_sampler_chunk(&mut self, chunk: &SamplerChunk) -> Result<()> {
+        self.write_chunk(SamplerChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn write_instrument_chunk(&mut self, chunk: &InstrumentChunk) -> Result<()> {
+        self.write_chunk(InstrumentChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn write_acid_chunk(&mut self, chunk: &AcidChunk) -> Result<()> {
+        self.write_chunk(AcidChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn write_cue_chunk(&mut self, chunk: &CueChunk) -> Result<()> {
+        self.write_chunk(CueChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn write_broadcast_extension_chunk(
+        &mut self,
+        chunk: &BroadcastExtensionChunk,
+    ) -> Result<()> {
+        self.write_chunk(BroadcastExtensionChunk::ID, &chunk.to_bytes())
+    }
+
+    pub fn write_associated_data_list(&mut self, list: &AssociatedDataList) -> Result<()> {
+        self.write_list(AssociatedDataList::LIST_TYPE, &list.to_bytes())
+    }
+
+    pub fn write_list_info(&mut self, info: &ListInfo) -> Result<()> {
+        self.write_list(ListInfo::LIST_TYPE, &info.to_bytes())
+    }
+
+    /// Write a `LIST` chunk with the given list type and contents.
+    pub fn write_list(&mut self, list_type: &[u8; 4], data: &[u8]) -> Result<()> {
+        let mut contents = list_type.to_vec();
+        contents.extend_from_slice(data);
+
+        self.write_chunk(b"LIST", &contents)
+    }
+
+    /// Write an arbitrary chunk. The `fmt ` and `data` chunks are managed by
+    /// the writer and can't be written this way.
+    pub fn write_chunk(&mut self, id: &[u8; 4], data: &[u8]) -> Result<()> {
+        if id == FormatChunk::ID || id == b"data" {
+            return Err(Error::InvalidInput(format!(
+                "cannot write {} chunk",
+                String::from_utf8_lossy(id)
+            )));
+        }
+
+        self.end_data()?;
+        write_chunk_to(&mut self.writer, id, data)
+    }
+
+    /// Fill in the chunk sizes and return the underlying writer. A file
+    /// without any frames still gets an empty `data` chunk.
+    pub fn finish(mut self) -> Result<W> {
+        if self.state == State::Header {
+            self.start_data()?;
+        }
+
+        self.end_data()?;
+
+        let end = self.writer.seek(SeekFrom::End(0))?;
+        let riff_size = end - 8;
+
+        self.writer.seek(SeekFrom::Start(0))?;
+
+        if riff_size > self.max_size || self.data_len > self.max_size {
+            let ds64 = Ds64Chunk {
+                riff_size,
+                data_size: self.data_len,
+                sample_count: self.data_len / self.format.block_align().max(1) as u64,
+                table: Vec::new(),
+            };
+
+            self.writer.write_all(b"RF64")?;
+            self.writer.write_all(&u32::MAX.to_le_bytes())?;
+            self.writer.write_all(b"WAVE")?;
+            write_chunk_to(&mut self.writer, Ds64Chunk::ID, &ds64.to_bytes())?;
+        } else {
+            self.writer.write_all(b"RIFF")?;
+            self.writer.write_all(&(riff_size as u32).to_le_bytes())?;
+        }
+
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+
+    fn check_frames(&mut self, samples: usize) -> Result<()> {
+        if !samples.is_multiple_of(self.format.channels() as usize) {
+            return Err(Error::InvalidInput(
+                "sample count is not a multiple of the channel count".to_owned(),
+            ));
+        }
+
+        match self.state {
+            State::Header => self.start_data(),
+            State::Frames { .. } => Ok(()),
+            State::Trailer => Err(Error::InvalidInput(
+                "cannot write frames after the data chunk".to_owned(),
+            )),
+        }
+    }
+
+    fn write_buffer(&mut self) -> Result<()> {
+        self.writer.write_all(&self.buffer)?;
+
+        if let State::Frames { len, .. } = &mut self.state {
+            *len += self.buffer.len() as u64;
+        }
+
+        Ok(())
+    }
+
+    fn start_data(&mut self) -> Result<()> {
+        self.writer.write_all(b"data")?;
+        self.writer.write_all(&0u32.to_le_bytes())?;
+
+        self.state = State::Frames {
+            start: self.writer.stream_position()?,
+            len: 0,
+        };
+
+        Ok(())
+    }
+
+    /// Close the `data` chunk if frames are being written, filling in its
+    /// size and padding it to an even length. The size of a `data` chunk too
+    /// large for RIFF is left for the `ds64` chunk.
+    fn end_data(&mut self) -> Result<()> {
+        if let State::Frames { start, len } = self.state {
+            let size = if len > self.max_size {
+                u32::MAX
+            } else {
+                len as u32
+            };
+
+            if len % 2 == 1 {
+                self.writer.write_all(&[0])?;
+            }
+
+            let end = self.writer.stream_position()?;
+            self.writer.seek(SeekFrom::Start(start - 4))?;
+            self.writer.write_all(&size.to_le_bytes())?;
+            self.writer.seek(SeekFrom::Start(end))?;
+
+            self.state = State::Trailer;
+            self.data_len = len;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wav::{Container, SampleFormat, SampleLoop, Wav};
+    use std::{convert::TryInto, io::Cursor};
+
+    #[test]
+    fn write_and_read_back() {
+        let format = FormatChunk::new(SampleFormat::Pcm, 1, 44100, 8).unwrap();
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), format).unwrap();
+        let mut info = ListInfo::default();
+        info.set(b"INAM", "Test");
+        let mut sampler = SamplerChunk::default();
+        sampler.loops_mut().push(SampleLoop::new(0, 2));
+
+        writer.write_list_info(&info).unwrap();
+        writer.write_f32(&[0.0, 0.5]).unwrap();
+        writer.write_i32(&[-128]).unwrap();
+        writer.write_sampler_chunk(&sampler).unwrap();
+        assert!(writer.write_f32(&[0.0]).is_err());
+
+        let file = writer.finish().unwrap().into_inner();
+        assert_eq!(
+            u32::from_le_bytes(file[4..8].try_into().unwrap()) as usize,
+            file.len() - 8
+        );
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.frame_count().unwrap(), Some(3));
+        assert_eq!(wav.get_list_info().unwrap(), Some(info));
+        assert_eq!(wav.get_sampler_chunk().unwrap(), Some(sampler));
+
+        let mut samples = [0; 4];
+        assert_eq!(wav.frames().unwrap().read_i32(&mut samples).unwrap(), 3);
+        assert_eq!(&samples[..3], &[0, 64, -128]);
+    }
+
+    #[test]
+    fn clip_out_of_range_samples() {
+        let format = FormatChunk::new(SampleFormat::Pcm, 2, 44100, 24).unwrap();
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), format).unwrap();
+        assert!(writer.write_f32(&[0.0]).is_err());
+
+        writer.write_f32(&[2.0, -2.0]).unwrap();
+        writer.write_i32(&[1, i32::MIN]).unwrap();
+
+        let mut wav = Wav::new(Cursor::new(writer.finish().unwrap().into_inner())).unwrap();
+        let mut samples = [0; 4];
+        wav.frames().unwrap().read_i32(&mut samples).unwrap();
+        assert_eq!(samples, [8388607, -8388608, 1, -8388608]);
+    }
+
+    #[test]
+    fn write_float() {
+        let format = FormatChunk::new(SampleFormat::IeeeFloat, 1, 48000, 32).unwrap();
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), format).unwrap();
+        writer.write_f32(&[0.25, -1.5]).unwrap();
+        assert!(writer.write_i32(&[1]).is_err());
+
+        let mut wav = Wav::new(Cursor::new(writer.finish().unwrap().into_inner())).unwrap();
+        assert_eq!(
+            wav.frames().unwrap().read_to_end_f32().unwrap(),
+            vec![0.25, -1.5]
+        );
+    }
+
+    #[test]
+    fn switch_to_rf64() {
+        let format = FormatChunk::new(SampleFormat::Pcm, 1, 44100, 16).unwrap();
+        let mut writer = WavWriter::new(Cursor::new(Vec::new()), format).unwrap();
+        writer.max_size = 6;
+        writer.write_i32(&[1, 2, 3, 4]).unwrap();
+
+        let file = writer.finish().unwrap().into_inner();
+        assert_eq!(&file[..4], b"RF64");
+        assert_eq!(&file[12..16], b"ds64");
+
+        let mut wav = Wav::new(Cursor::new(file)).unwrap();
+        assert_eq!(wav.container(), Container::Rf64);
+        assert_eq!(wav.frame_count().unwrap(), Some(4));
+
+        let mut samples = [0; 4];
+        wav.frames().unwrap().read_i32(&mut samples).unwrap();
+        assert_eq!(samples, [1, 2, 3, 4]);
+    }
+}
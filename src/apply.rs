@@ -0,0 +1,989 @@
+use anyhow::{anyhow, bail, Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+use walkdir::WalkDir;
+
+use crate::{
+    keyzones::{KeyRange, VelocityRange},
+    loops::Position,
+    output::{yes_no, LoopInfo, NoteInfo},
+    tags::TagKey,
+};
+use smplinfo::{
+    atomic_file::AtomicFile,
+    midi::Note,
+    sample_file::SampleFile,
+    tags::Tags,
+    wav::{AcidChunk, InstrumentChunk, ListInfo, LoopType, SampleLoop, SamplerChunk},
+};
+
+/// Apply metadata to many files from a CSV or JSON manifest
+///
+/// Each row of the manifest gives a file by its path, or a glob pattern such
+/// as `drums/*.wav`, along with the fields to set on the matching files. Paths
+/// are relative to the current directory. Empty fields are left unchanged,
+/// and where several rows match a file the later rows win.
+///
+/// The columns are the same as those of `--output csv`, so its output can be
+/// edited and applied back: root_note, key_range (or low_note and high_note),
+/// velocity_range (or low_velocity and high_velocity), loops, loop_start,
+/// loop_end, loop_type, loop_count, tags, tempo, beats, one_shot and
+/// acid_root_note. Any other column named after a tag, such as title or
+/// IART, sets that tag, and other FLAC Vorbis comment fields can be given in
+/// tags. Columns that can't be changed, such as frames, are ignored.
+///
+/// A JSON manifest is an array of objects with the same fields, or the output
+/// of `--output json`.
+///
+/// Nothing is changed unless every row matches at least one file.
+#[derive(Debug, StructOpt)]
+pub struct ApplyOptions {
+    /// Manifest to apply, read as JSON if it has a .json extension and as CSV
+    /// otherwise
+    manifest: PathBuf,
+}
+
+/// Columns written by `--output csv` or `--output json` that can't be applied
+/// to a file.
+const READ_ONLY_COLUMNS: &[&str] = &[
+    "filename",
+    "container",
+    "format",
+    "sample_format",
+    "channels",
+    "sample_rate",
+    "bits_per_sample",
+    "channel_mask",
+    "frames",
+    "duration",
+    "root_note_midi",
+    "markers",
+    "unshifted_note",
+    "fine_tune",
+    "gain",
+    "meter",
+    "stretch",
+    "disk_based",
+    "bwf",
+    "bwf_description",
+    "bwf_originator",
+    "bwf_time_reference",
+    "changes",
+    "error",
+];
+
+/// A row of a manifest.
+#[derive(Debug, PartialEq)]
+struct Row {
+    /// Row number, counting from 1, for error messages.
+    number: usize,
+    /// Path or glob pattern of the files the row applies to.
+    pattern: String,
+    /// Column names and values, not including the path.
+    values: Vec<(String, String)>,
+}
+
+/// Changes to make to a file, collected from the rows that match it.
+#[derive(Debug, Default)]
+struct Fields {
+    root_note: Option<Note>,
+    low_note: Option<Note>,
+    high_note: Option<Note>,
+    low_velocity: Option<u8>,
+    high_velocity: Option<u8>,
+    loops: Option<Vec<ManifestLoop>>,
+    loop_start: Option<Position>,
+    loop_end: Option<Position>,
+    loop_type: Option<LoopType>,
+    loop_count: Option<u32>,
+    /// Replaces all of the tags in the file.
+    tags: Option<Vec<(TagKey, String)>>,
+    /// Sets individual tags, after any in `tags`.
+    set_tags: Vec<(TagKey, String)>,
+    tempo: Option<f32>,
+    beats: Option<u32>,
+    one_shot: Option<bool>,
+    acid_root_note: Option<Note>,
+}
+
+/// A loop in the `loops` column, in the same form as `--output json`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestLoop {
+    start: u32,
+    end: u32,
+    #[serde(rename = "type", default)]
+    loop_type: Option<String>,
+    #[serde(default)]
+    play_count: u32,
+}
+
+impl Fields {
+    /// Set a field from a manifest column.
+    fn set(&mut self, column: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+
+        if value.is_empty() || READ_ONLY_COLUMNS.contains(&column) {
+            return Ok(());
+        }
+
+        match column {
+            "root_note" => self.root_note = Some(parse_note(value)?),
+            "key_range" => {
+                let range = value.parse::<KeyRange>().map_err(|e| anyhow!(e))?;
+                self.low_note = Some(range.low);
+                self.high_note = Some(range.high);
+            }
+            "low_note" => self.low_note = Some(parse_note(value)?),
+            "high_note" => self.high_note = Some(parse_note(value)?),
+            "velocity_range" => {
+                let range = value.parse::<VelocityRange>().map_err(|e| anyhow!(e))?;
+                self.low_velocity = Some(range.low);
+                self.high_velocity = Some(range.high);
+            }
+            "low_velocity" => self.low_velocity = Some(parse_velocity(value)?),
+            "high_velocity" => self.high_velocity = Some(parse_velocity(value)?),
+            "loops" => {
+                let loops = serde_json::from_str::<Vec<ManifestLoop>>(value)
+                    .with_context(|| format!("invalid loops: {}", value))?;
+
+                for sample_loop in &loops {
+                    if let Some(loop_type) = sample_loop.loop_type.as_ref() {
+                        loop_type.parse::<LoopType>().map_err(|e| anyhow!(e))?;
+                    }
+
+                    if sample_loop.end < sample_loop.start {
+                        bail!(
+                            "loop end {} is before loop start {}",
+                            sample_loop.end,
+                            sample_loop.start
+                        );
+                    }
+                }
+
+                self.loops = Some(loops);
+            }
+            "loop_start" => self.loop_start = Some(value.parse().map_err(|e: String| anyhow!(e))?),
+            "loop_end" => self.loop_end = Some(value.parse().map_err(|e: String| anyhow!(e))?),
+            "loop_type" => self.loop_type = Some(value.parse().map_err(|e: String| anyhow!(e))?),
+            "loop_count" => {
+                self.loop_count = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("invalid loop count: {}", value))?,
+                )
+            }
+            "tags" => {
+                let tags = serde_json::from_str::<BTreeMap<String, String>>(value)
+                    .with_context(|| format!("invalid tags: {}", value))?;
+
+                self.tags = Some(
+                    tags.into_iter()
+                        .map(|(key, value)| Ok((parse_tag(&key)?, value)))
+                        .collect::<Result<_>>()?,
+                );
+            }
+            "tempo" => self.tempo = Some(crate::parse_tempo(value).map_err(|e| anyhow!(e))?),
+            "beats" => {
+                self.beats = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("invalid beats: {}", value))?,
+                )
+            }
+            "one_shot" => {
+                self.one_shot = Some(match value.to_ascii_lowercase().as_str() {
+                    "yes" | "true" | "1" => true,
+                    "no" | "false" | "0" => false,
+                    _ => bail!("invalid one-shot, expected yes or no: {}", value),
+                })
+            }
+            "acid_root_note" => self.acid_root_note = Some(parse_note(value)?),
+            column => match ListInfo::tag_id(column) {
+                Some(id) => {
+                    let key = TagKey::Info(id);
+                    self.set_tags.retain(|(tag_key, _)| *tag_key != key);
+                    self.set_tags.push((key, value.to_owned()));
+                }
+                None => bail!("unknown column: {}", column),
+            },
+        }
+
+        Ok(())
+    }
+
+    fn edits_sampler(&self) -> bool {
+        self.root_note.is_some() || self.edits_loops()
+    }
+
+    fn edits_loops(&self) -> bool {
+        self.loops.is_some()
+            || self.loop_start.is_some()
+            || self.loop_end.is_some()
+            || self.loop_type.is_some()
+            || self.loop_count.is_some()
+    }
+
+    fn edits_instrument(&self) -> bool {
+        self.low_note.is_some()
+            || self.high_note.is_some()
+            || self.low_velocity.is_some()
+            || self.high_velocity.is_some()
+    }
+
+    fn edits_tags(&self) -> bool {
+        self.tags.is_some() || !self.set_tags.is_empty()
+    }
+
+    fn edits_acid(&self) -> bool {
+        self.tempo.is_some()
+            || self.beats.is_some()
+            || self.one_shot.is_some()
+            || self.acid_root_note.is_some()
+    }
+}
+
+fn parse_note(value: &str) -> Result<Note> {
+    match value.parse::<u8>() {
+        Ok(midi) if midi <= 127 => Ok(Note::from(midi)),
+        Ok(_) => bail!("invalid note: {}", value),
+        Err(_) => value
+            .parse()
+            .map_err(|_| anyhow!("invalid note: {}", value)),
+    }
+}
+
+fn parse_velocity(value: &str) -> Result<u8> {
+    match value.parse::<u8>() {
+        Ok(velocity) if velocity <= 127 => Ok(velocity),
+        _ => bail!("invalid velocity: {}", value),
+    }
+}
+
+fn parse_tag(key: &str) -> Result<TagKey> {
+    key.parse().map_err(|e: String| anyhow!(e))
+}
+
+pub fn apply_manifest(
+    options: &ApplyOptions,
+    dry_run: bool,
+    backup_suffix: Option<&str>,
+) -> Result<()> {
+    let manifest = &options.manifest;
+    let text =
+        fs::read_to_string(manifest).with_context(|| format!("failed to read {:?}", manifest))?;
+    let is_json = manifest
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let rows = if is_json {
+        read_json(&text)
+    } else {
+        read_csv(&text)
+    }
+    .with_context(|| format!("failed to read {:?}", manifest))?;
+
+    // Check every row before touching any files.
+    for row in &rows {
+        let mut fields = Fields::default();
+
+        for (column, value) in &row.values {
+            fields
+                .set(column, value)
+                .with_context(|| format!("row {}", row.number))?;
+        }
+    }
+
+    // Files are told apart by their canonical path, so that `a.wav` and
+    // `./a.wav` are the same file.
+    let mut files = Vec::<(PathBuf, PathBuf, Vec<&Row>)>::new();
+    let mut unmatched = Vec::new();
+
+    for row in &rows {
+        let paths = match_files(&row.pattern)?;
+
+        if paths.is_empty() {
+            unmatched.push(format!("row {}: {}", row.number, row.pattern));
+        }
+
+        for path in paths {
+            let canonical = fs::canonicalize(&path)?;
+
+            match files.iter_mut().find(|(file, _, _)| *file == canonical) {
+                Some((_, _, file_rows)) => file_rows.push(row),
+                None => files.push((canonical, path, vec![row])),
+            }
+        }
+    }
+
+    if !unmatched.is_empty() {
+        bail!(
+            "no files match {} manifest rows, so nothing was changed:\n  {}",
+            unmatched.len(),
+            unmatched.join("\n  ")
+        );
+    }
+
+    let mut failed = 0;
+
+    for (_, path, rows) in &files {
+        let mut fields = Fields::default();
+
+        for row in rows {
+            for (column, value) in &row.values {
+                fields.set(column, value)?;
+            }
+        }
+
+        if let Err(error) = apply_file(path, &fields, dry_run, backup_suffix) {
+            crate::handle_file_error(path, error, &mut failed)?;
+        }
+    }
+
+    if failed > 0 {
+        bail!("failed to apply to {} of {} files", failed, files.len());
+    }
+
+    Ok(())
+}
+
+/// Apply fields to a file, printing the changes as a diff.
+fn apply_file(
+    path: &Path,
+    fields: &Fields,
+    dry_run: bool,
+    backup_suffix: Option<&str>,
+) -> Result<()> {
+    let mut edit = if dry_run {
+        None
+    } else {
+        let edit = AtomicFile::edit(path)?;
+
+        Some(match backup_suffix {
+            Some(suffix) => edit.with_backup_suffix(suffix),
+            None => edit,
+        })
+    };
+    let mut original;
+    let file = match edit.as_mut() {
+        Some(edit) => edit.file(),
+        None => {
+            original = File::open(path)?;
+            &mut original
+        }
+    };
+
+    let mut sample = SampleFile::new(file)?;
+    let sample_rate = sample
+        .get_format_chunk()?
+        .map(|format| format.sample_rate());
+    let mut diff = Diff::default();
+
+    if fields.edits_sampler() {
+        let old = sample.get_sampler_chunk()?;
+        let mut new = old.clone().unwrap_or_default();
+
+        if let Some(note) = fields.root_note {
+            new.set_midi_unity_note(note);
+        }
+
+        let loops = new.loops_mut();
+
+        if let Some(manifest_loops) = fields.loops.as_ref() {
+            *loops = manifest_loops
+                .iter()
+                .map(|manifest_loop| SampleLoop {
+                    loop_type: manifest_loop
+                        .loop_type
+                        .as_ref()
+                        .and_then(|loop_type| loop_type.parse().ok())
+                        .unwrap_or_default(),
+                    play_count: manifest_loop.play_count,
+                    ..SampleLoop::new(manifest_loop.start, manifest_loop.end)
+                })
+                .collect();
+        }
+
+        let start = fields
+            .loop_start
+            .map(|p| p.to_frames(sample_rate))
+            .transpose()?;
+        let end = fields
+            .loop_end
+            .map(|p| p.to_frames(sample_rate))
+            .transpose()?;
+
+        if loops.is_empty() && (start.is_some() || end.is_some()) {
+            match (start, end) {
+                (Some(start), Some(end)) => loops.push(SampleLoop::new(start, end)),
+                _ => bail!("both loop_start and loop_end are needed to create a new loop"),
+            }
+        }
+
+        if let Some(first) = loops.first_mut() {
+            first.start = start.unwrap_or(first.start);
+            first.end = end.unwrap_or(first.end);
+            first.loop_type = fields.loop_type.unwrap_or(first.loop_type);
+            first.play_count = fields.loop_count.unwrap_or(first.play_count);
+
+            if first.end < first.start {
+                bail!(
+                    "loop end {} is before loop start {}",
+                    first.end,
+                    first.start
+                );
+            }
+        }
+
+        let describe = |chunk: Option<&SamplerChunk>| {
+            let mut lines = Vec::new();
+
+            if let Some(chunk) = chunk {
+                lines.push(format!(
+                    "Root note: {}",
+                    NoteInfo::from(chunk.midi_unity_note())
+                ));
+
+                for (i, sample_loop) in chunk.loops().iter().enumerate() {
+                    lines.push(format!("Loop {}: {}", i + 1, LoopInfo::from(sample_loop)));
+                }
+            }
+
+            lines
+        };
+
+        if diff.section(describe(old.as_ref()), describe(Some(&new))) && !dry_run {
+            sample.update_sampler_chunk(|chunk| *chunk = new)?;
+        }
+    }
+
+    if fields.edits_instrument() {
+        let old = sample.get_instrument_chunk()?;
+        let mut new = old.clone().unwrap_or_default();
+
+        if let Some(note) = fields.root_note {
+            new.set_unshifted_note(note);
+        }
+
+        if let Some(note) = fields.low_note {
+            new.set_low_note(note);
+        }
+
+        if let Some(note) = fields.high_note {
+            new.set_high_note(note);
+        }
+
+        if let Some(velocity) = fields.low_velocity {
+            new.set_low_velocity(velocity);
+        }
+
+        if let Some(velocity) = fields.high_velocity {
+            new.set_high_velocity(velocity);
+        }
+
+        if new.low_note() > new.high_note() {
+            bail!(
+                "invalid key range, {} is above {}",
+                new.low_note(),
+                new.high_note()
+            );
+        }
+
+        if new.low_velocity() > new.high_velocity() {
+            bail!(
+                "invalid velocity range, {} is above {}",
+                new.low_velocity(),
+                new.high_velocity()
+            );
+        }
+
+        let describe = |chunk: Option<&InstrumentChunk>| match chunk {
+            Some(chunk) => vec![
+                format!("Key range: {}-{}", chunk.low_note(), chunk.high_note()),
+                format!(
+                    "Velocity range: {}-{}",
+                    chunk.low_velocity(),
+                    chunk.high_velocity()
+                ),
+            ],
+            None => Vec::new(),
+        };
+
+        if diff.section(describe(old.as_ref()), describe(Some(&new))) && !dry_run {
+            sample.update_instrument_chunk(|chunk| *chunk = new)?;
+        }
+    }
+
+    if fields.edits_tags() {
+        let old = sample.get_tags()?;
+        let mut new = old.clone();
+
+        if let Some(tags) = fields.tags.as_ref() {
+            new = Tags::default();
+
+            for (key, value) in tags {
+                new.set(key.clone(), value);
+            }
+        }
+
+        for (key, value) in &fields.set_tags {
+            new.set(key.clone(), value);
+        }
+
+        let describe = |tags: &Tags| {
+            tags.iter()
+                .map(|(key, value)| format!("Tag {}: {}", key, value))
+                .collect()
+        };
+
+        if diff.section(describe(&old), describe(&new)) && !dry_run {
+            sample.update_tags(|tags| *tags = new)?;
+        }
+    }
+
+    if fields.edits_acid() {
+        let old = sample.get_acid_chunk()?;
+        let mut new = old.clone().unwrap_or_default();
+
+        if let Some(tempo) = fields.tempo {
+            new.set_tempo(tempo);
+        }
+
+        if let Some(beats) = fields.beats {
+            new.set_beats(beats);
+        }
+
+        if let Some(one_shot) = fields.one_shot {
+            new.set_one_shot(one_shot);
+        }
+
+        if let Some(note) = fields.acid_root_note {
+            new.set_root_note(Some(note));
+        }
+
+        let describe = |chunk: Option<&AcidChunk>| match chunk {
+            Some(chunk) => {
+                let mut lines = vec![
+                    format!("Tempo: {} BPM", chunk.tempo()),
+                    format!("Beats: {}", chunk.beats()),
+                    format!("One-shot: {}", yes_no(chunk.is_one_shot())),
+                ];
+
+                if let Some(note) = chunk.root_note() {
+                    lines.push(format!("ACID root note: {}", NoteInfo::from(note)));
+                }
+
+                lines
+            }
+            None => Vec::new(),
+        };
+
+        if diff.section(describe(old.as_ref()), describe(Some(&new))) && !dry_run {
+            sample.update_acid_chunk(|chunk| *chunk = new)?;
+        }
+    }
+
+    drop(sample);
+
+    if diff.lines.is_empty() {
+        log::info!("{:?} is already up to date", path);
+        return Ok(());
+    }
+
+    if let Some(edit) = edit {
+        edit.commit(crate::verify_sample)?;
+    }
+
+    println!("--- {}", path.display());
+    println!("+++ {}", path.display());
+
+    for line in &diff.lines {
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// Lines of a diff between the old and new metadata of a file.
+#[derive(Debug, Default)]
+struct Diff {
+    lines: Vec<String>,
+}
+
+impl Diff {
+    /// Add the lines describing a part of the file before and after the
+    /// change, returning whether they differ.
+    fn section(&mut self, old: Vec<String>, new: Vec<String>) -> bool {
+        if old == new {
+            return false;
+        }
+
+        for line in old.iter().filter(|line| !new.contains(line)) {
+            self.lines.push(format!("-{}", line));
+        }
+
+        for line in new.iter().filter(|line| !old.contains(line)) {
+            self.lines.push(format!("+{}", line));
+        }
+
+        true
+    }
+}
+
+/// Find the files matching a manifest path or glob pattern.
+fn match_files(pattern: &str) -> Result<Vec<PathBuf>> {
+    let pattern = pattern.replace('\\', "/");
+
+    if !pattern.contains(&['*', '?', '['][..]) {
+        let path = PathBuf::from(&pattern);
+
+        return Ok(if path.is_file() { vec![path] } else { vec![] });
+    }
+
+    // Walk the directory before the first component with a wildcard in it.
+    let wildcard = pattern.find(&['*', '?', '['][..]).unwrap();
+    let root = match pattern[..wildcard].rfind('/') {
+        Some(i) => &pattern[..i + 1],
+        None => "",
+    };
+    let regex = glob_regex(&pattern)?;
+    let mut files = Vec::new();
+
+    if !Path::new(root).is_dir() && !root.is_empty() {
+        return Ok(files);
+    }
+
+    for entry in WalkDir::new(if root.is_empty() { "." } else { root }).sort_by_file_name() {
+        let entry = entry?;
+        let path = entry.path().to_string_lossy().replace('\\', "/");
+        let path = match root {
+            "" => path.strip_prefix("./").unwrap_or(&path).to_owned(),
+            _ => path,
+        };
+
+        if entry.file_type().is_file() && regex.is_match(&path) {
+            files.push(PathBuf::from(path));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Convert a glob pattern to a regex. `*` and `?` don't match `/`, `**`
+/// matches any number of directories, and `[...]` matches a set of
+/// characters.
+fn glob_regex(pattern: &str) -> Result<Regex> {
+    static PART: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r"\*\*/|\*\*|\*|\?|\[!?\]?[^\]]*\]|[^*?\[]+|\[").unwrap());
+
+    let mut regex = String::from("^");
+
+    for part in PART.find_iter(pattern).map(|m| m.as_str()) {
+        match part {
+            "**/" => regex.push_str("(?:.*/)?"),
+            "**" => regex.push_str(".*"),
+            "*" => regex.push_str("[^/]*"),
+            "?" => regex.push_str("[^/]"),
+            "[" => bail!("invalid pattern, unclosed [: {}", pattern),
+            class if class.starts_with('[') => {
+                regex.push('[');
+
+                let class = &class[1..class.len() - 1];
+                let class = match class.strip_prefix('!') {
+                    Some(class) => {
+                        regex.push('^');
+                        class
+                    }
+                    None => class,
+                };
+
+                regex.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                regex.push(']');
+            }
+            literal => regex.push_str(&regex::escape(literal)),
+        }
+    }
+
+    regex.push('$');
+
+    Regex::new(&regex).with_context(|| format!("invalid pattern: {}", pattern))
+}
+
+/// Read the rows of a CSV manifest, which must have a header row naming its
+/// columns.
+fn read_csv(text: &str) -> Result<Vec<Row>> {
+    let mut records = parse_csv(text)?.into_iter();
+    let header = records.next().ok_or_else(|| anyhow!("manifest is empty"))?;
+    let path_column = header
+        .iter()
+        .position(|column| column == "path")
+        .ok_or_else(|| anyhow!("manifest has no path column"))?;
+    let mut rows = Vec::new();
+
+    for (i, record) in records.enumerate() {
+        // Rows are numbered as in a spreadsheet, where the header is row 1.
+        let number = i + 2;
+
+        if record.iter().all(|field| field.is_empty()) {
+            continue;
+        }
+
+        if record.len() > header.len() {
+            bail!("row {} has more fields than the header", number);
+        }
+
+        let pattern = record.get(path_column).cloned().unwrap_or_default();
+
+        if pattern.is_empty() {
+            bail!("row {} has no path", number);
+        }
+
+        rows.push(Row {
+            number,
+            pattern,
+            values: header
+                .iter()
+                .zip(record)
+                .enumerate()
+                .filter(|(i, _)| *i != path_column)
+                .map(|(_, (column, value))| (column.trim().to_owned(), value))
+                .collect(),
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Split CSV text into records of fields, following RFC 4180.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    let mut quoted = false;
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push(chars.next().unwrap());
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            '\n' if quoted => {
+                line += 1;
+                field.push(c);
+            }
+            c if quoted => field.push(c),
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+                line += 1;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        bail!("unclosed quote on line {}", line);
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Read the rows of a JSON manifest, which is an array of objects.
+fn read_json(text: &str) -> Result<Vec<Row>> {
+    let objects = serde_json::from_str::<Vec<serde_json::Map<String, Value>>>(text)
+        .context("expected an array of objects")?;
+    let mut rows = Vec::new();
+
+    for (i, object) in objects.into_iter().enumerate() {
+        let number = i + 1;
+        let mut values = Vec::new();
+
+        for (key, value) in object {
+            json_columns(key, value, &mut values);
+        }
+
+        let pattern = values
+            .iter()
+            .position(|(key, _)| key == "path")
+            .map(|i| values.remove(i).1);
+
+        rows.push(Row {
+            number,
+            pattern: pattern.ok_or_else(|| anyhow!("row {} has no path", number))?,
+            values,
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Turn a field of a JSON manifest into columns.
+///
+/// Records written by `--output json` group some fields into objects, which
+/// are split into a column per field, named as in `--output csv`. Notes
+/// written as an object with a name and a MIDI number use the number.
+fn json_columns(key: String, value: Value, columns: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Object(fields) if key == "instrument" || key == "acid" => {
+            for (field, value) in fields {
+                let field = match (key.as_str(), field.as_str()) {
+                    ("acid", "root_note") => "acid_root_note".to_owned(),
+                    _ => field,
+                };
+
+                json_columns(field, value, columns);
+            }
+        }
+        Value::Object(mut fields) if fields.contains_key("midi") => {
+            json_columns(key, fields.remove("midi").unwrap(), columns)
+        }
+        Value::String(value) => columns.push((key, value)),
+        value => columns.push((key, value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_csv_fields() {
+        assert_eq!(
+            parse_csv("path,title\r\na.wav,\"Kick, \"\"hard\"\"\"\nb.wav,\"two\nlines\"\n")
+                .unwrap(),
+            vec![
+                vec!["path", "title"],
+                vec!["a.wav", "Kick, \"hard\""],
+                vec!["b.wav", "two\nlines"],
+            ]
+        );
+        assert!(parse_csv("path\n\"a.wav\n").is_err());
+    }
+
+    #[test]
+    fn read_manifest_rows() {
+        let rows = read_csv("root_note,path,title\nC3,kick.wav,Kick\n\n,*.wav,\n").unwrap();
+
+        assert_eq!(
+            rows,
+            vec![
+                Row {
+                    number: 2,
+                    pattern: "kick.wav".to_owned(),
+                    values: vec![
+                        ("root_note".to_owned(), "C3".to_owned()),
+                        ("title".to_owned(), "Kick".to_owned()),
+                    ],
+                },
+                Row {
+                    number: 4,
+                    pattern: "*.wav".to_owned(),
+                    values: vec![
+                        ("root_note".to_owned(), "".to_owned()),
+                        ("title".to_owned(), "".to_owned()),
+                    ],
+                },
+            ]
+        );
+
+        let rows = read_json(r#"[{"path": "kick.wav", "root_note": 60, "tags": {"IART": "Me"}}]"#)
+            .unwrap();
+        assert_eq!(rows[0].pattern, "kick.wav");
+        assert_eq!(
+            rows[0].values,
+            vec![
+                ("root_note".to_owned(), "60".to_owned()),
+                ("tags".to_owned(), r#"{"IART":"Me"}"#.to_owned()),
+            ]
+        );
+        assert!(read_json(r#"[{"title": "Kick"}]"#).is_err());
+
+        let rows = read_json(
+            r#"[{"path": "a.wav", "format": {"channels": 1}, "root_note": {"name": "C3", "midi": 60},
+                 "instrument": {"gain": 0, "low_note": {"name": "C2", "midi": 48}},
+                 "acid": {"tempo": 120.0, "root_note": null}, "bwf": null, "changes": []}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            rows[0].values,
+            vec![
+                ("tempo".to_owned(), "120.0".to_owned()),
+                ("changes".to_owned(), "[]".to_owned()),
+                ("format".to_owned(), r#"{"channels":1}"#.to_owned()),
+                ("gain".to_owned(), "0".to_owned()),
+                ("low_note".to_owned(), "48".to_owned()),
+                ("root_note".to_owned(), "60".to_owned()),
+            ]
+        );
+
+        let mut fields = Fields::default();
+
+        for (column, value) in &rows[0].values {
+            fields.set(column, value).unwrap();
+        }
+
+        assert_eq!(fields.low_note, Some(Note::from(48)));
+    }
+
+    #[test]
+    fn set_fields() {
+        let mut fields = Fields::default();
+        fields.set("root_note", "60").unwrap();
+        fields.set("key_range", "C3-G3").unwrap();
+        fields.set("title", "Kick").unwrap();
+        fields.set("frames", "1234").unwrap();
+        fields
+            .set("loops", r#"[{"start": 0, "end": 10, "type": "forward"}]"#)
+            .unwrap();
+
+        assert_eq!(fields.root_note, Some(Note::from(60)));
+        assert_eq!(fields.high_note, Some(Note::from(67)));
+        assert_eq!(
+            fields.set_tags,
+            vec![(TagKey::Info(*b"INAM"), "Kick".to_owned())]
+        );
+        assert_eq!(fields.loops.unwrap()[0].end, 10);
+
+        assert!(Fields::default().set("root_note", "H3").is_err());
+        assert!(Fields::default()
+            .set("loops", r#"[{"start": 5, "end": 1}]"#)
+            .is_err());
+        assert!(Fields::default().set("bogus", "1").is_err());
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let regex = glob_regex("drums/**/*.wav").unwrap();
+        assert!(regex.is_match("drums/kick.wav"));
+        assert!(regex.is_match("drums/acoustic/kick.wav"));
+        assert!(!regex.is_match("keys/piano.wav"));
+
+        let regex = glob_regex("kick_[!0-4]?.wav").unwrap();
+        assert!(regex.is_match("kick_5a.wav"));
+        assert!(!regex.is_match("kick_1a.wav"));
+        assert!(!regex.is_match("kick_5/.wav"));
+
+        assert!(glob_regex("kick[.wav").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use structopt::StructOpt;
+
+use smplinfo::{atomic_file::AtomicFile, wav::Wav};
+
+/// Check WAV files for problems with their layout
+///
+/// Reports RIFF sizes that don't match the chunks in the file, odd-sized
+/// chunks missing their pad byte, duplicate chunks, chunks cut short by the
+/// end of the file, and unknown data after the last chunk.
+#[derive(Debug, StructOpt)]
+pub struct CheckOptions {
+    /// Rewrite files to fix any problems found
+    ///
+    /// Duplicate chunks and unknown data after the last chunk are removed, a
+    /// truncated data chunk is cut down to the last whole frame, and the
+    /// sampler data length in a smpl chunk is corrected.
+    #[structopt(long)]
+    repair: bool,
+
+    /// Files to check
+    #[structopt(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+pub fn check_files(
+    options: &CheckOptions,
+    dry_run: bool,
+    backup_suffix: Option<&str>,
+) -> Result<()> {
+    let mut failed = 0;
+
+    for path in &options.paths {
+        match check_file(options, path, dry_run, backup_suffix) {
+            Ok(true) => {}
+            Ok(false) => failed += 1,
+            Err(error) => {
+                crate::handle_file_error(path, error, &mut failed)?;
+            }
+        }
+    }
+
+    if failed > 0 {
+        bail!(
+            "found problems in {} of {} files",
+            failed,
+            options.paths.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Check a file, returning whether it is free of problems once any repairs
+/// have been made.
+fn check_file(
+    options: &CheckOptions,
+    path: &Path,
+    dry_run: bool,
+    backup_suffix: Option<&str>,
+) -> Result<bool> {
+    let file = File::open(path).with_context(|| format!("failed to open {:?}", path))?;
+    let mut wav = Wav::new(file).with_context(|| format!("failed to read {:?}", path))?;
+    let problems = wav.check()?;
+
+    if problems.is_empty() {
+        println!("{}: OK", path.to_string_lossy());
+        return Ok(true);
+    }
+
+    for problem in &problems {
+        println!("{}: {}", path.to_string_lossy(), problem);
+    }
+
+    if !options.repair {
+        return Ok(false);
+    }
+
+    if dry_run {
+        println!("Would repair {}", path.to_string_lossy());
+        return Ok(true);
+    }
+
+    let mut output =
+        AtomicFile::rewrite(path).with_context(|| format!("failed to create {:?}", path))?;
+
+    if let Some(suffix) = backup_suffix {
+        output = output.with_backup_suffix(suffix);
+    }
+
+    let mut writer = BufWriter::new(output.file());
+    wav.write_repaired(&mut writer)
+        .and_then(|_| Ok(writer.flush()?))
+        .with_context(|| format!("failed to repair {:?}", path))?;
+    drop(writer);
+
+    output
+        .commit(verify_repaired)
+        .with_context(|| format!("failed to write {:?}", path))?;
+
+    println!("Repaired {}", path.to_string_lossy());
+
+    Ok(true)
+}
+
+fn verify_repaired(file: &mut File) -> smplinfo::Result<()> {
+    crate::verify_sample(file)?;
+
+    match Wav::new(file)?.check()?.first() {
+        Some(problem) => {
+            Err(io::Error::new(io::ErrorKind::InvalidData, problem.to_string()).into())
+        }
+        None => Ok(()),
+    }
+}
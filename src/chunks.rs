@@ -0,0 +1,291 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use structopt::StructOpt;
+
+use smplinfo::{
+    atomic_file::AtomicFile,
+    wav::{ChunkInfo, Wav},
+};
+
+/// Show the chunks in a WAV file, or export, import or remove a chunk
+///
+/// With no other options, every chunk is listed along with its offset and
+/// size, including the chunks inside LIST chunks.
+///
+/// Chunks are given by their ID, such as smpl, or as LIST:TYPE for a LIST
+/// chunk with the given list type, such as LIST:INFO.
+#[derive(Debug, StructOpt)]
+pub struct ChunksOptions {
+    /// Write the contents of a chunk to the file given by --file
+    #[structopt(
+        long,
+        value_name = "CHUNK",
+        requires = "file",
+        conflicts_with_all = &["import", "remove"]
+    )]
+    export: Option<ChunkId>,
+
+    /// Replace the contents of a chunk with the file given by --file
+    ///
+    /// The chunk keeps its place in the file, or is added after the last chunk
+    /// if there isn't one.
+    #[structopt(
+        long,
+        value_name = "CHUNK",
+        requires = "file",
+        conflicts_with = "remove"
+    )]
+    import: Option<ChunkId>,
+
+    /// Add the imported chunk as a new chunk, even if there is already one
+    #[structopt(long, requires = "import")]
+    insert: bool,
+
+    /// Remove a chunk, moving everything after it back to take its place
+    #[structopt(long, value_name = "CHUNK")]
+    remove: Option<ChunkId>,
+
+    /// File to export a chunk to or import a chunk from, or - for standard
+    /// output or input
+    #[structopt(long)]
+    file: Option<PathBuf>,
+
+    /// WAV file to inspect or edit
+    path: PathBuf,
+}
+
+/// A chunk given on the command line, either by its ID or as `LIST:TYPE` for
+/// a `LIST` chunk with the given list type. IDs shorter than four characters
+/// are padded with spaces, so `fmt` is the same as `fmt `.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChunkId {
+    Chunk([u8; 4]),
+    List([u8; 4]),
+}
+
+impl FromStr for ChunkId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("LIST", list_type)) => parse_id(list_type).map(ChunkId::List),
+            Some(_) => Err(format!("invalid chunk, expected ID or LIST:TYPE: {}", s)),
+            None => parse_id(s).map(ChunkId::Chunk),
+        }
+    }
+}
+
+impl fmt::Display for ChunkId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChunkId::Chunk(id) => f.write_str(&String::from_utf8_lossy(id)),
+            ChunkId::List(list_type) => write!(f, "LIST:{}", String::from_utf8_lossy(list_type)),
+        }
+    }
+}
+
+impl ChunkId {
+    /// Whether the chunk is the one given, or any `LIST` chunk for `LIST`.
+    pub fn matches(&self, info: &ChunkInfo) -> bool {
+        match self {
+            ChunkId::Chunk(id) => info.id == *id,
+            ChunkId::List(list_type) => info.list_type == Some(*list_type),
+        }
+    }
+}
+
+fn parse_id(s: &str) -> Result<[u8; 4], String> {
+    if s.is_empty() || s.len() > 4 || !s.is_ascii() {
+        return Err(format!(
+            "invalid chunk ID, expected up to four ASCII characters: {}",
+            s
+        ));
+    }
+
+    let mut id = *b"    ";
+    id[..s.len()].copy_from_slice(s.as_bytes());
+
+    Ok(id)
+}
+
+pub fn inspect_chunks(
+    options: &ChunksOptions,
+    dry_run: bool,
+    backup_suffix: Option<&str>,
+) -> Result<()> {
+    let path = &options.path;
+
+    if options.import.is_some() || options.remove.is_some() {
+        return edit_chunks(options, dry_run, backup_suffix);
+    }
+
+    let file = File::open(path).with_context(|| format!("failed to open {:?}", path))?;
+    let mut wav = Wav::new(file).with_context(|| format!("failed to read {:?}", path))?;
+
+    if let Some(chunk) = options.export {
+        let data = read_chunk(&mut wav, chunk)?
+            .with_context(|| format!("{:?} has no {} chunk", path, chunk))?;
+        let output = options.file.as_deref().unwrap();
+
+        if output == Path::new("-") {
+            io::stdout().write_all(&data)?;
+        } else if dry_run {
+            println!(
+                "Would export {} chunk ({} bytes) to {}",
+                chunk,
+                data.len(),
+                output.to_string_lossy()
+            );
+        } else {
+            fs::write(output, &data).with_context(|| format!("failed to write {:?}", output))?;
+            println!(
+                "Exported {} chunk ({} bytes) to {}",
+                chunk,
+                data.len(),
+                output.to_string_lossy()
+            );
+        }
+
+        return Ok(());
+    }
+
+    println!("{:>10}  {:>10}  Chunk", "Offset", "Size");
+
+    for info in wav.layout()? {
+        println!(
+            "{:>10}  {:>10}  {}",
+            info.offset,
+            info.len,
+            describe_chunk(&info)
+        );
+    }
+
+    Ok(())
+}
+
+fn edit_chunks(options: &ChunksOptions, dry_run: bool, backup_suffix: Option<&str>) -> Result<()> {
+    let path = &options.path;
+    let data = match options.import {
+        Some(_) => Some(read_input(options.file.as_deref().unwrap())?),
+        None => None,
+    };
+
+    if dry_run {
+        let file = File::open(path).with_context(|| format!("failed to open {:?}", path))?;
+        let mut wav = Wav::new(file).with_context(|| format!("failed to read {:?}", path))?;
+
+        if let (Some(chunk), Some(data)) = (options.import, data.as_ref()) {
+            println!("Would import {} chunk ({} bytes)", chunk, data.len());
+        }
+
+        if let Some(chunk) = options.remove {
+            if read_chunk(&mut wav, chunk)?.is_none() {
+                bail!("{:?} has no {} chunk", path, chunk);
+            }
+
+            println!("Would remove {} chunk", chunk);
+        }
+
+        return Ok(());
+    }
+
+    let mut edit = AtomicFile::edit(path).with_context(|| format!("failed to open {:?}", path))?;
+
+    if let Some(suffix) = backup_suffix {
+        edit = edit.with_backup_suffix(suffix);
+    }
+
+    let mut wav = Wav::new(edit.file()).with_context(|| format!("failed to read {:?}", path))?;
+
+    if let (Some(chunk), Some(data)) = (options.import, data.as_ref()) {
+        match chunk {
+            ChunkId::Chunk(id) if options.insert => wav.insert_chunk(&id, data)?,
+            ChunkId::Chunk(id) => wav.set_chunk(&id, data)?,
+            ChunkId::List(list_type) if options.insert => {
+                wav.insert_chunk(b"LIST", &[&list_type[..], data].concat())?
+            }
+            ChunkId::List(list_type) => wav.set_list(&list_type, data)?,
+        }
+
+        println!("Imported {} chunk ({} bytes)", chunk, data.len());
+    }
+
+    if let Some(chunk) = options.remove {
+        let removed = match chunk {
+            ChunkId::Chunk(id) => wav.remove_chunk(&id)?,
+            ChunkId::List(list_type) => wav.remove_list(&list_type)?,
+        };
+
+        if !removed {
+            bail!("{:?} has no {} chunk", path, chunk);
+        }
+
+        println!("Removed {} chunk", chunk);
+    }
+
+    drop(wav);
+    edit.commit(crate::verify_sample)
+        .with_context(|| format!("failed to write {:?}", path))?;
+
+    Ok(())
+}
+
+fn read_chunk(
+    wav: &mut Wav<impl Read + Seek>,
+    chunk: ChunkId,
+) -> smplinfo::Result<Option<Vec<u8>>> {
+    match chunk {
+        ChunkId::Chunk(id) => wav.read_chunk(&id),
+        ChunkId::List(list_type) => wav.read_list(&list_type),
+    }
+}
+
+/// Read the contents of a file, or standard input if the path is `-`.
+fn read_input(path: &Path) -> Result<Vec<u8>> {
+    if path == Path::new("-") {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        return Ok(data);
+    }
+
+    fs::read(path).with_context(|| format!("failed to read {:?}", path))
+}
+
+pub fn describe_chunk(info: &ChunkInfo) -> String {
+    let indent = if info.parent.is_some() { "  " } else { "" };
+    let id = String::from_utf8_lossy(&info.id);
+    let truncated = if info.truncated { " (truncated)" } else { "" };
+
+    match info.list_type {
+        Some(list_type) => format!(
+            "{}{} {}{}",
+            indent,
+            id,
+            String::from_utf8_lossy(&list_type),
+            truncated
+        ),
+        None => format!("{}{}{}", indent, id, truncated),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_chunk_id() {
+        assert_eq!("smpl".parse(), Ok(ChunkId::Chunk(*b"smpl")));
+        assert_eq!("fmt".parse(), Ok(ChunkId::Chunk(*b"fmt ")));
+        assert_eq!("LIST:INFO".parse(), Ok(ChunkId::List(*b"INFO")));
+        assert!("".parse::<ChunkId>().is_err());
+        assert!("toolong".parse::<ChunkId>().is_err());
+        assert!("JUNK:INFO".parse::<ChunkId>().is_err());
+        assert_eq!(ChunkId::List(*b"adtl").to_string(), "LIST:adtl");
+    }
+}
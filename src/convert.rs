@@ -0,0 +1,244 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Cursor, Read, Seek, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use structopt::StructOpt;
+
+use smplinfo::{
+    aiff::{self, Aiff},
+    atomic_file::AtomicFile,
+    convert,
+    wav::Wav,
+};
+
+/// Convert samples between WAV and AIFF
+///
+/// Audio is copied without any loss. Loops, markers, the root note and tags
+/// are carried over, and anything that can't be represented in the new format
+/// is reported.
+#[derive(Debug, StructOpt)]
+pub struct ConvertOptions {
+    /// Format to convert to: wav or aiff
+    ///
+    /// Defaults to the format matching the extension of --output, or else the
+    /// opposite of the format of each input file.
+    #[structopt(long)]
+    to: Option<FileFormat>,
+
+    /// Path to write the converted file to, when converting a single file
+    #[structopt(short, long, conflicts_with = "output-dir")]
+    output: Option<PathBuf>,
+
+    /// Directory to write converted files to, instead of next to the input
+    #[structopt(long)]
+    output_dir: Option<PathBuf>,
+
+    /// Overwrite files that already exist
+    #[structopt(short, long)]
+    force: bool,
+
+    /// Files to convert
+    #[structopt(required = true)]
+    paths: Vec<PathBuf>,
+}
+
+/// A file format that samples can be converted to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    Wav,
+    Aiff,
+}
+
+impl FileFormat {
+    /// Work out the format of a file from its extension.
+    fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            FileFormat::Wav => "wav",
+            FileFormat::Aiff => "aif",
+        }
+    }
+}
+
+impl FromStr for FileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "wav" | "wave" => Ok(FileFormat::Wav),
+            "aif" | "aiff" | "aifc" => Ok(FileFormat::Aiff),
+            _ => Err(format!("unknown format, expected wav or aiff: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileFormat::Wav => f.write_str("WAV"),
+            FileFormat::Aiff => f.write_str("AIFF"),
+        }
+    }
+}
+
+pub fn convert_files(
+    options: &ConvertOptions,
+    dry_run: bool,
+    backup_suffix: Option<&str>,
+) -> Result<()> {
+    if options.output.is_some() && options.paths.len() > 1 {
+        bail!("--output can only be used when converting a single file");
+    }
+
+    let mut failed = 0;
+
+    for path in &options.paths {
+        if let Err(error) = convert_file(options, path, dry_run, backup_suffix) {
+            crate::handle_file_error(path, error, &mut failed)?;
+        }
+    }
+
+    if failed > 0 {
+        bail!(
+            "failed to convert {} of {} files",
+            failed,
+            options.paths.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn convert_file(
+    options: &ConvertOptions,
+    path: &Path,
+    dry_run: bool,
+    backup_suffix: Option<&str>,
+) -> Result<()> {
+    let mut input = File::open(path).with_context(|| format!("failed to open {:?}", path))?;
+    let mut header = [0; 12];
+    let header_len = input.read(&mut header)?;
+    input.rewind()?;
+
+    let from = if aiff::is_aiff(&header[..header_len]) {
+        FileFormat::Aiff
+    } else {
+        FileFormat::Wav
+    };
+
+    let to = options
+        .to
+        .or_else(|| options.output.as_deref().and_then(FileFormat::from_path))
+        .unwrap_or(match from {
+            FileFormat::Wav => FileFormat::Aiff,
+            FileFormat::Aiff => FileFormat::Wav,
+        });
+
+    if from == to {
+        log::warn!("{:?} is already a {} file, skipping", path, to);
+        return Ok(());
+    }
+
+    let output_path = match options.output.as_ref() {
+        Some(output) => output.clone(),
+        None => {
+            let file_name = Path::new(path.file_name().unwrap()).with_extension(to.extension());
+
+            match options.output_dir.as_ref() {
+                Some(dir) => dir.join(file_name),
+                None => path.with_file_name(file_name),
+            }
+        }
+    };
+
+    if output_path.exists() && !options.force {
+        bail!(
+            "{:?} already exists, use --force to overwrite it",
+            output_path
+        );
+    }
+
+    let warnings = if dry_run {
+        // Convert in memory to find out what would be lost.
+        convert_stream(input, from, Cursor::new(Vec::new()))?
+    } else {
+        let mut output = AtomicFile::create(&output_path)
+            .with_context(|| format!("failed to create {:?}", output_path))?;
+
+        if let Some(suffix) = backup_suffix {
+            output = output.with_backup_suffix(suffix);
+        }
+
+        let mut writer = BufWriter::new(output.file());
+        let warnings = convert_stream(input, from, &mut writer)
+            .and_then(|warnings| {
+                writer.flush()?;
+                Ok(warnings)
+            })
+            .with_context(|| format!("failed to convert {:?}", path))?;
+        drop(writer);
+
+        output
+            .commit(|file| verify_output(file, to))
+            .with_context(|| format!("failed to write {:?}", output_path))?;
+
+        warnings
+    };
+
+    println!(
+        "{} {} -> {} ({})",
+        if dry_run {
+            "Would convert"
+        } else {
+            "Converted"
+        },
+        path.to_string_lossy(),
+        output_path.to_string_lossy(),
+        to
+    );
+
+    for warning in &warnings {
+        println!("Not converted: {}", warning);
+    }
+
+    Ok(())
+}
+
+fn convert_stream(input: File, from: FileFormat, output: impl Write + Seek) -> Result<Vec<String>> {
+    Ok(match from {
+        FileFormat::Wav => convert::wav_to_aiff(input, output)?,
+        FileFormat::Aiff => convert::aiff_to_wav(input, output)?,
+    })
+}
+
+/// Check that a converted file can be read back.
+fn verify_output(file: &mut File, format: FileFormat) -> smplinfo::Result<()> {
+    match format {
+        FileFormat::Wav => Wav::new(file)?.frames().map(drop),
+        FileFormat::Aiff => Aiff::new(file)?.frames().map(drop),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_file_format() {
+        assert_eq!("AIFF".parse(), Ok(FileFormat::Aiff));
+        assert_eq!("wav".parse(), Ok(FileFormat::Wav));
+        assert!("flac".parse::<FileFormat>().is_err());
+        assert_eq!(
+            FileFormat::from_path(Path::new("kick.aifc")),
+            Some(FileFormat::Aiff)
+        );
+        assert_eq!(FileFormat::from_path(Path::new("kick")), None);
+    }
+}
@@ -1,8 +1,17 @@
-use std::{fmt::Write, str::FromStr};
+use std::{error, fmt, path::Path, str::FromStr};
 
-use once_cell::sync::Lazy;
-use regex::Regex;
-use smplinfo::midi::Note;
+use smplinfo::{
+    midi::Note,
+    tags::{TagKey, Tags},
+    wav::ListInfo,
+};
+
+const SHARP_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+const FLAT_NAMES: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B",
+];
 
 /// Format string for a sample filename.
 #[derive(Debug)]
@@ -14,26 +23,154 @@ pub struct FormatString {
 #[derive(Debug)]
 enum FormatPart {
     Literal(String),
-    MidiNote,
+    Field(Field, Modifiers),
+}
+
+/// A value that can be substituted into a format string.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Field {
+    Stem,
+    Extension,
+    Directory,
+    Sequence,
     Note,
+    MidiNote,
+    VelocityLayer,
+    RoundRobin,
+    SampleRate,
+    BitDepth,
+    Channels,
+    Duration,
+    Loop,
+    Tag([u8; 4]),
+}
+
+impl Field {
+    /// Names of the fields that can be given as `%{NAME}`, other than tags.
+    const NAMES: &'static [(&'static str, Field)] = &[
+        ("stem", Field::Stem),
+        ("ext", Field::Extension),
+        ("dir", Field::Directory),
+        ("seq", Field::Sequence),
+        ("note", Field::Note),
+        ("midi", Field::MidiNote),
+        ("layer", Field::VelocityLayer),
+        ("rr", Field::RoundRobin),
+        ("rate", Field::SampleRate),
+        ("bits", Field::BitDepth),
+        ("channels", Field::Channels),
+        ("duration", Field::Duration),
+        ("loop", Field::Loop),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(field_name, _)| *field_name == name)
+            .map(|(_, field)| *field)
+            .or_else(|| ListInfo::tag_id(name).map(Field::Tag))
+    }
+
+    fn is_number(self) -> bool {
+        matches!(
+            self,
+            Field::Sequence
+                | Field::MidiNote
+                | Field::VelocityLayer
+                | Field::RoundRobin
+                | Field::SampleRate
+                | Field::BitDepth
+                | Field::Channels
+                | Field::Duration
+        )
+    }
+}
+
+/// Options changing how a field is written, given after a colon as in
+/// `%{seq:3}` or `%{note:flat,c4}`.
+#[derive(Debug, Default)]
+struct Modifiers {
+    /// Minimum number of digits, padded with leading zeros.
+    width: Option<usize>,
+    flat: bool,
+    /// Number the octaves so that middle C is C4 rather than C3.
+    middle_c4: bool,
+    case: Option<Case>,
+    milliseconds: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+/// What is known about a file when working out its new name.
+#[derive(Debug)]
+pub struct Properties<'a> {
+    pub path: &'a Path,
+    pub tags: &'a Tags,
+    pub root_note: Option<Note>,
+    /// Position of the file among all the files being renamed, from 1.
+    pub sequence: usize,
+    /// Position of the velocity range among those of files with the same
+    /// root note, from 1 for the softest.
+    pub velocity_layer: Option<usize>,
+    /// Position among files with the same root note and velocity range, from
+    /// 1.
+    pub round_robin: Option<usize>,
+    pub sample_rate: Option<u32>,
+    pub bit_depth: Option<u16>,
+    pub channels: Option<u16>,
+    /// Duration in seconds.
+    pub duration: Option<f64>,
+    pub looped: bool,
+}
+
+impl<'a> Properties<'a> {
+    pub fn new(path: &'a Path, tags: &'a Tags) -> Self {
+        Self {
+            path,
+            tags,
+            root_note: None,
+            sequence: 1,
+            velocity_layer: None,
+            round_robin: None,
+            sample_rate: None,
+            bit_depth: None,
+            channels: None,
+            duration: None,
+            looped: false,
+        }
+    }
+
+    /// Name of the directory the file is in, even if the path doesn't
+    /// include it.
+    fn directory(&self) -> Option<String> {
+        let parent = match self.path.parent() {
+            Some(parent) if parent.file_name().is_some() => parent.to_owned(),
+            _ => self.path.canonicalize().ok()?.parent()?.to_owned(),
+        };
+
+        Some(parent.file_name()?.to_string_lossy().into_owned())
+    }
 }
 
 impl FormatString {
     /// Format a filename using the given properties.
-    pub fn format(&self, root_note: Option<Note>) -> String {
+    pub fn format(&self, properties: &Properties) -> String {
         let mut string = String::new();
 
         for part in self.parts.iter() {
             match part {
                 FormatPart::Literal(literal) => string.push_str(literal.as_str()),
-                FormatPart::MidiNote => {
-                    if let Some(note) = root_note {
-                        write!(string, "{:03}", u8::from(note)).unwrap();
-                    }
-                }
-                FormatPart::Note => {
-                    if let Some(note) = root_note {
-                        write!(string, "{}", note).unwrap();
+                FormatPart::Field(field, modifiers) => {
+                    if let Some(value) = field_value(*field, modifiers, properties) {
+                        // Don't let a value turn into a path.
+                        string.extend(value.chars().map(|c| match c {
+                            '/' | '\\' => '_',
+                            c => c,
+                        }));
                     }
                 }
             }
@@ -41,41 +178,195 @@ impl FormatString {
 
         string
     }
+
+    /// Whether the format string uses the velocity layer or round-robin
+    /// index, which need all the files to be read first.
+    pub fn uses_layers(&self) -> bool {
+        self.parts.iter().any(|part| {
+            matches!(
+                part,
+                FormatPart::Field(Field::VelocityLayer | Field::RoundRobin, _)
+            )
+        })
+    }
+}
+
+fn field_value(field: Field, modifiers: &Modifiers, properties: &Properties) -> Option<String> {
+    let path = properties.path;
+    let value = match field {
+        Field::Stem => path.file_stem()?.to_string_lossy().into_owned(),
+        Field::Extension => path.extension()?.to_string_lossy().into_owned(),
+        Field::Directory => properties.directory()?,
+        Field::Sequence => properties.sequence.to_string(),
+        Field::Note => {
+            let note = u8::from(properties.root_note?);
+            let names = if modifiers.flat {
+                &FLAT_NAMES
+            } else {
+                &SHARP_NAMES
+            };
+            let octave = i16::from(note / 12) - if modifiers.middle_c4 { 1 } else { 2 };
+
+            format!("{}{}", names[usize::from(note % 12)], octave)
+        }
+        Field::MidiNote => u8::from(properties.root_note?).to_string(),
+        Field::VelocityLayer => properties.velocity_layer?.to_string(),
+        Field::RoundRobin => properties.round_robin?.to_string(),
+        Field::SampleRate => properties.sample_rate?.to_string(),
+        Field::BitDepth => properties.bit_depth?.to_string(),
+        Field::Channels => properties.channels?.to_string(),
+        Field::Duration if modifiers.milliseconds => {
+            format!("{:.0}", properties.duration? * 1000.0)
+        }
+        Field::Duration => format!("{:.3}", properties.duration?),
+        Field::Loop => if properties.looped { "loop" } else { "oneshot" }.to_owned(),
+        Field::Tag(id) => properties.tags.get(&TagKey::Info(id))?.to_owned(),
+    };
+    let value = match modifiers.width {
+        Some(width) => format!("{:0>width$}", value, width = width),
+        None => value,
+    };
+
+    Some(match modifiers.case {
+        Some(Case::Upper) => value.to_uppercase(),
+        Some(Case::Lower) => value.to_lowercase(),
+        None => value,
+    })
+}
+
+/// An error parsing a format string.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    /// Position of the character the error is at, from 1.
+    pub position: usize,
+    pub message: String,
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl error::Error for ParseError {}
+
 impl FromStr for FormatString {
-    type Err = String;
+    type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        static REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"%.|[^%]+|%$").unwrap());
-
+        let error = |offset: usize, message: String| ParseError {
+            position: s[..offset].chars().count() + 1,
+            message,
+        };
         let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.char_indices();
 
-        for m in REGEX.find_iter(s) {
-            match m.as_str() {
-                ms @ "%%" => {
-                    parts.push(FormatPart::Literal(ms.to_owned()));
-                }
-                "%m" => {
-                    parts.push(FormatPart::MidiNote);
-                }
-                "%n" => {
-                    parts.push(FormatPart::Note);
+        while let Some((offset, c)) = chars.next() {
+            if c != '%' {
+                literal.push(c);
+                continue;
+            }
+
+            let field = match chars.next() {
+                Some((_, '%')) => {
+                    literal.push('%');
+                    continue;
                 }
-                ms => {
-                    if ms.starts_with("%") {
-                        panic!("invalid format specifier: {}", ms);
-                    } else {
-                        parts.push(FormatPart::Literal(ms.to_owned()));
-                    }
+                Some((_, 'm')) => FormatPart::Field(
+                    Field::MidiNote,
+                    Modifiers {
+                        width: Some(3),
+                        ..Modifiers::default()
+                    },
+                ),
+                Some((_, 'n')) => FormatPart::Field(Field::Note, Modifiers::default()),
+                Some((_, '{')) => {
+                    let start = offset + 2;
+                    let len = s[start..]
+                        .find('}')
+                        .ok_or_else(|| error(offset, "unterminated %{".to_owned()))?;
+
+                    // Skip to the closing brace.
+                    chars.nth(s[start..start + len].chars().count());
+
+                    parse_field(&s[start..start + len], start, &error)?
                 }
+                Some((_, c)) => return Err(error(offset, format!("unknown specifier %{}", c))),
+                None => return Err(error(offset, "incomplete specifier %".to_owned())),
+            };
+
+            if !literal.is_empty() {
+                parts.push(FormatPart::Literal(std::mem::take(&mut literal)));
             }
+
+            parts.push(field);
+        }
+
+        if !literal.is_empty() {
+            parts.push(FormatPart::Literal(literal));
         }
 
         Ok(Self { parts })
     }
 }
 
+/// Parse the contents of `%{...}`, which start at `offset` in the format
+/// string.
+fn parse_field(
+    spec: &str,
+    offset: usize,
+    error: &impl Fn(usize, String) -> ParseError,
+) -> Result<FormatPart, ParseError> {
+    let (name, modifier_list) = match spec.find(':') {
+        Some(i) => (&spec[..i], Some(i + 1)),
+        None => (spec, None),
+    };
+    let field = Field::from_name(name)
+        .ok_or_else(|| error(offset, format!("unknown field or tag {:?}", name)))?;
+    let mut modifiers = Modifiers::default();
+    let mut start = match modifier_list {
+        Some(start) => start,
+        None => return Ok(FormatPart::Field(field, modifiers)),
+    };
+
+    for modifier in spec[start..].split(',') {
+        let is_note = field == Field::Note;
+        let supported = match modifier {
+            "upper" => modifiers.case.replace(Case::Upper).is_none(),
+            "lower" => modifiers.case.replace(Case::Lower).is_none(),
+            "flat" | "sharp" if is_note => {
+                modifiers.flat = modifier == "flat";
+                true
+            }
+            "c3" | "c4" if is_note => {
+                modifiers.middle_c4 = modifier == "c4";
+                true
+            }
+            "ms" if field == Field::Duration => {
+                modifiers.milliseconds = true;
+                true
+            }
+            width if field.is_number() => match width.parse() {
+                Ok(width) => modifiers.width.replace(width).is_none(),
+                Err(_) => false,
+            },
+            _ => false,
+        };
+
+        if !supported {
+            return Err(error(
+                offset + start,
+                format!("invalid modifier {:?} for {}", modifier, name),
+            ));
+        }
+
+        start += modifier.len() + 1;
+    }
+
+    Ok(FormatPart::Field(field, modifiers))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -83,13 +374,72 @@ mod tests {
     #[test]
     fn format_produces_expected_string() {
         fn format(format_string: &str, root_note: Option<Note>) -> String {
+            let mut tags = Tags::default();
+            tags.set(TagKey::Info(*b"INAM"), "Kick");
+            tags.set(TagKey::Info(*b"IART"), "AC/DC");
+
+            let mut properties = Properties::new(Path::new("drums/kick 01.wav"), &tags);
+            properties.root_note = root_note;
+            properties.sequence = 7;
+            properties.velocity_layer = Some(2);
+            properties.sample_rate = Some(44100);
+            properties.bit_depth = Some(24);
+            properties.channels = Some(2);
+            properties.duration = Some(1.25);
+
             FormatString::from_str(format_string)
                 .unwrap()
-                .format(root_note)
+                .format(&properties)
         }
 
+        let c3 = Some(Note::from(60));
+        let a_sharp = Some(Note::from(70));
+
         assert_eq!(format("hello", None), "hello");
+        assert_eq!(format("100%%", None), "100%");
         assert_eq!(format("%n", None), "");
-        assert_eq!(format("%n", Some(Note::from(60))), "C3");
+        assert_eq!(format("%n", c3), "C3");
+        assert_eq!(format("%m", c3), "060");
+        assert_eq!(format("%{midi}", c3), "60");
+        assert_eq!(format("%{title}-%n", c3), "Kick-C3");
+        assert_eq!(format("%{IART}", None), "AC_DC");
+        assert_eq!(format("%{genre}", None), "");
+        assert_eq!(format("%{dir}_%{stem}.%{ext}", None), "drums_kick 01.wav");
+        assert_eq!(format("%{seq:3}-%{layer}-%{rr}", None), "007-2-");
+        assert_eq!(format("%{rate}_%{bits}_%{channels}", None), "44100_24_2");
+        assert_eq!(format("%{duration} %{duration:ms}", None), "1.250 1250");
+        assert_eq!(format("%{loop:upper}", None), "ONESHOT");
+        assert_eq!(format("%{note:flat,c4,lower}", a_sharp), "bb4");
+        assert_eq!(format("%{note:sharp}", a_sharp), "A#3");
+        assert_eq!(format("%{title:upper}", None), "KICK");
+    }
+
+    #[test]
+    fn invalid_format_is_an_error() {
+        fn error(format_string: &str) -> String {
+            FormatString::from_str(format_string)
+                .unwrap_err()
+                .to_string()
+        }
+
+        assert_eq!(
+            error("%{nope}"),
+            "unknown field or tag \"nope\" at position 3"
+        );
+        assert_eq!(error("ab%x"), "unknown specifier %x at position 3");
+        assert_eq!(error("é%"), "incomplete specifier % at position 2");
+        assert_eq!(error("%n %{seq"), "unterminated %{ at position 4");
+        assert_eq!(
+            error("%{note:flat,3}"),
+            "invalid modifier \"3\" for note at position 13"
+        );
+        assert_eq!(
+            error("%{seq:upper,lower}"),
+            "invalid modifier \"lower\" for seq at position 13"
+        );
+        assert_eq!(
+            error("%{stem:c4}"),
+            "invalid modifier \"c4\" for stem at position 8"
+        );
     }
 }
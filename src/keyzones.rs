@@ -0,0 +1,248 @@
+use std::{fmt, str::FromStr};
+
+use smplinfo::midi::Note;
+
+/// A range of notes a sample is mapped to, given as `LOW-HIGH` (e.g.
+/// `C1-E1`) or as a single note.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyRange {
+    pub low: Note,
+    pub high: Note,
+}
+
+impl FromStr for KeyRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(note) = s.parse::<Note>() {
+            return Ok(Self {
+                low: note,
+                high: note,
+            });
+        }
+
+        // Note names may contain a dash themselves for negative octaves (e.g.
+        // `C-1`), so try every dash as the separator.
+        for (i, _) in s.match_indices('-') {
+            if let (Ok(low), Ok(high)) = (s[..i].parse::<Note>(), s[i + 1..].parse::<Note>()) {
+                if low > high {
+                    return Err(format!(
+                        "invalid key range, {} is above {}: {}",
+                        low, high, s
+                    ));
+                }
+
+                return Ok(Self { low, high });
+            }
+        }
+
+        Err(format!("invalid key range: {}", s))
+    }
+}
+
+impl fmt::Display for KeyRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.low, self.high)
+    }
+}
+
+/// A range of velocities a sample is mapped to, given as `LOW-HIGH` (e.g.
+/// `0-63`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct VelocityRange {
+    pub low: u8,
+    pub high: u8,
+}
+
+impl FromStr for VelocityRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |s: &str| match s.trim().parse::<u8>() {
+            Ok(velocity) if velocity <= 127 => Ok(velocity),
+            _ => Err(format!("invalid velocity: {}", s)),
+        };
+
+        let (low, high) = s
+            .split_once('-')
+            .ok_or_else(|| format!("invalid velocity range, expected LOW-HIGH: {}", s))?;
+        let (low, high) = (parse(low)?, parse(high)?);
+
+        if low > high {
+            return Err(format!(
+                "invalid velocity range, {} is above {}: {}",
+                low, high, s
+            ));
+        }
+
+        Ok(Self { low, high })
+    }
+}
+
+impl fmt::Display for VelocityRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.low, self.high)
+    }
+}
+
+/// Compute contiguous key ranges covering the whole keyboard for a set of
+/// samples with the given root notes, like a sampler's auto-map.
+///
+/// Each sample gets the keys closest to its root note, with the lowest and
+/// highest samples extended to the ends of the keyboard. Samples sharing the
+/// same root note (such as velocity layers) get the same range. The returned
+/// ranges are in the same order as the given root notes.
+pub fn auto_keyzones(root_notes: &[Note]) -> Vec<KeyRange> {
+    let mut distinct = root_notes
+        .iter()
+        .map(|note| u8::from(*note))
+        .collect::<Vec<_>>();
+    distinct.sort_unstable();
+    distinct.dedup();
+
+    root_notes
+        .iter()
+        .map(|note| {
+            let root = u8::from(*note);
+            let i = distinct.binary_search(&root).unwrap();
+
+            let low = match i {
+                0 => 0,
+                i => {
+                    let below = distinct[i - 1];
+                    below + (root - below) / 2 + 1
+                }
+            };
+
+            let high = match distinct.get(i + 1) {
+                Some(&above) => root + (above - root) / 2,
+                None => 127,
+            };
+
+            KeyRange {
+                low: low.into(),
+                high: high.into(),
+            }
+        })
+        .collect()
+}
+
+/// Where a sample sits among the samples sharing its root note.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Layer {
+    /// Position of the sample's velocity range, from 1 for the softest.
+    pub velocity: usize,
+    /// Position among samples with the same velocity range, from 1.
+    pub round_robin: usize,
+}
+
+/// Number the velocity layers and round robins of a set of samples with the
+/// given root notes and velocity ranges.
+///
+/// Round robins are numbered in the order the samples are given. The returned
+/// layers are in the same order as the given samples.
+pub fn velocity_layers(samples: &[(Note, VelocityRange)]) -> Vec<Layer> {
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, (note, range))| {
+            let mut ranges = samples
+                .iter()
+                .filter(|(other, _)| other == note)
+                .map(|(_, range)| *range)
+                .collect::<Vec<_>>();
+            ranges.sort_unstable();
+            ranges.dedup();
+
+            Layer {
+                velocity: ranges.binary_search(range).unwrap() + 1,
+                round_robin: samples[..=i]
+                    .iter()
+                    .filter(|sample| sample == &&(*note, *range))
+                    .count(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(low: u8, high: u8) -> KeyRange {
+        KeyRange {
+            low: low.into(),
+            high: high.into(),
+        }
+    }
+
+    #[test]
+    fn parse_key_range() {
+        assert_eq!("C1-E1".parse(), Ok(range(36, 40)));
+        assert_eq!("C-1-E1".parse(), Ok(range(12, 40)));
+        assert_eq!("C-2-C-1".parse(), Ok(range(0, 12)));
+        assert_eq!("36-40".parse(), Ok(range(36, 40)));
+        assert_eq!("C3".parse(), Ok(range(60, 60)));
+        assert!("E1-C1".parse::<KeyRange>().is_err());
+        assert!("C1-X1".parse::<KeyRange>().is_err());
+    }
+
+    #[test]
+    fn parse_velocity_range() {
+        assert_eq!("0-63".parse(), Ok(VelocityRange { low: 0, high: 63 }));
+        assert!("64-0".parse::<VelocityRange>().is_err());
+        assert!("0-128".parse::<VelocityRange>().is_err());
+        assert!("63".parse::<VelocityRange>().is_err());
+    }
+
+    #[test]
+    fn auto_keyzones_are_contiguous() {
+        let notes = [60, 36, 48, 48, 40]
+            .iter()
+            .map(|n| Note::from(*n))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            auto_keyzones(&notes),
+            vec![
+                range(55, 127),
+                range(0, 38),
+                range(45, 54),
+                range(45, 54),
+                range(39, 44)
+            ]
+        );
+    }
+
+    #[test]
+    fn auto_keyzones_single_sample_covers_keyboard() {
+        assert_eq!(auto_keyzones(&[Note::from(60)]), vec![range(0, 127)]);
+        assert!(auto_keyzones(&[]).is_empty());
+    }
+
+    #[test]
+    fn velocity_layers_by_root_note() {
+        let sample = |note: u8, low: u8, high: u8| (Note::from(note), VelocityRange { low, high });
+        let layer = |velocity, round_robin| Layer {
+            velocity,
+            round_robin,
+        };
+
+        assert_eq!(
+            velocity_layers(&[
+                sample(60, 64, 127),
+                sample(60, 0, 63),
+                sample(62, 64, 127),
+                sample(60, 64, 127),
+                sample(60, 0, 63),
+            ]),
+            vec![
+                layer(2, 1),
+                layer(1, 1),
+                layer(1, 1),
+                layer(2, 2),
+                layer(1, 2)
+            ]
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+
+/// A position within a sample, either as an exact sample frame or as a time
+/// offset from the start.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Position {
+    Frames(u32),
+    Seconds(f64),
+}
+
+impl Position {
+    /// Resolve this position to a sample frame.
+    pub fn to_frames(self, sample_rate: Option<u32>) -> Result<u32> {
+        match self {
+            Position::Frames(frames) => Ok(frames),
+            Position::Seconds(seconds) => {
+                let sample_rate = sample_rate.ok_or_else(|| {
+                    anyhow!(
+                        "cannot use time position {} without a known sample rate",
+                        self
+                    )
+                })?;
+                let frames = (seconds * sample_rate as f64).round();
+
+                if frames > u32::MAX as f64 {
+                    bail!("position {} is too far into the sample", self);
+                }
+
+                Ok(frames as u32)
+            }
+        }
+    }
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (number, scale) = if let Some(number) = s.strip_suffix("ms") {
+            (number, 0.001)
+        } else if let Some(number) = s.strip_suffix('s') {
+            (number, 1.0)
+        } else {
+            return s
+                .strip_suffix('f')
+                .unwrap_or(s)
+                .parse()
+                .map(Position::Frames)
+                .map_err(|_| format!("invalid position: {}", s));
+        };
+
+        match number.parse::<f64>() {
+            Ok(value) if value.is_finite() && value >= 0.0 => Ok(Position::Seconds(value * scale)),
+            _ => Err(format!("invalid position: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Position::Frames(frames) => write!(f, "{}", frames),
+            Position::Seconds(seconds) => write!(f, "{}s", seconds),
+        }
+    }
+}
+
+/// A loop range given on the command line as `START..END`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LoopRange {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl FromStr for LoopRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once("..")
+            .ok_or_else(|| format!("invalid loop range, expected START..END: {}", s))?;
+
+        Ok(Self {
+            start: start.parse()?,
+            end: end.parse()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_position() {
+        assert_eq!("1000".parse(), Ok(Position::Frames(1000)));
+        assert_eq!("1000f".parse(), Ok(Position::Frames(1000)));
+        assert_eq!("1.25s".parse(), Ok(Position::Seconds(1.25)));
+        assert_eq!("500ms".parse(), Ok(Position::Seconds(0.5)));
+        assert!("-1s".parse::<Position>().is_err());
+        assert!("abc".parse::<Position>().is_err());
+    }
+
+    #[test]
+    fn position_to_frames() {
+        assert_eq!(Position::Frames(10).to_frames(None).unwrap(), 10);
+        assert_eq!(
+            Position::Seconds(1.25).to_frames(Some(48000)).unwrap(),
+            60000
+        );
+        assert!(Position::Seconds(1.0).to_frames(None).is_err());
+    }
+
+    #[test]
+    fn parse_loop_range() {
+        assert_eq!(
+            "100..500ms".parse(),
+            Ok(LoopRange {
+                start: Position::Frames(100),
+                end: Position::Seconds(0.5),
+            })
+        );
+        assert!("100-200".parse::<LoopRange>().is_err());
+    }
+}
@@ -1,50 +1,220 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use std::{
-    fs::{self, rename, OpenOptions},
+    env,
+    fs::{self, File},
+    io,
     path::{Path, PathBuf},
     str::FromStr,
 };
-use structopt::StructOpt;
+use structopt::{clap::AppSettings, StructOpt};
 use walkdir::WalkDir;
 
-use crate::format::FormatString;
-use smplinfo::{midi::Note, wav::Wav};
+use crate::{
+    apply::ApplyOptions,
+    check::CheckOptions,
+    chunks::ChunksOptions,
+    convert::ConvertOptions,
+    format::{FormatString, Properties},
+    keyzones::{auto_keyzones, velocity_layers, KeyRange, Layer, VelocityRange},
+    loops::{LoopRange, Position},
+    markers::{MarkerLabel, NewMarker},
+    output::{
+        yes_no, AcidInfo, BroadcastInfo, FormatInfo, InstrumentInfo, LoopInfo, MarkerInfo,
+        OutputFormat, Record, RecordWriter,
+    },
+    rename::{Rename, RenameArgs, UndoOptions},
+    strip::StripArgs,
+    tags::{TagAssignment, TagKey},
+};
+use smplinfo::{
+    atomic_file::AtomicFile,
+    midi::Note,
+    sample::Sample,
+    sample_file::SampleFile,
+    wav::{FormatChunk, LoopType, Marker, SampleLoop, SamplerChunk},
+    Error,
+};
 
+mod apply;
+mod check;
+mod chunks;
+mod convert;
 mod format;
+mod keyzones;
+mod loops;
+mod markers;
+mod output;
+mod rename;
+mod strip;
+mod tags;
 
 /// WAV sample data reader and writer.
 ///
+/// Use one of the commands below to show or change files. For compatibility,
+/// the options of the info, set, rename and strip commands can also be given
+/// without a command, such as `smplinfo --root-note C3 kick.wav`. A file
+/// named like a command is taken as the command, so give such files after
+/// the command and `--`, as in `smplinfo info -- set`, or as a path such as
+/// `./set`.
+///
 /// If no arguments are provided, the GUI will launch.
 #[derive(Debug, StructOpt)]
+#[structopt(setting = AppSettings::SubcommandsNegateReqs)]
 struct Options {
     /// Silence all command output
-    #[structopt(short, long)]
+    #[structopt(short, long, global = true)]
     quiet: bool,
 
     /// Verbose mode (-v, -vv, -vvv, etc)
-    #[structopt(short = "v", long, parse(from_occurrences))]
+    #[structopt(short = "v", long, parse(from_occurrences), global = true)]
     verbose: usize,
 
-    /// Read/write files in directories recursively
-    #[structopt(short, long)]
-    recursive: bool,
-
     /// Don't actually edit any files
-    #[structopt(short = "n", long)]
+    #[structopt(short = "n", long, global = true)]
     dry_run: bool,
 
-    /// Rename files using a format string
+    /// Keep a copy of each file before it is changed, named by appending
+    /// SUFFIX to the file name (e.g. `--backup .bak`)
+    ///
+    /// Changes are always written to a temporary file that replaces the
+    /// original once complete, so an interrupted edit never leaves a damaged
+    /// file behind.
+    #[structopt(long, value_name = "SUFFIX", global = true)]
+    backup: Option<String>,
+
+    #[structopt(flatten)]
+    edit: EditArgs,
+
+    /// Rename files using a format string, as with the rename command
+    #[structopt(long, value_name = "FORMAT")]
+    rename: Option<FormatString>,
+
+    #[structopt(flatten)]
+    rename_args: RenameArgs,
+
+    /// Rewrite files without chunks that upset some samplers, as with the
+    /// strip command
+    #[structopt(long)]
+    strip: bool,
+
+    #[structopt(flatten)]
+    strip_args: StripArgs,
+
+    #[structopt(flatten)]
+    files: FileArgs,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    Info(InfoOptions),
+    Set(SetOptions),
+    Rename(RenameOptions),
+    Strip(StripOptions),
+    Check(CheckOptions),
+    Convert(ConvertOptions),
+    Chunks(ChunksOptions),
+    Apply(ApplyOptions),
+    Undo(UndoOptions),
+}
+
+/// Show information about samples
+#[derive(Debug, StructOpt)]
+struct InfoOptions {
+    #[structopt(flatten)]
+    files: FileArgs,
+}
+
+/// Change the metadata of samples
+#[derive(Debug, StructOpt)]
+struct SetOptions {
+    #[structopt(flatten)]
+    edit: EditArgs,
+
+    #[structopt(flatten)]
+    files: FileArgs,
+}
+
+/// Rename samples using a format string
+#[derive(Debug, StructOpt)]
+struct RenameOptions {
+    /// Format string for the new file names
     ///
     /// The following format characters are supported:
     ///
-    /// - %m: MIDI note number of the sample root note
+    /// - %m: MIDI note number of the sample root note, padded to 3 digits
     /// - %n: Root note name
+    /// - %{KEY}: Value of a metadata tag, such as %{title} or %{IART}
+    /// - %{stem}, %{ext}: Original file name without extension, and extension
+    /// - %{dir}: Name of the directory the file is in
+    /// - %{seq}: Position of the file among the files given, from 1
+    /// - %{note}, %{midi}: Root note name and MIDI note number
+    /// - %{layer}: Velocity layer among files with the same root note, from 1
+    /// - %{rr}: Round robin among files with the same note and velocities
+    /// - %{rate}, %{bits}, %{channels}: Sample rate, bit depth and channels
+    /// - %{duration}: Duration in seconds
+    /// - %{loop}: "loop" if the sample has loops, otherwise "oneshot"
     /// - %%: Percent literal
-    #[structopt(long, verbatim_doc_comment)]
-    rename: Option<FormatString>,
+    ///
+    /// Modifiers can be given after a colon, separated by commas, such as
+    /// %{seq:3} or %{note:flat,c4}:
+    ///
+    /// - A number: Pad numbers with zeros to this many digits
+    /// - flat, sharp: Spell notes with flats or sharps (default)
+    /// - c3, c4: Number octaves with middle C as C3 (default) or C4
+    /// - upper, lower: Change the case of the value
+    /// - ms: Give the duration in milliseconds
+    #[structopt(verbatim_doc_comment)]
+    format: FormatString,
+
+    #[structopt(flatten)]
+    args: RenameArgs,
+
+    #[structopt(flatten)]
+    files: FileArgs,
+}
+
+/// Rewrite samples without chunks that upset some samplers
+///
+/// Unless --keep-chunk or --strip-chunk is given, LIST, id3, JUNK, PAD and
+/// FLLR chunks are removed. The fmt, data and ds64 chunks are always kept.
+#[derive(Debug, StructOpt)]
+struct StripOptions {
+    #[structopt(flatten)]
+    strip: StripArgs,
+
+    #[structopt(flatten)]
+    files: FileArgs,
+}
+
+// Files for a command to read or write.
+#[derive(Debug, StructOpt)]
+struct FileArgs {
+    /// Read/write files in directories recursively
+    #[structopt(short, long)]
+    recursive: bool,
+
+    /// How to print information about each file: text, json, ndjson, csv or
+    /// tsv
+    ///
+    /// The machine-readable formats write one record per file with every
+    /// field, including the changes made and any error. In CSV and TSV,
+    /// lists and tags are written as JSON. Defaults to text.
+    #[structopt(long, value_name = "FORMAT")]
+    output: Option<OutputFormat>,
+
+    /// Files and directories to read/write
+    #[structopt(required = true)]
+    paths: Vec<PathBuf>,
+}
 
+// Changes to make to the metadata of each file.
+#[derive(Debug, Default, StructOpt)]
+struct EditArgs {
     /// Set the root note
     #[structopt(long)]
     root_note: Option<Note>,
@@ -53,17 +223,171 @@ struct Options {
     #[structopt(long)]
     root_note_from_filename: bool,
 
-    /// Files and directories to read/write
-    paths: Vec<PathBuf>,
+    /// Set the start of the first loop
+    ///
+    /// Positions are given in sample frames (e.g. `44100`), or as a time
+    /// offset in seconds or milliseconds (e.g. `1.25s`, `500ms`).
+    #[structopt(long)]
+    loop_start: Option<Position>,
+
+    /// Set the end of the first loop (inclusive)
+    #[structopt(long)]
+    loop_end: Option<Position>,
+
+    /// Set the loop type: forward, alternating or backward
+    ///
+    /// Applies to the first loop and any loops added with --add-loop.
+    #[structopt(long)]
+    loop_type: Option<LoopType>,
+
+    /// Set how many times to play a loop, or 0 to loop forever
+    ///
+    /// Applies to the first loop and any loops added with --add-loop.
+    #[structopt(long)]
+    loop_count: Option<u32>,
+
+    /// Add a loop given as START..END, can be used multiple times
+    #[structopt(long, number_of_values = 1)]
+    add_loop: Vec<LoopRange>,
+
+    /// Remove all existing loops before applying any other loop options
+    #[structopt(long)]
+    clear_loops: bool,
+
+    /// Set the range of notes the sample is mapped to (e.g. C1-E1)
+    #[structopt(long)]
+    key_range: Option<KeyRange>,
+
+    /// Set the range of velocities the sample is mapped to (e.g. 0-63)
+    #[structopt(long)]
+    velocity_range: Option<VelocityRange>,
+
+    /// Map contiguous key ranges across all selected samples
+    ///
+    /// Samples are sorted by root note and each one is given the keys closest
+    /// to its root note, covering the whole keyboard. Samples sharing the same
+    /// root note get the same key range.
+    #[structopt(long, conflicts_with = "key-range")]
+    auto_keyzones: bool,
+
+    /// Add a marker given as POSITION[=LABEL], can be used multiple times
+    #[structopt(long, number_of_values = 1)]
+    add_marker: Vec<NewMarker>,
+
+    /// Rename a marker given as ID=LABEL, can be used multiple times
+    #[structopt(long, number_of_values = 1)]
+    rename_marker: Vec<MarkerLabel>,
+
+    /// Delete the marker with the given ID, can be used multiple times
+    #[structopt(long, number_of_values = 1)]
+    delete_marker: Vec<u32>,
+
+    /// Remove all existing markers before applying any other marker options
+    #[structopt(long)]
+    clear_markers: bool,
+
+    /// Set a metadata tag given as KEY=VALUE, can be used multiple times
+    ///
+    /// Keys are either INFO chunk IDs such as INAM, or one of the following
+    /// names: title, artist, comment, genre, copyright, software, date, album,
+    /// track, keywords, engineer, subject, source. FLAC files can also have
+    /// any other Vorbis comment field, such as REPLAYGAIN_TRACK_GAIN.
+    #[structopt(long, number_of_values = 1, verbatim_doc_comment)]
+    set_tag: Vec<TagAssignment>,
+
+    /// Remove a metadata tag, can be used multiple times
+    #[structopt(long, number_of_values = 1)]
+    remove_tag: Vec<TagKey>,
+
+    /// Set the ACID tempo of the sample in BPM
+    #[structopt(long, parse(try_from_str = parse_tempo))]
+    tempo: Option<f32>,
+
+    /// Set the ACID length of the sample in beats
+    #[structopt(long)]
+    beats: Option<u32>,
+
+    /// Mark the sample as a one-shot in the ACID chunk
+    #[structopt(long, conflicts_with = "no-one-shot")]
+    one_shot: bool,
+
+    /// Mark the sample as a loop in the ACID chunk
+    #[structopt(long)]
+    no_one_shot: bool,
+
+    /// Keep the ACID root note in sync with the sampler root note
+    ///
+    /// Only applies to files that already have an ACID chunk, or when other
+    /// ACID options are also given.
+    #[structopt(long)]
+    sync_acid_root_note: bool,
 }
 
-impl Options {
+impl EditArgs {
     fn write(&self) -> bool {
-        self.root_note.is_some() || self.root_note_from_filename
+        self.root_note.is_some()
+            || self.root_note_from_filename
+            || self.edit_loops()
+            || self.edit_instrument()
+            || self.edit_markers()
+            || self.edit_tags()
+            || self.edit_acid()
+            || self.sync_acid_root_note
+    }
+
+    fn edit_acid(&self) -> bool {
+        self.tempo.is_some() || self.beats.is_some() || self.one_shot || self.no_one_shot
+    }
+
+    fn edit_tags(&self) -> bool {
+        !self.set_tag.is_empty() || !self.remove_tag.is_empty()
+    }
+
+    fn edit_markers(&self) -> bool {
+        !self.add_marker.is_empty()
+            || !self.rename_marker.is_empty()
+            || !self.delete_marker.is_empty()
+            || self.clear_markers
+    }
+
+    fn edit_instrument(&self) -> bool {
+        self.key_range.is_some() || self.velocity_range.is_some() || self.auto_keyzones
+    }
+
+    fn edit_loops(&self) -> bool {
+        self.loop_start.is_some()
+            || self.loop_end.is_some()
+            || self.loop_type.is_some()
+            || self.loop_count.is_some()
+            || !self.add_loop.is_empty()
+            || self.clear_loops
+    }
+}
+
+/// What to do with each file, given either by a command or by the options
+/// given without one.
+struct Job<'a> {
+    edit: &'a EditArgs,
+    rename: Option<&'a FormatString>,
+    rename_args: &'a RenameArgs,
+    strip: Option<&'a StripArgs>,
+    dry_run: bool,
+    backup: Option<&'a str>,
+}
+
+impl Job<'_> {
+    fn write(&self) -> bool {
+        self.edit.write() || self.strip.is_some()
     }
 }
 
 pub fn main() -> Result<()> {
+    // Without any arguments there is nothing to do here, as the GUI launches
+    // instead.
+    if env::args_os().len() <= 1 {
+        return Ok(());
+    }
+
     let options = Options::from_args();
 
     stderrlog::new()
@@ -72,18 +396,228 @@ pub fn main() -> Result<()> {
         .init()
         .unwrap();
 
-    for path in &options.paths {
-        let metadata = fs::metadata(&path)?;
+    let dry_run = options.dry_run;
+    let backup = options.backup.as_deref();
+    let no_edit = EditArgs::default();
+    let no_rename = RenameArgs::default();
+    let job = |edit, rename, strip| Job {
+        edit,
+        rename,
+        rename_args: &no_rename,
+        strip,
+        dry_run,
+        backup,
+    };
+
+    match options.command.as_ref() {
+        Some(Command::Info(info)) => process_files(&job(&no_edit, None, None), &info.files),
+        Some(Command::Set(set)) => {
+            if !set.edit.write() {
+                bail!("no changes given, see `smplinfo set --help` for the options");
+            }
+
+            process_files(&job(&set.edit, None, None), &set.files)
+        }
+        Some(Command::Rename(rename)) => process_files(
+            &Job {
+                rename_args: &rename.args,
+                ..job(&no_edit, Some(&rename.format), None)
+            },
+            &rename.files,
+        ),
+        Some(Command::Strip(strip)) => {
+            process_files(&job(&no_edit, None, Some(&strip.strip)), &strip.files)
+        }
+        Some(Command::Check(check)) => check::check_files(check, dry_run, backup),
+        Some(Command::Convert(convert)) => convert::convert_files(convert, dry_run, backup),
+        Some(Command::Chunks(chunks)) => chunks::inspect_chunks(chunks, dry_run, backup),
+        Some(Command::Apply(apply)) => apply::apply_manifest(apply, dry_run, backup),
+        Some(Command::Undo(undo)) => rename::undo(undo, dry_run),
+        None => {
+            if !options.strip && !options.strip_args.is_empty() {
+                bail!(
+                    "--keep-chunk, --strip-chunk and --plain-format can only be used with --strip"
+                );
+            }
+
+            if options.rename.is_none() && !options.rename_args.is_empty() {
+                bail!("--on-conflict and --undo-log can only be used with --rename");
+            }
+
+            let strip = Some(&options.strip_args).filter(|_| options.strip);
+
+            process_files(
+                &Job {
+                    rename_args: &options.rename_args,
+                    ..job(&options.edit, options.rename.as_ref(), strip)
+                },
+                &options.files,
+            )
+        }
+    }
+}
+
+/// Show and edit each of the given files.
+fn process_files(job: &Job, args: &FileArgs) -> Result<()> {
+    let files = collect_files(args)?;
+
+    let key_ranges = if job.edit.auto_keyzones {
+        plan_keyzones(job.edit, &files)?
+    } else {
+        vec![None; files.len()]
+    };
+    let layers = match job.rename {
+        Some(format) if format.uses_layers() => plan_layers(job.edit, &files)?,
+        _ => vec![None; files.len()],
+    };
+
+    let mut output = RecordWriter::new(io::stdout(), args.output.unwrap_or(OutputFormat::Text))?;
+    let mut failed = 0;
+    // Files are renamed together once all of them have been processed, so
+    // their records are kept until then.
+    let mut records = Vec::new();
+    let mut renames = Vec::new();
+    // An error that stops processing is only returned once the files that
+    // were already processed have been renamed and written out.
+    let mut fatal = None;
+
+    for (i, ((path, key_range), layer)) in files.iter().zip(key_ranges).zip(layers).enumerate() {
+        let mut record = Record::new(path);
+        let plan = FilePlan {
+            key_range,
+            sequence: i + 1,
+            layer,
+        };
+
+        match process_file(job, path, &plan, &mut record) {
+            Ok(Some(new_path)) => renames.push((
+                records.len(),
+                Rename {
+                    from: path.clone(),
+                    to: new_path,
+                },
+            )),
+            Ok(None) => {}
+            Err(error) => {
+                record.error = Some(format!("{:#}", error));
+
+                match handle_file_error(path, error, &mut failed) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(error) => fatal = Some(error),
+                }
+            }
+        }
+
+        if job.rename.is_some() {
+            records.push(record);
+        } else {
+            output.write(&record)?;
+        }
+
+        if fatal.is_some() {
+            break;
+        }
+    }
+
+    let renamed = rename_files(job, &renames, &mut records);
+
+    for record in &records {
+        output.write(record)?;
+    }
+
+    output.finish()?;
+
+    if let Some(error) = fatal {
+        return Err(error);
+    }
+
+    renamed?;
+
+    if failed > 0 {
+        bail!("failed to process {} of {} files", failed, files.len());
+    }
+
+    Ok(())
+}
+
+/// Rename files as planned by `process_file`, noting each rename in the
+/// file's record.
+fn rename_files(job: &Job, renames: &[(usize, Rename)], records: &mut [Record]) -> Result<()> {
+    if renames.is_empty() {
+        return Ok(());
+    }
+
+    let new_paths = rename::rename_files(
+        &renames
+            .iter()
+            .map(|(_, rename)| rename.clone())
+            .collect::<Vec<_>>(),
+        job.rename_args,
+        job.dry_run,
+    )?;
+
+    for ((index, rename), new_path) in renames.iter().zip(new_paths) {
+        if let Some(new_path) = new_path {
+            records[*index].change(format!(
+                "{} file: {} -> {}",
+                if job.dry_run {
+                    "Would rename"
+                } else {
+                    "Renamed"
+                },
+                rename.from.file_name().unwrap().to_string_lossy(),
+                new_path.file_name().unwrap().to_string_lossy()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Decide whether to carry on with the next file after an error, based on
+/// the kind of error.
+///
+/// Files that aren't in a format that can be read are skipped, and files that
+/// are damaged or unsupported, or that the options given can't be applied to,
+/// are reported and counted in `failed`. I/O errors are returned to stop
+/// processing altogether, since they are likely to affect every file.
+///
+/// Returns whether the file was reported as failed rather than skipped.
+pub fn handle_file_error(path: &Path, error: anyhow::Error, failed: &mut usize) -> Result<bool> {
+    match error.downcast_ref::<Error>().map(Error::without_path) {
+        Some(Error::NotRecognized { .. }) => {
+            log::warn!("skipping {:?}: {:#}", path, error);
+
+            Ok(false)
+        }
+        Some(Error::Io(_)) => Err(error),
+        None if error.downcast_ref::<io::Error>().is_some() => Err(error),
+        _ => {
+            log::error!("{:?}: {:#}", path, error);
+            *failed += 1;
+
+            Ok(true)
+        }
+    }
+}
+
+/// Get the paths of all files selected by the given arguments.
+fn collect_files(args: &FileArgs) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for path in &args.paths {
+        let metadata = fs::metadata(path)?;
 
         if metadata.is_file() {
-            process_file(&options, &path)?;
+            files.push(path.clone());
         } else if metadata.is_dir() {
-            if options.recursive {
+            if args.recursive {
                 for entry in WalkDir::new(path) {
                     let entry = entry?;
 
                     if entry.file_type().is_file() {
-                        process_file(&options, entry.path())?;
+                        files.push(entry.into_path());
                     }
                 }
             } else {
@@ -92,73 +626,568 @@ pub fn main() -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(files)
 }
 
-fn process_file(options: &Options, path: &Path) -> Result<()> {
-    let file = OpenOptions::new()
-        .read(true)
-        .write(options.write() && !options.dry_run)
-        .open(path)?;
+/// What is worked out about a file from all the files being processed.
+struct FilePlan {
+    key_range: Option<KeyRange>,
+    /// Position of the file among all the files, from 1.
+    sequence: usize,
+    layer: Option<Layer>,
+}
 
-    let mut wav = Wav::new(file)?;
-    let mut current_root_note = None;
-    let mut new_root_note = options.root_note;
+/// Read a file to plan changes across all files, or `None` if it can't be
+/// read.
+fn read_for_plan(path: &Path) -> Result<Option<Sample>> {
+    match Sample::read(path) {
+        Ok(sample) => Ok(Some(sample)),
+        Err(e) if matches!(e.without_path(), Error::Io(_)) => Err(e.into()),
+        // Files that can't be read are reported when they are processed.
+        Err(_) => Ok(None),
+    }
+}
 
-    println!("Filename: {}", path.file_name().unwrap().to_string_lossy());
-    println!("Path: {}", path.to_string_lossy());
+/// Compute the key range of each file when automatically mapping keyzones
+/// across all of them.
+fn plan_keyzones(options: &EditArgs, files: &[PathBuf]) -> Result<Vec<Option<KeyRange>>> {
+    let mut root_notes = Vec::with_capacity(files.len());
 
-    if let Some(sampler) = wav.get_sampler_chunk()? {
-        let note = sampler.midi_unity_note();
+    for path in files {
+        let sample = read_for_plan(path)?;
+        let note = new_root_note(options, path)
+            .or_else(|| sample.as_ref().and_then(|sample| sample.note().copied()));
 
-        println!("Root note: {} (MIDI {})", note, u8::from(note));
+        if note.is_none() {
+            log::warn!(
+                "{:?} has no root note, skipping it for keyzone mapping",
+                path
+            );
+        }
 
-        current_root_note = Some(note);
+        root_notes.push(note);
     }
 
-    if options.root_note_from_filename {
-        let filename = path.file_name().unwrap().to_string_lossy();
-        let notes = find_notes_in_string(filename.as_ref()).collect::<Vec<_>>();
+    let mut key_ranges =
+        auto_keyzones(&root_notes.iter().flatten().copied().collect::<Vec<_>>()).into_iter();
 
-        if notes.len() == 1 {
-            new_root_note = Some(notes[0]);
+    Ok(root_notes
+        .into_iter()
+        .map(|note| note.and_then(|_| key_ranges.next()))
+        .collect())
+}
+
+/// Compute the velocity layer and round robin of each file with a root note,
+/// for renaming.
+fn plan_layers(options: &EditArgs, files: &[PathBuf]) -> Result<Vec<Option<Layer>>> {
+    let mut samples = Vec::with_capacity(files.len());
+
+    for path in files {
+        let sample = read_for_plan(path)?;
+        let note = new_root_note(options, path)
+            .or_else(|| sample.as_ref().and_then(|sample| sample.note().copied()));
+        let velocity_range = options
+            .velocity_range
+            .or_else(|| {
+                let range = sample.as_ref()?.velocity_range()?;
+
+                Some(VelocityRange {
+                    low: *range.start(),
+                    high: *range.end(),
+                })
+            })
+            .unwrap_or(VelocityRange { low: 0, high: 127 });
+
+        samples.push(note.map(|note| (note, velocity_range)));
+    }
+
+    let mut layers =
+        velocity_layers(&samples.iter().flatten().copied().collect::<Vec<_>>()).into_iter();
+
+    Ok(samples
+        .into_iter()
+        .map(|sample| sample.and_then(|_| layers.next()))
+        .collect())
+}
+
+/// Show and edit a file, filling in `record` with what is known about it and
+/// the changes made.
+///
+/// Returns the path the file should be renamed to, if any. Files aren't
+/// renamed here so that all the new names can be checked together.
+fn process_file(
+    job: &Job,
+    path: &Path,
+    plan: &FilePlan,
+    record: &mut Record,
+) -> Result<Option<PathBuf>> {
+    // Changes are made to a copy of the file, which replaces the original
+    // once all of them have been written.
+    let options = job.edit;
+    let mut edit = if job.write() && !job.dry_run {
+        let edit = AtomicFile::edit(path)?;
+
+        Some(match job.backup {
+            Some(suffix) => edit.with_backup_suffix(suffix),
+            None => edit,
+        })
+    } else {
+        None
+    };
+    let mut original;
+    let file = match edit.as_mut() {
+        Some(edit) => edit.file(),
+        None => {
+            original = File::open(path)?;
+            &mut original
         }
+    };
+
+    let mut sample = SampleFile::new(file)?;
+
+    if job.strip.is_some() && sample.as_wav().is_none() {
+        bail!("only WAV files can be stripped");
     }
 
+    let mut current_root_note = None;
+    let new_root_note = new_root_note(options, path);
+    let format = sample.get_format_chunk()?;
+    let sample_rate = format.as_ref().map(FormatChunk::sample_rate);
+    let frame_count = sample.frame_count()?;
+    let sampler = sample.get_sampler_chunk()?;
+    let mut looped = sampler
+        .as_ref()
+        .is_some_and(|sampler| !sampler.loops().is_empty());
+    let instrument = sample.get_instrument_chunk()?;
+    let markers = sample.get_markers()?;
+    let mut tags = sample.get_tags()?;
+
+    record.container = Some(sample.container());
+    record.format = format.as_ref().map(FormatInfo::from);
+    record.frames = frame_count;
+    record.duration = match (frame_count, sample_rate) {
+        (Some(frames), Some(rate)) if rate > 0 => Some(frames as f64 / rate as f64),
+        _ => None,
+    };
+
+    if let Some(sampler) = sampler.as_ref() {
+        record.set_sampler(sampler);
+        current_root_note = Some(sampler.midi_unity_note());
+    }
+
+    record.instrument = instrument.as_ref().map(InstrumentInfo::from);
+    record.markers = markers.iter().map(MarkerInfo::from).collect();
+    record.tags = tags
+        .iter()
+        .map(|(key, value)| (key.clone(), value.to_owned()))
+        .collect();
+    record.bwf = sample
+        .get_broadcast_extension_chunk()?
+        .as_ref()
+        .map(BroadcastInfo::from);
+
+    let acid = sample.get_acid_chunk()?;
+    record.acid = acid.as_ref().map(AcidInfo::from);
+
     if let Some(note) = new_root_note {
-        if options.dry_run {
-            println!("Would set root note to {}", note);
+        if job.dry_run {
+            record.change(format!("Would set root note to {}", note));
         } else {
-            wav.update_sampler_chunk(|chunk| {
+            sample.update_sampler_chunk(|chunk| {
                 chunk.set_midi_unity_note(note);
 
-                println!("Set root note to {}", note);
+                record.change(format!("Set root note to {}", note));
             })?;
         }
     }
 
-    if let Some(format) = options.rename.as_ref() {
-        let new_name = format.format(new_root_note.or(current_root_note));
+    if options.edit_loops() {
+        let mut sampler = sampler.unwrap_or_default();
+        edit_loops(options, &mut sampler, sample_rate)?;
+        looped = !sampler.loops().is_empty();
 
-        if new_name.as_str() != path.file_name().unwrap() {
-            if options.dry_run {
-                println!(
-                    "Would rename file: {} -> {}",
-                    path.file_name().unwrap().to_string_lossy(),
-                    new_name
-                );
-            } else {
-                drop(wav);
-                rename(path, path.with_file_name(new_name))?;
+        if job.dry_run {
+            if sampler.loops().is_empty() {
+                record.change("Would remove all loops");
+            }
+
+            for (i, sample_loop) in sampler.loops().iter().enumerate() {
+                record.change(format!(
+                    "Would set loop {} to {}",
+                    i + 1,
+                    describe_loop(sample_loop)
+                ));
+            }
+        } else {
+            sample.update_sampler_chunk(|chunk| {
+                *chunk.loops_mut() = sampler.loops().to_vec();
+
+                if chunk.loops().is_empty() {
+                    record.change("Removed all loops");
+                }
+
+                for (i, sample_loop) in chunk.loops().iter().enumerate() {
+                    record.change(format!(
+                        "Set loop {} to {}",
+                        i + 1,
+                        describe_loop(sample_loop)
+                    ));
+                }
+            })?;
+        }
+    }
+
+    let key_range = plan.key_range.or(options.key_range);
+
+    if key_range.is_some() || options.velocity_range.is_some() {
+        let root_note = new_root_note.or(current_root_note);
+
+        if job.dry_run {
+            if let Some(key_range) = key_range {
+                record.change(format!("Would set key range to {}", key_range));
+            }
+
+            if let Some(velocity_range) = options.velocity_range {
+                record.change(format!("Would set velocity range to {}", velocity_range));
+            }
+        } else {
+            sample.update_instrument_chunk(|chunk| {
+                if let Some(note) = root_note {
+                    chunk.set_unshifted_note(note);
+                }
+
+                if let Some(key_range) = key_range {
+                    chunk.set_low_note(key_range.low);
+                    chunk.set_high_note(key_range.high);
+
+                    record.change(format!("Set key range to {}", key_range));
+                }
+
+                if let Some(velocity_range) = options.velocity_range {
+                    chunk.set_low_velocity(velocity_range.low);
+                    chunk.set_high_velocity(velocity_range.high);
+
+                    record.change(format!("Set velocity range to {}", velocity_range));
+                }
+            })?;
+        }
+    }
+
+    if options.edit_markers() {
+        let mut markers = markers;
+        edit_markers(options, &mut markers, sample_rate)?;
+
+        if job.dry_run {
+            if markers.is_empty() {
+                record.change("Would remove all markers");
+            }
+
+            for marker in &markers {
+                record.change(format!(
+                    "Would set marker {} to {}",
+                    marker.id,
+                    describe_marker(marker)
+                ));
+            }
+        } else {
+            sample.update_markers(|existing| *existing = markers)?;
+
+            for marker in sample.get_markers()? {
+                record.change(format!(
+                    "Set marker {} to {}",
+                    marker.id,
+                    describe_marker(&marker)
+                ));
             }
         }
     }
 
-    println!();
+    if options.edit_tags() {
+        for tag in &options.set_tag {
+            tags.set(tag.key.clone(), &tag.value);
+        }
+
+        for key in &options.remove_tag {
+            tags.remove(key);
+        }
+
+        if job.dry_run {
+            for tag in &options.set_tag {
+                record.change(format!("Would set tag {} to {:?}", tag.key, tag.value));
+            }
+
+            for key in &options.remove_tag {
+                record.change(format!("Would remove tag {}", key));
+            }
+        } else {
+            sample.update_tags(|existing| *existing = tags.clone())?;
+
+            for tag in &options.set_tag {
+                record.change(format!("Set tag {} to {:?}", tag.key, tag.value));
+            }
+
+            for key in &options.remove_tag {
+                record.change(format!("Removed tag {}", key));
+            }
+        }
+    }
+
+    let sync_root_note = if options.sync_acid_root_note && (acid.is_some() || options.edit_acid()) {
+        new_root_note.or(current_root_note)
+    } else {
+        None
+    };
+
+    if options.edit_acid() || sync_root_note.is_some() {
+        let one_shot = match (options.one_shot, options.no_one_shot) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+
+        if job.dry_run {
+            if let Some(tempo) = options.tempo {
+                record.change(format!("Would set tempo to {} BPM", tempo));
+            }
+
+            if let Some(beats) = options.beats {
+                record.change(format!("Would set beats to {}", beats));
+            }
+
+            if let Some(one_shot) = one_shot {
+                record.change(format!("Would set one-shot to {}", yes_no(one_shot)));
+            }
+
+            if let Some(note) = sync_root_note {
+                record.change(format!("Would set ACID root note to {}", note));
+            }
+        } else {
+            sample.update_acid_chunk(|chunk| {
+                if let Some(tempo) = options.tempo {
+                    chunk.set_tempo(tempo);
+                    record.change(format!("Set tempo to {} BPM", tempo));
+                }
+
+                if let Some(beats) = options.beats {
+                    chunk.set_beats(beats);
+                    record.change(format!("Set beats to {}", beats));
+                }
+
+                if let Some(one_shot) = one_shot {
+                    chunk.set_one_shot(one_shot);
+                    record.change(format!("Set one-shot to {}", yes_no(one_shot)));
+                }
+
+                if let Some(note) = sync_root_note {
+                    chunk.set_root_note(Some(note));
+                    record.change(format!("Set ACID root note to {}", note));
+                }
+            })?;
+        }
+    }
+
+    let new_name = job
+        .rename
+        .map(|format_string| {
+            format_string.format(&Properties {
+                root_note: new_root_note.or(current_root_note),
+                sequence: plan.sequence,
+                velocity_layer: plan.layer.map(|layer| layer.velocity),
+                round_robin: plan.layer.map(|layer| layer.round_robin),
+                sample_rate,
+                bit_depth: format.as_ref().map(FormatChunk::valid_bits_per_sample),
+                channels: format.as_ref().map(FormatChunk::channels),
+                duration: record.duration,
+                looped,
+                ..Properties::new(path, &tags)
+            })
+        })
+        .filter(|new_name| new_name.as_str() != path.file_name().unwrap());
+
+    if let Some(new_name) = new_name.as_deref() {
+        if matches!(new_name, "" | "." | "..") {
+            bail!("rename format gives the invalid file name {:?}", new_name);
+        }
+    }
+
+    drop(sample);
+
+    match (edit, job.strip) {
+        // Stripping rewrites the file from the edited copy.
+        (Some(mut edit), Some(strip)) => {
+            strip::strip_file(
+                path,
+                edit.file(),
+                &strip.filter(),
+                strip.plain_format,
+                false,
+                job.backup,
+                &mut record.changes,
+            )?;
+        }
+        (Some(edit), None) => edit.commit(verify_sample)?,
+        (None, Some(strip)) => {
+            strip::strip_file(
+                path,
+                &mut File::open(path)?,
+                &strip.filter(),
+                strip.plain_format,
+                job.dry_run,
+                job.backup,
+                &mut record.changes,
+            )?;
+        }
+        (None, None) => {}
+    }
+
+    Ok(new_name.map(|new_name| path.with_file_name(new_name)))
+}
+
+/// Check that an edited file can be read back, including every chunk that
+/// can be edited.
+fn verify_sample(file: &mut File) -> smplinfo::Result<()> {
+    let mut sample = SampleFile::new(file)?;
+    sample.get_format_chunk()?;
+    sample.frame_count()?;
+    sample.get_sampler_chunk()?;
+    sample.get_instrument_chunk()?;
+    sample.get_markers()?;
+    sample.get_tags()?;
+    sample.get_acid_chunk()?;
 
     Ok(())
 }
 
+/// Get the root note to set for a file from the given options, if any.
+fn new_root_note(options: &EditArgs, path: &Path) -> Option<Note> {
+    if options.root_note_from_filename {
+        let filename = path.file_name().unwrap().to_string_lossy();
+        let notes = find_notes_in_string(filename.as_ref()).collect::<Vec<_>>();
+
+        if notes.len() == 1 {
+            return Some(notes[0]);
+        }
+    }
+
+    options.root_note
+}
+
+/// Apply the loop options to the loops in the given sampler chunk.
+fn edit_loops(
+    options: &EditArgs,
+    sampler: &mut SamplerChunk,
+    sample_rate: Option<u32>,
+) -> Result<()> {
+    let loops = sampler.loops_mut();
+
+    if options.clear_loops {
+        loops.clear();
+    }
+
+    let start = options
+        .loop_start
+        .map(|p| p.to_frames(sample_rate))
+        .transpose()?;
+    let end = options
+        .loop_end
+        .map(|p| p.to_frames(sample_rate))
+        .transpose()?;
+
+    if loops.is_empty() && (start.is_some() || end.is_some()) {
+        match (start, end) {
+            (Some(start), Some(end)) => loops.push(SampleLoop::new(start, end)),
+            _ => bail!("both --loop-start and --loop-end are required to create a new loop"),
+        }
+    }
+
+    if let Some(first) = loops.first_mut() {
+        first.start = start.unwrap_or(first.start);
+        first.end = end.unwrap_or(first.end);
+        first.loop_type = options.loop_type.unwrap_or(first.loop_type);
+        first.play_count = options.loop_count.unwrap_or(first.play_count);
+    }
+
+    for range in &options.add_loop {
+        loops.push(SampleLoop {
+            loop_type: options.loop_type.unwrap_or_default(),
+            play_count: options.loop_count.unwrap_or_default(),
+            ..SampleLoop::new(
+                range.start.to_frames(sample_rate)?,
+                range.end.to_frames(sample_rate)?,
+            )
+        });
+    }
+
+    for sample_loop in loops.iter() {
+        if sample_loop.end < sample_loop.start {
+            bail!(
+                "loop end {} is before loop start {}",
+                sample_loop.end,
+                sample_loop.start
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply the marker options to the given list of markers.
+fn edit_markers(
+    options: &EditArgs,
+    markers: &mut Vec<Marker>,
+    sample_rate: Option<u32>,
+) -> Result<()> {
+    if options.clear_markers {
+        markers.clear();
+    }
+
+    for id in &options.delete_marker {
+        if markers.iter().any(|marker| marker.id == *id) {
+            markers.retain(|marker| marker.id != *id);
+        } else {
+            log::warn!("no marker with ID {} to delete", id);
+        }
+    }
+
+    for rename in &options.rename_marker {
+        match markers.iter_mut().find(|marker| marker.id == rename.id) {
+            Some(marker) => marker.label = Some(rename.label.clone()),
+            None => log::warn!("no marker with ID {} to rename", rename.id),
+        }
+    }
+
+    for new_marker in &options.add_marker {
+        markers.push(Marker {
+            id: Marker::next_id(markers),
+            position: new_marker.position.to_frames(sample_rate)?,
+            label: new_marker.label.clone(),
+        });
+    }
+
+    markers.sort_by_key(|marker| marker.position);
+
+    Ok(())
+}
+
+fn describe_marker(marker: &Marker) -> String {
+    MarkerInfo::from(marker).to_string()
+}
+
+fn describe_format(format: &FormatChunk) -> String {
+    FormatInfo::from(format).to_string()
+}
+
+fn describe_loop(sample_loop: &SampleLoop) -> String {
+    LoopInfo::from(sample_loop).to_string()
+}
+
+fn parse_tempo(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(tempo) if tempo.is_finite() && tempo > 0.0 => Ok(tempo),
+        _ => Err(format!("invalid tempo: {}", s)),
+    }
+}
+
 fn find_notes_in_string(s: &str) -> impl Iterator<Item = Note> + '_ {
     static REGEX: Lazy<Regex> =
         Lazy::new(|| Regex::new(r"(?:^|[\-_.\s])([A-G]#?-?\d)(?:$|[\-_.\s])").unwrap());
@@ -167,3 +1196,77 @@ fn find_notes_in_string(s: &str) -> impl Iterator<Item = Note> + '_ {
         .captures_iter(s)
         .filter_map(|capture| Note::from_str(&capture[1]).ok())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Options {
+        Options::from_iter_safe(std::iter::once("smplinfo").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn options_without_command() {
+        let options = parse(&["-n", "--root-note", "C3", "--strip", "-r", "samples"]);
+
+        assert!(options.dry_run);
+        assert!(options.strip);
+        assert!(options.files.recursive);
+        assert_eq!(options.edit.root_note, Some(Note::from(60)));
+        assert_eq!(options.files.paths, vec![PathBuf::from("samples")]);
+        assert!(options.command.is_none());
+    }
+
+    #[test]
+    fn options_with_command() {
+        let options = parse(&["set", "--root-note", "C3", "kick.wav", "-n"]);
+
+        assert!(options.dry_run);
+        assert!(matches!(
+            options.command,
+            Some(Command::Set(SetOptions { ref edit, ref files }))
+                if edit.root_note == Some(Note::from(60)) && files.paths.len() == 1
+        ));
+
+        let options = parse(&["rename", "%n.wav", "kick.wav", "snare.wav"]);
+        assert!(matches!(
+            options.command,
+            Some(Command::Rename(RenameOptions { ref files, .. })) if files.paths.len() == 2
+        ));
+
+        let options = parse(&["rename", "--on-conflict", "skip", "%n.wav", "kick.wav"]);
+        assert!(matches!(
+            options.command,
+            Some(Command::Rename(RenameOptions { ref args, .. }))
+                if args.on_conflict == Some(rename::ConflictPolicy::Skip)
+        ));
+
+        let options = parse(&["check", "kick.wav"]);
+        assert!(options.files.paths.is_empty());
+
+        assert!(Options::from_iter_safe(&["smplinfo", "info"]).is_err());
+    }
+
+    #[test]
+    fn file_errors_by_kind() {
+        let path = Path::new("kick.wav");
+        let mut failed = 0;
+
+        let not_wav = Error::NotRecognized { format: "WAV" }.with_path(path);
+        assert!(!handle_file_error(path, not_wav.into(), &mut failed).unwrap());
+
+        let invalid = Error::MissingChunk { id: *b"fmt " }.with_path(path);
+        assert!(handle_file_error(path, invalid.into(), &mut failed).unwrap());
+
+        let options = anyhow::anyhow!("both --loop-start and --loop-end are required");
+        assert!(handle_file_error(path, options, &mut failed).unwrap());
+        assert_eq!(failed, 2);
+
+        let io = anyhow::Error::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(handle_file_error(path, io, &mut failed).is_err());
+
+        let io = Error::from(io::Error::from(io::ErrorKind::NotFound)).with_path(path);
+        assert!(handle_file_error(path, io.into(), &mut failed).is_err());
+        assert_eq!(failed, 2);
+    }
+}
@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+use crate::loops::Position;
+
+/// A new marker given on the command line as `POSITION[=LABEL]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewMarker {
+    pub position: Position,
+    pub label: Option<String>,
+}
+
+impl FromStr for NewMarker {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((position, label)) => Ok(Self {
+                position: position.parse()?,
+                label: Some(label.to_owned()),
+            }),
+            None => Ok(Self {
+                position: s.parse()?,
+                label: None,
+            }),
+        }
+    }
+}
+
+/// A new label for an existing marker given on the command line as
+/// `ID=LABEL`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarkerLabel {
+    pub id: u32,
+    pub label: String,
+}
+
+impl FromStr for MarkerLabel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (id, label) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid marker label, expected ID=LABEL: {}", s))?;
+
+        Ok(Self {
+            id: id
+                .parse()
+                .map_err(|_| format!("invalid marker ID: {}", id))?,
+            label: label.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_new_marker() {
+        assert_eq!(
+            "1.5s=Chorus".parse(),
+            Ok(NewMarker {
+                position: Position::Seconds(1.5),
+                label: Some("Chorus".into()),
+            })
+        );
+        assert_eq!(
+            "44100".parse(),
+            Ok(NewMarker {
+                position: Position::Frames(44100),
+                label: None,
+            })
+        );
+        assert!("soon=Chorus".parse::<NewMarker>().is_err());
+    }
+
+    #[test]
+    fn parse_marker_label() {
+        assert_eq!(
+            "2=Verse 2".parse(),
+            Ok(MarkerLabel {
+                id: 2,
+                label: "Verse 2".into(),
+            })
+        );
+        assert!("Verse".parse::<MarkerLabel>().is_err());
+        assert!("x=Verse".parse::<MarkerLabel>().is_err());
+    }
+}
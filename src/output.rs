@@ -0,0 +1,710 @@
+use serde::{Serialize, Serializer};
+use std::{
+    fmt,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::tags::TagKey;
+use smplinfo::{
+    midi::Note,
+    wav::{
+        AcidChunk, BroadcastExtensionChunk, FormatChunk, InstrumentChunk, Marker, SampleLoop,
+        SamplerChunk,
+    },
+};
+
+/// How to print the information about each file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+    Tsv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(format!("invalid output format: {}", s)),
+        }
+    }
+}
+
+/// Everything known about a file, along with the changes made to it.
+///
+/// Every field is always present in machine-readable output, as `null` or an
+/// empty list when the file doesn't have it.
+#[derive(Debug, Default, Serialize)]
+pub struct Record {
+    pub path: String,
+    pub filename: String,
+    pub container: Option<String>,
+    pub format: Option<FormatInfo>,
+    pub frames: Option<u64>,
+    /// Duration in seconds, if the sample rate is known.
+    pub duration: Option<f64>,
+    pub root_note: Option<NoteInfo>,
+    pub loops: Vec<LoopInfo>,
+    pub instrument: Option<InstrumentInfo>,
+    pub markers: Vec<MarkerInfo>,
+    #[serde(serialize_with = "serialize_tags")]
+    pub tags: Vec<(TagKey, String)>,
+    pub acid: Option<AcidInfo>,
+    pub bwf: Option<BroadcastInfo>,
+    /// Changes made to the file, or that would be made in a dry run.
+    pub changes: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl Record {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_string_lossy().into_owned(),
+            filename: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a description of a change made to the file.
+    pub fn change(&mut self, change: impl Into<String>) {
+        self.changes.push(change.into());
+    }
+
+    pub fn set_sampler(&mut self, sampler: &SamplerChunk) {
+        self.root_note = Some(sampler.midi_unity_note().into());
+        self.loops = sampler.loops().iter().map(LoopInfo::from).collect();
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormatInfo {
+    pub sample_format: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: u16,
+    pub channel_mask: Option<u32>,
+}
+
+impl From<&FormatChunk> for FormatInfo {
+    fn from(format: &FormatChunk) -> Self {
+        Self {
+            sample_format: format.sample_format().to_string(),
+            channels: format.channels(),
+            sample_rate: format.sample_rate(),
+            bits_per_sample: format.valid_bits_per_sample(),
+            channel_mask: format.channel_mask(),
+        }
+    }
+}
+
+impl fmt::Display for FormatInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, {}-bit, {} Hz, ",
+            self.sample_format, self.bits_per_sample, self.sample_rate
+        )?;
+
+        match self.channels {
+            1 => f.write_str("mono"),
+            2 => f.write_str("stereo"),
+            channels => write!(f, "{} channels", channels),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NoteInfo {
+    pub name: String,
+    pub midi: u8,
+}
+
+impl From<Note> for NoteInfo {
+    fn from(note: Note) -> Self {
+        Self {
+            name: note.to_string(),
+            midi: note.into(),
+        }
+    }
+}
+
+impl fmt::Display for NoteInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (MIDI {})", self.name, self.midi)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoopInfo {
+    pub start: u32,
+    pub end: u32,
+    #[serde(rename = "type")]
+    pub loop_type: String,
+    /// Number of times to play the loop, or 0 for infinite.
+    pub play_count: u32,
+}
+
+impl From<&SampleLoop> for LoopInfo {
+    fn from(sample_loop: &SampleLoop) -> Self {
+        Self {
+            start: sample_loop.start,
+            end: sample_loop.end,
+            loop_type: sample_loop.loop_type.to_string(),
+            play_count: sample_loop.play_count,
+        }
+    }
+}
+
+impl fmt::Display for LoopInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{} ({}, ", self.start, self.end, self.loop_type)?;
+
+        match self.play_count {
+            0 => f.write_str("infinite)"),
+            1 => f.write_str("1 time)"),
+            count => write!(f, "{} times)", count),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct InstrumentInfo {
+    pub unshifted_note: NoteInfo,
+    pub fine_tune: i8,
+    pub gain: i8,
+    pub low_note: NoteInfo,
+    pub high_note: NoteInfo,
+    pub low_velocity: u8,
+    pub high_velocity: u8,
+}
+
+impl From<&InstrumentChunk> for InstrumentInfo {
+    fn from(instrument: &InstrumentChunk) -> Self {
+        Self {
+            unshifted_note: instrument.unshifted_note().into(),
+            fine_tune: instrument.fine_tune(),
+            gain: instrument.gain(),
+            low_note: instrument.low_note().into(),
+            high_note: instrument.high_note().into(),
+            low_velocity: instrument.low_velocity(),
+            high_velocity: instrument.high_velocity(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MarkerInfo {
+    pub id: u32,
+    pub position: u32,
+    pub label: Option<String>,
+}
+
+impl From<&Marker> for MarkerInfo {
+    fn from(marker: &Marker) -> Self {
+        Self {
+            id: marker.id,
+            position: marker.position,
+            label: marker.label.clone(),
+        }
+    }
+}
+
+impl fmt::Display for MarkerInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.label.as_ref() {
+            Some(label) => write!(f, "{} ({:?})", self.position, label),
+            None => write!(f, "{}", self.position),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AcidInfo {
+    pub tempo: f32,
+    pub beats: u32,
+    pub meter: String,
+    pub one_shot: bool,
+    pub stretch: bool,
+    pub disk_based: bool,
+    pub root_note: Option<NoteInfo>,
+}
+
+impl From<&AcidChunk> for AcidInfo {
+    fn from(acid: &AcidChunk) -> Self {
+        let (numerator, denominator) = acid.meter();
+
+        Self {
+            tempo: acid.tempo(),
+            beats: acid.beats(),
+            meter: format!("{}/{}", numerator, denominator),
+            one_shot: acid.is_one_shot(),
+            stretch: acid.is_stretch(),
+            disk_based: acid.is_disk_based(),
+            root_note: acid.root_note().map(NoteInfo::from),
+        }
+    }
+}
+
+/// Fields of a broadcast extension (`bext`) chunk.
+#[derive(Debug, Serialize)]
+pub struct BroadcastInfo {
+    pub description: String,
+    pub originator: String,
+    pub originator_reference: String,
+    pub origination_date: String,
+    pub origination_time: String,
+    pub time_reference: u64,
+    pub version: u16,
+    /// The UMID as hex, without the trailing zeros of a basic UMID.
+    pub umid: Option<String>,
+    pub loudness_value: Option<f32>,
+    pub loudness_range: Option<f32>,
+    pub max_true_peak_level: Option<f32>,
+    pub max_momentary_loudness: Option<f32>,
+    pub max_short_term_loudness: Option<f32>,
+    pub coding_history: String,
+}
+
+impl From<&BroadcastExtensionChunk> for BroadcastInfo {
+    fn from(bext: &BroadcastExtensionChunk) -> Self {
+        let umid = bext.umid().map(|umid| {
+            // A basic UMID is only 32 bytes, with the rest left as zeros.
+            let len = if umid[32..].iter().all(|b| *b == 0) {
+                32
+            } else {
+                64
+            };
+
+            umid[..len]
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        });
+
+        Self {
+            description: bext.description(),
+            originator: bext.originator(),
+            originator_reference: bext.originator_reference(),
+            origination_date: bext.origination_date(),
+            origination_time: bext.origination_time(),
+            time_reference: bext.time_reference(),
+            version: bext.version(),
+            umid,
+            loudness_value: bext.loudness_value(),
+            loudness_range: bext.loudness_range(),
+            max_true_peak_level: bext.max_true_peak_level(),
+            max_momentary_loudness: bext.max_momentary_loudness(),
+            max_short_term_loudness: bext.max_short_term_loudness(),
+            coding_history: bext.coding_history(),
+        }
+    }
+}
+
+/// Tags are written as an object keyed by their four-character INFO ID, or by
+/// field name for Vorbis comment fields that have no INFO equivalent.
+fn serialize_tags<S: Serializer>(
+    tags: &[(TagKey, String)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(tags.iter().map(|(key, value)| (key.id(), value)))
+}
+
+/// Columns of CSV and TSV output, in order.
+///
+/// Lists and tags don't fit in a single column, so they are written as JSON.
+const COLUMNS: &[&str] = &[
+    "path",
+    "filename",
+    "container",
+    "sample_format",
+    "channels",
+    "sample_rate",
+    "bits_per_sample",
+    "channel_mask",
+    "frames",
+    "duration",
+    "root_note",
+    "root_note_midi",
+    "loops",
+    "low_note",
+    "high_note",
+    "low_velocity",
+    "high_velocity",
+    "markers",
+    "tags",
+    "tempo",
+    "beats",
+    "meter",
+    "one_shot",
+    "acid_root_note",
+    "bwf_description",
+    "bwf_originator",
+    "bwf_time_reference",
+    "changes",
+    "error",
+];
+
+/// Writes a record for each file in one of the output formats.
+pub struct RecordWriter<W> {
+    output: W,
+    format: OutputFormat,
+    count: usize,
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(mut output: W, format: OutputFormat) -> io::Result<Self> {
+        match format {
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                write_row(&mut output, format, COLUMNS.iter().copied())?;
+            }
+            OutputFormat::Json => output.write_all(b"[")?,
+            _ => {}
+        }
+
+        Ok(Self {
+            output,
+            format,
+            count: 0,
+        })
+    }
+
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Text => write_text(&mut self.output, record)?,
+            OutputFormat::Json => {
+                if self.count > 0 {
+                    self.output.write_all(b",")?;
+                }
+
+                self.output.write_all(b"\n  ")?;
+                serde_json::to_writer(&mut self.output, record)?;
+            }
+            OutputFormat::Ndjson => {
+                serde_json::to_writer(&mut self.output, record)?;
+                self.output.write_all(b"\n")?;
+            }
+            OutputFormat::Csv | OutputFormat::Tsv => {
+                let row = columns(record);
+                write_row(
+                    &mut self.output,
+                    self.format,
+                    row.iter().map(String::as_str),
+                )?;
+            }
+        }
+
+        self.count += 1;
+        self.output.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.format == OutputFormat::Json {
+            if self.count > 0 {
+                self.output.write_all(b"\n")?;
+            }
+
+            self.output.write_all(b"]\n")?;
+        }
+
+        self.output.flush()
+    }
+}
+
+fn write_text(output: &mut impl Write, record: &Record) -> io::Result<()> {
+    writeln!(output, "Filename: {}", record.filename)?;
+    writeln!(output, "Path: {}", record.path)?;
+
+    match record.container.as_deref() {
+        Some("RIFF") | None => {}
+        Some(container) => writeln!(output, "Container: {}", container)?,
+    }
+
+    if let Some(format) = record.format.as_ref() {
+        writeln!(output, "Format: {}", format)?;
+
+        if let Some(mask) = format.channel_mask {
+            writeln!(output, "Channel mask: 0x{:x}", mask)?;
+        }
+    }
+
+    match (record.frames, record.duration) {
+        (Some(frames), Some(duration)) => {
+            writeln!(output, "Duration: {:.3}s ({} frames)", duration, frames)?
+        }
+        (Some(frames), None) => writeln!(output, "Duration: {} frames", frames)?,
+        _ => {}
+    }
+
+    if let Some(note) = record.root_note.as_ref() {
+        writeln!(output, "Root note: {}", note)?;
+    }
+
+    for (i, sample_loop) in record.loops.iter().enumerate() {
+        writeln!(output, "Loop {}: {}", i + 1, sample_loop)?;
+    }
+
+    if let Some(instrument) = record.instrument.as_ref() {
+        writeln!(
+            output,
+            "Key range: {}-{}",
+            instrument.low_note.name, instrument.high_note.name
+        )?;
+        writeln!(
+            output,
+            "Velocity range: {}-{}",
+            instrument.low_velocity, instrument.high_velocity
+        )?;
+    }
+
+    for marker in &record.markers {
+        writeln!(output, "Marker {}: {}", marker.id, marker)?;
+    }
+
+    for (key, value) in &record.tags {
+        writeln!(output, "Tag {}: {}", key, value)?;
+    }
+
+    if let Some(bwf) = record.bwf.as_ref() {
+        write_broadcast_text(output, bwf)?;
+    }
+
+    if let Some(acid) = record.acid.as_ref() {
+        writeln!(output, "Tempo: {} BPM", acid.tempo)?;
+        writeln!(output, "Beats: {}", acid.beats)?;
+        writeln!(output, "Meter: {}", acid.meter)?;
+        writeln!(output, "One-shot: {}", yes_no(acid.one_shot))?;
+        writeln!(output, "Stretch: {}", yes_no(acid.stretch))?;
+
+        if let Some(note) = acid.root_note.as_ref() {
+            writeln!(output, "ACID root note: {}", note)?;
+        }
+    }
+
+    for change in &record.changes {
+        writeln!(output, "{}", change)?;
+    }
+
+    writeln!(output)
+}
+
+fn write_broadcast_text(output: &mut impl Write, bwf: &BroadcastInfo) -> io::Result<()> {
+    let text_fields = [
+        ("Description", &bwf.description),
+        ("Originator", &bwf.originator),
+        ("Originator reference", &bwf.originator_reference),
+        ("Origination date", &bwf.origination_date),
+        ("Origination time", &bwf.origination_time),
+    ];
+
+    for (name, value) in text_fields.iter() {
+        if !value.is_empty() {
+            writeln!(output, "BWF {}: {}", name, value)?;
+        }
+    }
+
+    writeln!(output, "BWF Time reference: {}", bwf.time_reference)?;
+    writeln!(output, "BWF Version: {}", bwf.version)?;
+
+    if let Some(umid) = bwf.umid.as_ref() {
+        writeln!(output, "BWF UMID: {}", umid)?;
+    }
+
+    let loudness_fields = [
+        ("Loudness value", bwf.loudness_value, "LUFS"),
+        ("Loudness range", bwf.loudness_range, "LU"),
+        ("Max true peak level", bwf.max_true_peak_level, "dBTP"),
+        ("Max momentary loudness", bwf.max_momentary_loudness, "LUFS"),
+        (
+            "Max short-term loudness",
+            bwf.max_short_term_loudness,
+            "LUFS",
+        ),
+    ];
+
+    for (name, value, unit) in loudness_fields.iter() {
+        if let Some(value) = value {
+            writeln!(output, "BWF {}: {:.2} {}", name, value, unit)?;
+        }
+    }
+
+    for line in bwf.coding_history.lines() {
+        writeln!(output, "BWF Coding history: {}", line)?;
+    }
+
+    Ok(())
+}
+
+pub fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Get the values of each of the [`COLUMNS`] for a record.
+fn columns(record: &Record) -> Vec<String> {
+    fn opt<T: ToString>(value: Option<T>) -> String {
+        value.map(|value| value.to_string()).unwrap_or_default()
+    }
+
+    fn json<T: Serialize + ?Sized>(value: &T) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    let format = record.format.as_ref();
+    let instrument = record.instrument.as_ref();
+    let acid = record.acid.as_ref();
+    let bwf = record.bwf.as_ref();
+
+    vec![
+        record.path.clone(),
+        record.filename.clone(),
+        opt(record.container.as_ref()),
+        opt(format.map(|f| &f.sample_format)),
+        opt(format.map(|f| f.channels)),
+        opt(format.map(|f| f.sample_rate)),
+        opt(format.map(|f| f.bits_per_sample)),
+        opt(format.and_then(|f| f.channel_mask)),
+        opt(record.frames),
+        opt(record.duration),
+        opt(record.root_note.as_ref().map(|note| &note.name)),
+        opt(record.root_note.as_ref().map(|note| note.midi)),
+        json(&record.loops),
+        opt(instrument.map(|i| &i.low_note.name)),
+        opt(instrument.map(|i| &i.high_note.name)),
+        opt(instrument.map(|i| i.low_velocity)),
+        opt(instrument.map(|i| i.high_velocity)),
+        json(&record.markers),
+        tags_json(&record.tags),
+        opt(acid.map(|a| a.tempo)),
+        opt(acid.map(|a| a.beats)),
+        opt(acid.map(|a| &a.meter)),
+        opt(acid.map(|a| a.one_shot)),
+        opt(acid
+            .and_then(|a| a.root_note.as_ref())
+            .map(|note| &note.name)),
+        opt(bwf.map(|b| &b.description)),
+        opt(bwf.map(|b| &b.originator)),
+        opt(bwf.map(|b| b.time_reference)),
+        json(&record.changes),
+        opt(record.error.as_ref()),
+    ]
+}
+
+fn tags_json(tags: &[(TagKey, String)]) -> String {
+    let mut bytes = Vec::new();
+    serialize_tags(tags, &mut serde_json::Serializer::new(&mut bytes)).unwrap();
+    String::from_utf8(bytes).unwrap()
+}
+
+/// Write a row of CSV or TSV.
+///
+/// CSV fields are quoted as in RFC 4180 where needed. TSV can't quote, so
+/// tabs, newlines and backslashes in fields are escaped with a backslash.
+fn write_row<'a>(
+    output: &mut impl Write,
+    format: OutputFormat,
+    fields: impl Iterator<Item = &'a str>,
+) -> io::Result<()> {
+    let separator = if format == OutputFormat::Tsv {
+        "\t"
+    } else {
+        ","
+    };
+
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            output.write_all(separator.as_bytes())?;
+        }
+
+        if format == OutputFormat::Tsv {
+            let field = field
+                .replace('\\', "\\\\")
+                .replace('\t', "\\t")
+                .replace('\n', "\\n")
+                .replace('\r', "\\r");
+            output.write_all(field.as_bytes())?;
+        } else if field.contains(&[',', '"', '\n', '\r'][..]) {
+            write!(output, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            output.write_all(field.as_bytes())?;
+        }
+    }
+
+    output.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> Record {
+        Record {
+            root_note: Some(NoteInfo::from(Note::from(60))),
+            tags: vec![(TagKey::Info(*b"INAM"), "Kick, \"hard\"\tone".to_owned())],
+            changes: vec!["Set root note to C3".to_owned()],
+            ..Record::new(Path::new("samples/kick.wav"))
+        }
+    }
+
+    fn write(format: OutputFormat) -> String {
+        let mut output = Vec::new();
+        let mut writer = RecordWriter::new(&mut output, format).unwrap();
+        writer.write(&record()).unwrap();
+        writer.finish().unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn json_output() {
+        let records: serde_json::Value = serde_json::from_str(&write(OutputFormat::Json)).unwrap();
+        let record = &records[0];
+
+        assert_eq!(records.as_array().unwrap().len(), 1);
+        assert_eq!(record["filename"], "kick.wav");
+        assert_eq!(record["root_note"]["midi"], 60);
+        assert_eq!(record["tags"]["INAM"], "Kick, \"hard\"\tone");
+        assert!(record["format"].is_null());
+        assert!(record["loops"].as_array().unwrap().is_empty());
+
+        let line = write(OutputFormat::Ndjson);
+        assert_eq!(line.lines().count(), 1);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&line).unwrap(),
+            *record
+        );
+    }
+
+    #[test]
+    fn delimited_output() {
+        let csv = write(OutputFormat::Csv);
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].starts_with("samples/kick.wav,kick.wav,,"));
+        assert!(lines[1].contains(",C3,60,[],"));
+        assert!(lines[1].contains(r#","{""INAM"":""Kick, \""hard\""\tone""}","#));
+
+        let tsv = write(OutputFormat::Tsv);
+        let lines = tsv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].split('\t').count(), COLUMNS.len());
+        assert!(lines[1].contains(r#"{"INAM":"Kick, \\"hard\\"\\tone"}"#));
+    }
+}
@@ -0,0 +1,526 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+use structopt::StructOpt;
+
+/// Undo log used when none is given.
+const UNDO_LOG: &str = ".smplinfo-undo.json";
+
+/// Undo the most recent batch of renames
+#[derive(Debug, StructOpt)]
+pub struct UndoOptions {
+    /// Undo log to read the renames from, as written by the rename command
+    /// [default: .smplinfo-undo.json]
+    #[structopt(long, value_name = "FILE")]
+    undo_log: Option<PathBuf>,
+}
+
+// Options for renaming files.
+#[derive(Debug, Default, StructOpt)]
+pub struct RenameArgs {
+    /// What to do when a new name is already taken by another file: fail,
+    /// suffix or skip [default: fail]
+    ///
+    /// fail renames nothing, suffix adds _2, _3 and so on before the
+    /// extension, and skip leaves the file with its current name.
+    #[structopt(long, value_name = "POLICY")]
+    pub on_conflict: Option<ConflictPolicy>,
+
+    /// File to record renames in so that they can be undone with the undo
+    /// command [default: .smplinfo-undo.json]
+    #[structopt(long, value_name = "FILE")]
+    pub undo_log: Option<PathBuf>,
+}
+
+impl RenameArgs {
+    /// Whether any of the options were given.
+    pub fn is_empty(&self) -> bool {
+        self.on_conflict.is_none() && self.undo_log.is_none()
+    }
+}
+
+/// What to do when a file would be renamed to a name that is already taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Fail,
+    Suffix,
+    Skip,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(ConflictPolicy::Fail),
+            "suffix" => Ok(ConflictPolicy::Suffix),
+            "skip" => Ok(ConflictPolicy::Skip),
+            _ => Err(format!(
+                "invalid conflict policy, expected fail, suffix or skip: {}",
+                s
+            )),
+        }
+    }
+}
+
+/// A file to be moved from one path to another.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Renames made by past runs, most recent last, so that they can be undone.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+struct UndoLog {
+    batches: Vec<Vec<Rename>>,
+}
+
+impl UndoLog {
+    fn read(path: &Path) -> Result<Self> {
+        match fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("failed to read undo log {:?}", path)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("failed to read undo log {:?}", path)),
+        }
+    }
+
+    /// Write the log, removing the file once there is nothing left to undo.
+    fn write(&self, path: &Path) -> Result<()> {
+        if self.batches.is_empty() {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("failed to write undo log {:?}", path))
+    }
+}
+
+/// Rename files together, resolving names that are already taken according
+/// to `args` and recording the renames in the undo log.
+///
+/// Returns the new path of each file, or `None` if it was skipped. Either
+/// every file is renamed or none are.
+pub fn rename_files(
+    renames: &[Rename],
+    args: &RenameArgs,
+    dry_run: bool,
+) -> Result<Vec<Option<PathBuf>>> {
+    let renames = renames
+        .iter()
+        .map(|rename| Rename {
+            from: absolute(&rename.from),
+            to: absolute(&rename.to),
+        })
+        .collect::<Vec<_>>();
+    let targets = resolve(
+        &renames,
+        args.on_conflict.unwrap_or(ConflictPolicy::Fail),
+        |path| path.symlink_metadata().is_ok(),
+    )?
+    .into_iter()
+    .zip(&renames)
+    .map(|(target, rename)| target.filter(|target| *target != rename.from))
+    .collect::<Vec<_>>();
+
+    let batch = renames
+        .iter()
+        .zip(&targets)
+        .filter_map(|(rename, target)| {
+            Some(Rename {
+                from: rename.from.clone(),
+                to: target.clone()?,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if !dry_run && !batch.is_empty() {
+        let log_path = args
+            .undo_log
+            .as_deref()
+            .unwrap_or_else(|| Path::new(UNDO_LOG));
+        let mut log = UndoLog::read(log_path)?;
+
+        execute(&batch)?;
+
+        log.batches.push(batch);
+        log.write(log_path)
+            .context("files were renamed, but can't be undone")?;
+    }
+
+    Ok(targets)
+}
+
+/// Undo the most recent batch of renames in the undo log.
+pub fn undo(options: &UndoOptions, dry_run: bool) -> Result<()> {
+    let log_path = options
+        .undo_log
+        .as_deref()
+        .unwrap_or_else(|| Path::new(UNDO_LOG));
+    let mut log = UndoLog::read(log_path)?;
+    let batch = log
+        .batches
+        .pop()
+        .ok_or_else(|| anyhow!("nothing to undo in {:?}", log_path))?;
+    let renames = batch
+        .iter()
+        .rev()
+        .map(|rename| Rename {
+            from: rename.to.clone(),
+            to: rename.from.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    for rename in &renames {
+        if rename.from.symlink_metadata().is_err() {
+            bail!("can't undo renames, {:?} no longer exists", rename.from);
+        }
+    }
+
+    resolve(&renames, ConflictPolicy::Fail, |path| {
+        path.symlink_metadata().is_ok()
+    })
+    .context("can't undo renames")?;
+
+    if !dry_run {
+        execute(&renames)?;
+        log.write(log_path)?;
+    }
+
+    for rename in &renames {
+        println!(
+            "{} {} -> {}",
+            if dry_run { "Would rename" } else { "Renamed" },
+            rename.from.display(),
+            rename.to.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Work out where each file will be renamed to, or `None` if it is skipped,
+/// given whether a path is taken by a file.
+///
+/// A new name can't be used by more than one file, or by a file that isn't
+/// itself being renamed. Files that swap names, or rename onto each other in
+/// a chain, are fine.
+fn resolve(
+    renames: &[Rename],
+    policy: ConflictPolicy,
+    exists: impl Fn(&Path) -> bool,
+) -> Result<Vec<Option<PathBuf>>> {
+    let mut targets = renames
+        .iter()
+        .map(|rename| Some(rename.to.clone()))
+        .collect::<Vec<_>>();
+
+    // Skipping a file keeps its name taken, which can cause another conflict,
+    // so go round until nothing else is skipped.
+    loop {
+        let moving = renames
+            .iter()
+            .zip(&targets)
+            .filter(|(rename, target)| target.as_ref() != Some(&rename.from) && target.is_some())
+            .map(|(rename, _)| rename.from.as_path())
+            .collect::<HashSet<_>>();
+        let taken = |path: &Path, claimed: &HashMap<PathBuf, usize>| {
+            claimed.contains_key(path) || (exists(path) && !moving.contains(path))
+        };
+        let mut claimed = HashMap::new();
+        let mut conflicts = Vec::new();
+        let mut skipped = false;
+
+        for (i, rename) in renames.iter().enumerate() {
+            let target = match &targets[i] {
+                Some(target) if *target == rename.from => continue,
+                Some(target) => target.clone(),
+                None => continue,
+            };
+
+            if !taken(&target, &claimed) {
+                claimed.insert(target, i);
+                continue;
+            }
+
+            match policy {
+                ConflictPolicy::Fail => conflicts.push(match claimed.get(&target) {
+                    Some(&other) => format!(
+                        "{:?} and {:?} would both be renamed to {:?}",
+                        renames[other].from, rename.from, target
+                    ),
+                    None => format!(
+                        "{:?} would be renamed to {:?}, which already exists",
+                        rename.from, target
+                    ),
+                }),
+                ConflictPolicy::Suffix => {
+                    let suffixed = (2..)
+                        .map(|n| with_suffix(&target, n))
+                        .find(|path| !claimed.contains_key(path) && !exists(path))
+                        .unwrap();
+
+                    log::warn!(
+                        "{:?} is taken, renaming {:?} to {:?}",
+                        target,
+                        rename.from,
+                        suffixed
+                    );
+                    claimed.insert(suffixed.clone(), i);
+                    targets[i] = Some(suffixed);
+                }
+                ConflictPolicy::Skip => {
+                    log::warn!("{:?} is taken, not renaming {:?}", target, rename.from);
+                    targets[i] = None;
+                    skipped = true;
+                }
+            }
+        }
+
+        if !conflicts.is_empty() {
+            bail!(
+                "not renaming any files, new names conflict:\n  {}",
+                conflicts.join("\n  ")
+            );
+        }
+
+        if !skipped {
+            return Ok(targets);
+        }
+    }
+}
+
+/// Rename every file, putting back any that were already renamed if one of
+/// them fails.
+///
+/// Files are first moved to temporary names, so that files can swap names.
+fn execute(renames: &[Rename]) -> Result<()> {
+    let mut done = Vec::new();
+    let result = execute_steps(renames, &mut done);
+
+    if result.is_err() {
+        for (from, to) in done.iter().rev() {
+            if let Err(e) = fs::rename(to, from) {
+                log::error!("failed to move {:?} back to {:?}: {}", to, from, e);
+            }
+        }
+    }
+
+    result
+}
+
+/// Make each rename, adding each move made to `done`.
+fn execute_steps(renames: &[Rename], done: &mut Vec<(PathBuf, PathBuf)>) -> Result<()> {
+    let temps = renames
+        .iter()
+        .map(|rename| temp_path(&rename.from))
+        .collect::<Vec<_>>();
+
+    for (rename, temp) in renames.iter().zip(&temps) {
+        fs::rename(&rename.from, temp)
+            .with_context(|| format!("failed to rename {:?}", rename.from))?;
+        done.push((rename.from.clone(), temp.clone()));
+    }
+
+    for (rename, temp) in renames.iter().zip(&temps) {
+        // Something else may have taken the name since it was checked.
+        if rename.to.symlink_metadata().is_ok() {
+            bail!(
+                "failed to rename {:?}, {:?} already exists",
+                rename.from,
+                rename.to
+            );
+        }
+
+        fs::rename(temp, &rename.to)
+            .with_context(|| format!("failed to rename {:?} to {:?}", rename.from, rename.to))?;
+        done.push((temp.clone(), rename.to.clone()));
+    }
+
+    Ok(())
+}
+
+/// Get a path for a file to be moved to while files are being renamed.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut temp_name = OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(format!(".{}.rename", process::id()));
+
+    path.with_file_name(temp_name)
+}
+
+/// Add `_N` to the end of the file name, before the extension.
+fn with_suffix(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.file_stem().unwrap_or_default().to_owned();
+    name.push(format!("_{}", n));
+
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+
+    path.with_file_name(name)
+}
+
+/// Make a path absolute, resolving links in the directory so that different
+/// paths to the same file are the same.
+fn absolute(path: &Path) -> PathBuf {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    match (parent.canonicalize(), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => env::current_dir().unwrap_or_default().join(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renames(pairs: &[(&str, &str)]) -> Vec<Rename> {
+        pairs
+            .iter()
+            .map(|(from, to)| Rename {
+                from: PathBuf::from(from),
+                to: PathBuf::from(to),
+            })
+            .collect()
+    }
+
+    fn resolve_names(
+        pairs: &[(&str, &str)],
+        existing: &[&str],
+        policy: ConflictPolicy,
+    ) -> Result<Vec<Option<String>>> {
+        let existing = existing.iter().map(PathBuf::from).collect::<Vec<_>>();
+        let targets = resolve(&renames(pairs), policy, |path| {
+            existing.iter().any(|existing| existing == path)
+        })?;
+
+        Ok(targets
+            .into_iter()
+            .map(|target| target.map(|path| path.to_string_lossy().into_owned()))
+            .collect())
+    }
+
+    #[test]
+    fn resolve_conflicts() {
+        let pairs = [("a.wav", "x.wav"), ("b.wav", "x.wav"), ("c.wav", "d.wav")];
+        let existing = ["a.wav", "b.wav", "c.wav", "d.wav", "x_2.wav"];
+        let some = |name: &str| Some(name.to_owned());
+
+        let error = resolve_names(&pairs, &existing, ConflictPolicy::Fail).unwrap_err();
+        assert!(error.to_string().contains("\"a.wav\" and \"b.wav\""));
+        assert!(error
+            .to_string()
+            .contains("\"d.wav\", which already exists"));
+
+        assert_eq!(
+            resolve_names(&pairs, &existing, ConflictPolicy::Suffix).unwrap(),
+            vec![some("x.wav"), some("x_3.wav"), some("d_2.wav")]
+        );
+        assert_eq!(
+            resolve_names(&pairs, &existing, ConflictPolicy::Skip).unwrap(),
+            vec![some("x.wav"), None, None]
+        );
+    }
+
+    #[test]
+    fn resolve_cycles_and_chains() {
+        let some = |name: &str| Some(name.to_owned());
+
+        assert_eq!(
+            resolve_names(
+                &[("a.wav", "b.wav"), ("b.wav", "a.wav")],
+                &["a.wav", "b.wav"],
+                ConflictPolicy::Fail
+            )
+            .unwrap(),
+            vec![some("b.wav"), some("a.wav")]
+        );
+
+        // Once c.wav is skipped, b.wav can't be renamed to it either.
+        assert_eq!(
+            resolve_names(
+                &[("a.wav", "b.wav"), ("b.wav", "c.wav"), ("c.wav", "d.wav")],
+                &["a.wav", "b.wav", "c.wav", "d.wav"],
+                ConflictPolicy::Skip
+            )
+            .unwrap(),
+            vec![None, None, None]
+        );
+    }
+
+    #[test]
+    fn execute_and_undo() {
+        let dir = env::temp_dir().join(format!("smplinfo-rename-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.wav"), "a").unwrap();
+        fs::write(dir.join("b.wav"), "b").unwrap();
+
+        let log = dir.join("undo.json");
+        let args = RenameArgs {
+            on_conflict: None,
+            undo_log: Some(log.clone()),
+        };
+        let swap = [
+            Rename {
+                from: dir.join("a.wav"),
+                to: dir.join("b.wav"),
+            },
+            Rename {
+                from: dir.join("b.wav"),
+                to: dir.join("a.wav"),
+            },
+        ];
+
+        rename_files(&swap, &args, false).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.wav")).unwrap(), "b");
+        assert_eq!(fs::read_to_string(dir.join("b.wav")).unwrap(), "a");
+
+        // A failed rename puts back the files that were already moved.
+        let missing = [
+            Rename {
+                from: dir.join("a.wav"),
+                to: dir.join("c.wav"),
+            },
+            Rename {
+                from: dir.join("missing.wav"),
+                to: dir.join("d.wav"),
+            },
+        ];
+        assert!(execute(&missing).is_err());
+        assert_eq!(fs::read_to_string(dir.join("a.wav")).unwrap(), "b");
+
+        undo(
+            &UndoOptions {
+                undo_log: Some(log.clone()),
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.wav")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("b.wav")).unwrap(), "b");
+        assert!(!log.exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use std::{
+    fs::File,
+    io::{BufWriter, Cursor, Write},
+    path::Path,
+};
+use structopt::StructOpt;
+
+use crate::chunks::{describe_chunk, ChunkId};
+use smplinfo::{
+    atomic_file::AtomicFile,
+    wav::{ChunkInfo, Wav},
+};
+
+/// Chunks removed by `--strip` when no chunks are given, which are known to
+/// upset some older samplers.
+const DEFAULT_REMOVED: &[ChunkId] = &[
+    ChunkId::Chunk(*b"LIST"),
+    ChunkId::Chunk(*b"id3 "),
+    ChunkId::Chunk(*b"ID3 "),
+    ChunkId::Chunk(*b"JUNK"),
+    ChunkId::Chunk(*b"PAD "),
+    ChunkId::Chunk(*b"FLLR"),
+];
+
+// Options for stripping chunks from files.
+#[derive(Debug, Default, StructOpt)]
+pub struct StripArgs {
+    /// Only keep the given chunk when stripping, can be used multiple times
+    ///
+    /// Chunks are given by their ID, such as smpl, or as LIST:TYPE for a LIST
+    /// chunk with the given list type, such as LIST:adtl.
+    #[structopt(long, number_of_values = 1, value_name = "CHUNK")]
+    pub keep_chunk: Vec<ChunkId>,
+
+    /// Remove the given chunk when stripping, can be used multiple times
+    #[structopt(long, number_of_values = 1, value_name = "CHUNK")]
+    pub strip_chunk: Vec<ChunkId>,
+
+    /// Replace an extensible format with plain PCM or float when stripping,
+    /// if nothing would be lost
+    #[structopt(long)]
+    pub plain_format: bool,
+}
+
+impl StripArgs {
+    /// Whether any of the options were given.
+    pub fn is_empty(&self) -> bool {
+        self.keep_chunk.is_empty() && self.strip_chunk.is_empty() && !self.plain_format
+    }
+
+    pub fn filter(&self) -> ChunkFilter {
+        ChunkFilter {
+            keep: self.keep_chunk.clone(),
+            remove: self.strip_chunk.clone(),
+        }
+    }
+}
+
+/// Which chunks to keep when stripping a file.
+#[derive(Clone, Debug, Default)]
+pub struct ChunkFilter {
+    /// If not empty, only these chunks are kept.
+    pub keep: Vec<ChunkId>,
+    pub remove: Vec<ChunkId>,
+}
+
+impl ChunkFilter {
+    pub fn keeps(&self, info: &ChunkInfo) -> bool {
+        let remove = match (self.keep.is_empty(), self.remove.is_empty()) {
+            (true, true) => DEFAULT_REMOVED,
+            _ => &self.remove,
+        };
+
+        (self.keep.is_empty() || self.keep.iter().any(|chunk| chunk.matches(info)))
+            && !remove.iter().any(|chunk| chunk.matches(info))
+    }
+}
+
+/// Rewrite a file with only the chunks kept by the filter.
+///
+/// `input` is read from in place of the file at `path`, so that other edits
+/// made to a copy of the file are kept. A description of each change is added
+/// to `changes`.
+pub fn strip_file(
+    path: &Path,
+    input: &mut File,
+    filter: &ChunkFilter,
+    plain_format: bool,
+    dry_run: bool,
+    backup_suffix: Option<&str>,
+    changes: &mut Vec<String>,
+) -> Result<()> {
+    let mut wav = Wav::new(input)?;
+    let plain = if plain_format {
+        wav.get_format_chunk()?.and_then(|format| format.to_plain())
+    } else {
+        None
+    };
+    let keep = |info: &ChunkInfo| filter.keeps(info);
+
+    let removed = if dry_run {
+        wav.write_stripped(Cursor::new(Vec::new()), keep, plain_format)?
+    } else {
+        let mut output =
+            AtomicFile::rewrite(path).with_context(|| format!("failed to create {:?}", path))?;
+
+        if let Some(suffix) = backup_suffix {
+            output = output.with_backup_suffix(suffix);
+        }
+
+        let mut writer = BufWriter::new(output.file());
+        let removed = wav
+            .write_stripped(&mut writer, keep, plain_format)
+            .and_then(|removed| {
+                writer.flush()?;
+                Ok(removed)
+            })
+            .with_context(|| format!("failed to strip {:?}", path))?;
+        drop(writer);
+
+        output
+            .commit(crate::verify_sample)
+            .with_context(|| format!("failed to write {:?}", path))?;
+
+        removed
+    };
+
+    let prefix = if dry_run { "Would remove" } else { "Removed" };
+
+    for info in &removed {
+        changes.push(format!("{} {} chunk", prefix, describe_chunk(info)));
+    }
+
+    if let Some(format) = plain {
+        changes.push(format!(
+            "{} format to {}",
+            if dry_run { "Would set" } else { "Set" },
+            crate::describe_format(&format)
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(id: &[u8; 4], list_type: Option<[u8; 4]>) -> ChunkInfo {
+        ChunkInfo {
+            id: *id,
+            list_type,
+            parent: None,
+            offset: 12,
+            len: 4,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn chunk_filter() {
+        let filter = ChunkFilter::default();
+        assert!(filter.keeps(&info(b"smpl", None)));
+        assert!(!filter.keeps(&info(b"LIST", Some(*b"INFO"))));
+        assert!(!filter.keeps(&info(b"JUNK", None)));
+
+        let filter = ChunkFilter {
+            keep: vec![ChunkId::Chunk(*b"smpl"), ChunkId::List(*b"adtl")],
+            remove: vec![],
+        };
+        assert!(filter.keeps(&info(b"smpl", None)));
+        assert!(filter.keeps(&info(b"LIST", Some(*b"adtl"))));
+        assert!(!filter.keeps(&info(b"LIST", Some(*b"INFO"))));
+        assert!(!filter.keeps(&info(b"inst", None)));
+
+        let filter = ChunkFilter {
+            keep: vec![],
+            remove: vec![ChunkId::List(*b"INFO")],
+        };
+        assert!(filter.keeps(&info(b"JUNK", None)));
+        assert!(filter.keeps(&info(b"LIST", Some(*b"adtl"))));
+        assert!(!filter.keeps(&info(b"LIST", Some(*b"INFO"))));
+    }
+}
@@ -0,0 +1,57 @@
+use std::str::FromStr;
+
+/// A metadata tag key given on the command line, either as a four-character
+/// INFO ID (e.g. `INAM`), a friendly name (e.g. `title`) or the name of a
+/// FLAC Vorbis comment field (e.g. `REPLAYGAIN_TRACK_GAIN`).
+pub use smplinfo::tags::TagKey;
+
+/// A tag value given on the command line as `KEY=VALUE`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagAssignment {
+    pub key: TagKey,
+    pub value: String,
+}
+
+impl FromStr for TagAssignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("invalid tag, expected KEY=VALUE: {}", s))?;
+
+        Ok(Self {
+            key: key.parse()?,
+            value: value.to_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tag_assignment() {
+        assert_eq!(
+            "title=Kick 01".parse(),
+            Ok(TagAssignment {
+                key: TagKey::Info(*b"INAM"),
+                value: "Kick 01".into(),
+            })
+        );
+        assert_eq!(
+            "ISFT=".parse(),
+            Ok(TagAssignment {
+                key: TagKey::Info(*b"ISFT"),
+                value: "".into(),
+            })
+        );
+        assert!("title".parse::<TagAssignment>().is_err());
+        assert_eq!(
+            "mood=calm".parse::<TagAssignment>().map(|tag| tag.key),
+            Ok(TagKey::Info(*b"MOOD"))
+        );
+        assert!("=1".parse::<TagAssignment>().is_err());
+    }
+}